@@ -4,7 +4,7 @@
 //! - Forwards JSON-RPC requests to the Bun Docs API at `https://bun.com/docs/mcp`
 //! - Parses Server-Sent Events (SSE) responses from the API
 //! - Implements automatic retry logic with exponential backoff for transient failures
-//! - Provides testability via `with_base_url()` constructor for mock servers
+//! - Can be pointed at a mirror or mock server via [`ClientConfig`] or `with_base_url()`
 //!
 //! ## Example
 //!
@@ -30,39 +30,130 @@
 //! depending on the content-type header. When parsing SSE streams:
 //! - Only "message" and "completion" event types are processed
 //! - Heartbeat and other event types are ignored
-//! - **Important**: This implementation expects a complete JSON-RPC object in a single
-//!   SSE event. If the server streams partial deltas across multiple events, this
-//!   implementation will not accumulate them. Adjust `parse_sse_response()` if the
-//!   protocol changes to delta streaming.
+//! - Multi-line `data:` fields are joined, and JSON split across several events is
+//!   accumulated until it is complete
+//! - Progress and log notifications sent before the response are relayed to the MCP
+//!   client when the caller passes a [`NotificationRelay`]; other notifications, server
+//!   requests, and responses to other request IDs are skipped
+//! - A stream that breaks after an event with an ID is resumed with a `GET` carrying
+//!   `Last-Event-ID`; if the server cannot resume it, the whole request is retried
+//! - A stream that ends without the response is an error describing what arrived
+//!
+//! See [`sse`] for the details.
+//!
+//! ## Body Size Limits
+//!
+//! Every response body is read with a size limit ([`ClientConfig::max_response_size`]
+//! for API responses and SSE streams, [`ClientConfig::max_page_size`] for pages),
+//! counting bytes as they stream in; a larger body fails the request with
+//! [`body::ResponseTooLarge`] instead of growing memory. See [`body`].
+//!
+//! ## Page URL Policy
+//!
+//! Documentation page URLs come from upstream search results, so they are checked
+//! against a [`PagePolicy`] before they are fetched: only allowlisted hosts over
+//! HTTPS, only public addresses after DNS resolution, and the same for every
+//! redirect. Pages on the upstream's own origin are always allowed. See
+//! [`page_policy`].
+//!
+//! ## Upstream Session
+//!
+//! If the API assigns an `Mcp-Session-Id` header, it is sent back on every later
+//! request, and [`BunDocsClient::end_session`] terminates the session with an HTTP
+//! `DELETE` when the proxy shuts down. While the session lasts, its `GET` message
+//! stream is kept open for server-initiated notifications such as
+//! `notifications/tools/list_changed`, see [`listen`].
+//!
+//! ## Response Cache
+//!
+//! Successful `tools/call` responses and fetched pages are cached in memory (LRU with
+//! a TTL, see [`cache`]); a cached response is re-stamped with the ID of the request
+//! it answers. With [`ClientConfig::disk_cache`] they are also persisted on disk (see
+//! [`disk_cache`]), which is consulted when the memory cache misses, so a new process
+//! starts warm.
+//!
+//! Pages keep the `ETag` and `Last-Modified` [`Validators`] they were served with.
+//! Once a cached page expires it is revalidated with `If-None-Match` and
+//! `If-Modified-Since`, and a `304 Not Modified` answer keeps the cached body.
+//!
+//! ## Request Coalescing
+//!
+//! Identical requests in flight at the same time (differing only in their JSON-RPC
+//! `id` and `params._meta`) share one upstream call, see [`single_flight`]. Each
+//! caller gets the response re-stamped with its own ID, and the progress and log
+//! notifications of the shared call, with progress re-stamped with its own token.
+//! Requests that ask for progress are only coalesced with others that do.
+//!
+//! ## Circuit Breaker
+//!
+//! API calls and page fetches run through a [`CircuitBreaker`] shared by all clones
+//! of the client. After several consecutive calls fail even when retried, it opens
+//! and calls fail at once (serving cached answers where there are any) until a
+//! cool-down has passed and a probe call succeeds, see [`circuit`].
+//! [`BunDocsClient::circuit_status`] reports its state.
+//!
+//! ## Degraded Mode
+//!
+//! When a request fails and an expired answer is cached, that answer is returned
+//! instead of the error, marked stale in its content. From then on expired answers
+//! are served immediately while a background request refreshes them; the first
+//! successful request ends degraded mode. [`ClientConfig::offline`] serves only
+//! cached answers and never contacts the API.
 //!
 //! ## Retry Strategy
 //!
-//! Transient failures (network errors, 429, 5xx status codes) are retried up to
-//! [`MAX_RETRIES`] times with exponential backoff (200 ms → 400 ms → 800 ms, capped at 1 s).
+//! Every outbound request (`POST` to the API, markdown page fetches and the session
+//! `DELETE`) runs through a [`RetryPolicy`]: transient failures (network errors, 429,
+//! 5xx status codes) are retried with jittered exponential backoff, honoring
+//! `Retry-After`. See [`retry`] for the details. [`ClientConfig`] sets the default
+//! policy, and the `*_with_retry` methods override it for a single call.
 
 use anyhow::{Context as _, Result};
-use bytes::Bytes;
-use eventsource_stream::Eventsource as _;
-use futures::StreamExt as _;
 use reqwest::{Client, StatusCode, Url, header::HeaderMap};
 use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 use tracing::{debug, info, warn};
 
-/// Base URL for the Bun documentation API
-const BUN_DOCS_API: &str = "https://bun.com/docs/mcp";
-
-/// HTTP request timeout in seconds
-const REQUEST_TIMEOUT_SECS: u64 = 5_u64;
-
-/// Maximum number of retry attempts for transient failures
-const MAX_RETRIES: usize = 3_usize;
-
-/// Base delay for exponential backoff (milliseconds)
-const BACKOFF_BASE_MS: u64 = 200_u64;
-
-/// Maximum backoff delay (milliseconds)
-const BACKOFF_MAX_MS: u64 = 1000_u64;
+pub mod body;
+pub mod cache;
+pub mod circuit;
+pub mod disk_cache;
+pub mod listen;
+pub mod page_policy;
+pub mod retry;
+pub mod single_flight;
+pub mod sse;
+
+pub use body::{DEFAULT_MAX_PAGE_SIZE, DEFAULT_MAX_RESPONSE_SIZE};
+pub use cache::{
+    CacheConfig, CacheStats, DEFAULT_CACHE_ENTRIES, DEFAULT_CACHE_MAX_BYTES,
+    DEFAULT_CACHE_TTL_SECS, ResponseCache,
+};
+pub use circuit::{
+    CircuitBreaker, CircuitConfig, CircuitStatus, DEFAULT_CIRCUIT_COOLDOWN_SECS,
+    DEFAULT_CIRCUIT_THRESHOLD,
+};
+pub use disk_cache::{
+    DEFAULT_DISK_CACHE_MAX_BYTES, DEFAULT_DISK_CACHE_TTL_SECS, DiskCache, DiskCacheConfig,
+    DiskEntry, Validators,
+};
+pub use page_policy::{DEFAULT_PAGE_HOST, PagePolicy, PageRejected};
+pub use retry::{
+    BACKOFF_BASE_MS, BACKOFF_MAX_MS, Failure, MAX_RETRIES, RETRY_AFTER_MAX_SECS, RetryPolicy,
+};
+pub use single_flight::SingleFlight;
+pub use sse::NotificationRelay;
+
+/// Default base URL for the Bun documentation API
+pub const BUN_DOCS_API: &str = "https://bun.com/docs/mcp";
+
+/// Default HTTP request timeout in seconds
+pub const REQUEST_TIMEOUT_SECS: u64 = 5_u64;
 
 /// Maximum error response body size to read (100KB, prevents OOM from malicious/misconfigured servers)
 const MAX_ERROR_BODY_SIZE: usize = 100_000_usize;
@@ -70,12 +161,193 @@ const MAX_ERROR_BODY_SIZE: usize = 100_000_usize;
 /// Maximum size for error body snippets in logs (2KB)
 const MAX_ERROR_SNIPPET_SIZE: usize = 2048;
 
+/// Header carrying the upstream MCP session ID
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Header naming the last SSE event received when resuming a stream
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// Maximum number of times a broken SSE response stream is resumed
+pub const MAX_SSE_RESUMES: u32 = 3_u32;
+
+/// Upper bound for the reconnection delay requested with an SSE `retry:` field
+pub const MAX_SSE_RESUME_DELAY_MS: u64 = 5_000_u64;
+
+/// Upstream endpoint, timeout and retry settings for [`BunDocsClient`]
+///
+/// ```no_run
+/// use bun_docs_mcp_proxy::http::{BunDocsClient, ClientConfig, RetryPolicy};
+/// use std::time::Duration;
+///
+/// # fn example() -> anyhow::Result<()> {
+/// let config = ClientConfig::default()
+///     .base_url("http://localhost:3000/mcp")
+///     .timeout(Duration::from_secs(30))
+///     .retry(RetryPolicy::default().max_attempts(5));
+/// let client = BunDocsClient::with_config(config)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// MCP endpoint requests are posted to.
+    base_url: String,
+    /// Timeout for each individual HTTP request.
+    timeout: Duration,
+    /// Default retry policy for every request.
+    retry: RetryPolicy,
+    /// Limits and expiry of the response cache.
+    cache: CacheConfig,
+    /// Location and limits of the on-disk cache, if enabled.
+    disk_cache: Option<DiskCacheConfig>,
+    /// Failure threshold and cool-down of the circuit breaker.
+    circuit: CircuitConfig,
+    /// Largest accepted API response body or SSE stream in bytes.
+    max_response_size: usize,
+    /// Largest accepted documentation page in bytes.
+    max_page_size: usize,
+    /// Hosts, schemes and addresses documentation pages may be fetched from.
+    page_policy: PagePolicy,
+    /// Serve only cached responses and never contact the API.
+    offline: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            base_url: BUN_DOCS_API.to_owned(),
+            timeout: Duration::from_secs(REQUEST_TIMEOUT_SECS),
+            retry: RetryPolicy::default(),
+            cache: CacheConfig::default(),
+            disk_cache: None,
+            circuit: CircuitConfig::default(),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            max_page_size: DEFAULT_MAX_PAGE_SIZE,
+            page_policy: PagePolicy::default(),
+            offline: false,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Sets the MCP endpoint, e.g. a local mock or an internal mirror.
+    #[must_use]
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into();
+        self
+    }
+
+    /// Sets the timeout for each individual HTTP request.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the default retry policy for every request.
+    #[must_use]
+    pub const fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the limits and expiry of the response cache.
+    #[must_use]
+    pub const fn cache(mut self, cache: CacheConfig) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Enables the on-disk cache with the given location and limits.
+    #[must_use]
+    pub fn disk_cache(mut self, disk_cache: DiskCacheConfig) -> Self {
+        self.disk_cache = Some(disk_cache);
+        self
+    }
+
+    /// Sets the failure threshold and cool-down of the circuit breaker.
+    #[must_use]
+    pub const fn circuit(mut self, circuit: CircuitConfig) -> Self {
+        self.circuit = circuit;
+        self
+    }
+
+    /// Sets the largest accepted API response body or SSE stream in bytes.
+    #[must_use]
+    pub const fn max_response_size(mut self, bytes: usize) -> Self {
+        self.max_response_size = bytes;
+        self
+    }
+
+    /// Sets the largest accepted documentation page in bytes.
+    #[must_use]
+    pub const fn max_page_size(mut self, bytes: usize) -> Self {
+        self.max_page_size = bytes;
+        self
+    }
+
+    /// Sets the hosts, schemes and addresses documentation pages may be fetched from.
+    #[must_use]
+    pub fn page_policy(mut self, policy: PagePolicy) -> Self {
+        self.page_policy = policy;
+        self
+    }
+
+    /// Serves only cached responses (marked stale if expired) and never contacts
+    /// the API.
+    #[must_use]
+    pub const fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+}
+
+/// A documentation page kept in the memory cache
+#[derive(Debug, Clone)]
+struct CachedPage {
+    /// Raw Markdown/MDX source.
+    body: String,
+    /// Validators for revalidating the page with a conditional request.
+    validators: Validators,
+}
+
 /// HTTP client for interacting with the Bun Docs API
+///
+/// Clones are cheap and share the connection pool, upstream session and caches.
+#[derive(Clone)]
 pub struct BunDocsClient {
     /// The underlying `reqwest::Client` used for making HTTP requests.
     client: Client,
+    /// Client for documentation pages, resolving and redirecting per `page_policy`.
+    page_client: Client,
+    /// Which page URLs may be fetched, trusting the base URL's origin.
+    page_policy: PagePolicy,
     /// The base URL for all API requests made by this client.
     base_url: Url,
+    /// Timeout and retry settings.
+    config: ClientConfig,
+    /// Session ID assigned by the API, if any.
+    session_id: Arc<Mutex<Option<String>>>,
+    /// Cached `tools/call` responses.
+    responses: Arc<ResponseCache<Value>>,
+    /// Cached documentation pages.
+    pages: Arc<ResponseCache<CachedPage>>,
+    /// Persistent cache behind both in-memory caches, if enabled.
+    disk: Option<Arc<DiskCache>>,
+    /// Set while the API is failing and cached responses are served instead.
+    degraded: Arc<AtomicBool>,
+    /// Cache keys with a background refresh in progress.
+    refreshing: Arc<Mutex<HashSet<String>>>,
+    /// Notifications pushed by the API on its message stream.
+    events: broadcast::Sender<Value>,
+    /// Upstream `tools/list` result, fetched when the tool list changes.
+    tools: Arc<Mutex<Option<Value>>>,
+    /// Task listening on the upstream message stream.
+    listener: Arc<Mutex<Option<JoinHandle<()>>>>,
+    /// Upstream requests in flight, shared by identical concurrent requests.
+    flights: Arc<SingleFlight<Value>>,
+    /// Fails calls fast while the API keeps failing.
+    circuit: Arc<CircuitBreaker>,
 }
 
 impl Default for BunDocsClient {
@@ -102,53 +374,150 @@ impl BunDocsClient {
     /// # Errors
     /// Returns an error if the URL cannot be parsed
     pub fn with_base_url(url: &str) -> Result<Self> {
-        Ok(Self {
-            client: Client::new(),
-            base_url: Url::parse(url).context("Invalid base URL")?,
-        })
+        Self::with_config(ClientConfig::default().base_url(url))
     }
 
-    /// Calculates an exponential backoff delay for retry attempts.
-    ///
-    /// The delay increases with each `attempt` (e.g., 200ms, 400ms, 800ms) up to a maximum of 1000ms.
-    /// This helps prevent overwhelming the server during transient failures.
+    /// Creates a new client from a [`ClientConfig`].
     ///
     /// # Arguments
-    /// * `attempt` - The current retry attempt number (must be >= 1).
+    /// * `config` - Endpoint, timeout and retry settings
     ///
-    /// # Returns
-    /// The calculated delay in milliseconds.
-    fn backoff_delay_ms(attempt: usize) -> u64 {
-        debug_assert!(attempt > 0_usize, "attempt must be >= 1");
-        // 200ms, 400ms, 800ms (cap at 1000ms)
-        // Safe: attempt.saturating_sub(1) will be small in practice (<= MAX_RETRIES=3)
-        #[expect(
-            clippy::cast_possible_truncation,
-            reason = "attempt.saturating_sub(1) is bounded by MAX_RETRIES=3, fits in u32"
-        )]
-        let base =
-            BACKOFF_BASE_MS.saturating_mul(1_u64 << (attempt.saturating_sub(1_usize) as u32));
-        base.min(BACKOFF_MAX_MS)
-    }
-
-    /// Determines if an HTTP status code indicates a transient error that is worth retrying.
+    /// An on-disk cache that cannot be opened is logged and left disabled.
     ///
-    /// Transient errors typically include server errors (5xx) and rate limiting (429).
+    /// # Errors
+    /// Returns an error if the base URL cannot be parsed or is not `http(s)`
+    pub fn with_config(config: ClientConfig) -> Result<Self> {
+        let base_url = Url::parse(&config.base_url).context("Invalid base URL")?;
+        if !matches!(base_url.scheme(), "http" | "https") {
+            return Err(anyhow::anyhow!(
+                "Invalid base URL: expected http or https, got {}",
+                base_url.scheme()
+            ));
+        }
+        let disk = match config.disk_cache.clone().map(DiskCache::open) {
+            Some(Ok(disk)) => {
+                debug!("Using disk cache in {}", disk.dir().display());
+                Some(Arc::new(disk))
+            }
+            Some(Err(error)) => {
+                warn!("Disk cache disabled: {:#}", error);
+                None
+            }
+            None => None,
+        };
+        let page_policy = config.page_policy.clone().trust_origin_of(&base_url);
+        let mut page_client = Client::builder().redirect(page_policy.redirect_policy());
+        if let Some(resolver) = page_policy.resolver() {
+            page_client = page_client.dns_resolver(Arc::new(resolver));
+        }
+        Ok(Self {
+            client: Client::new(),
+            page_client: page_client
+                .build()
+                .context("Failed to build page HTTP client")?,
+            page_policy,
+            base_url,
+            responses: Arc::new(ResponseCache::new(config.cache)),
+            pages: Arc::new(ResponseCache::new(config.cache)),
+            circuit: Arc::new(CircuitBreaker::new(config.circuit)),
+            disk,
+            config,
+            session_id: Arc::new(Mutex::new(None)),
+            degraded: Arc::new(AtomicBool::new(false)),
+            refreshing: Arc::new(Mutex::new(HashSet::new())),
+            events: broadcast::channel(listen::EVENT_CHANNEL_CAPACITY).0,
+            tools: Arc::new(Mutex::new(None)),
+            listener: Arc::new(Mutex::new(None)),
+            flights: Arc::new(SingleFlight::default()),
+        })
+    }
+
+    /// Returns the base URL requests are sent to.
+    pub const fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Returns the state of the circuit breaker guarding upstream calls.
+    pub fn circuit_status(&self) -> CircuitStatus {
+        self.circuit.status()
+    }
+
+    /// Returns the combined hit/miss counters and size of the response caches.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.responses.stats() + self.pages.stats()
+    }
+
+    /// Returns the upstream session ID, if the API assigned one.
+    fn session_id(&self) -> Option<String> {
+        self.session_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Remembers the session ID from a response, if it carries one.
+    fn remember_session(&self, headers: &HeaderMap) {
+        let Some(id) = headers
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return;
+        };
+        let mut session_id = self
+            .session_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if session_id.as_deref() != Some(id) {
+            debug!("Upstream session ID: {}", id);
+            *session_id = Some(id.to_owned());
+            drop(session_id);
+            self.start_listening();
+        }
+    }
+
+    /// Ends the upstream session, if the API assigned one.
     ///
-    /// # Arguments
-    /// * `status` - The `StatusCode` to check.
+    /// Sends `DELETE` with the session ID. A `405 Method Not Allowed` answer means the
+    /// API does not support explicit termination and is not treated as an error. Only
+    /// one attempt is made, since the proxy is shutting down.
     ///
-    /// # Returns
-    /// `true` if the status code is transient and suggests a retry, `false` otherwise.
-    const fn is_transient_status(status: StatusCode) -> bool {
-        matches!(
-            status,
-            StatusCode::TOO_MANY_REQUESTS
-                | StatusCode::INTERNAL_SERVER_ERROR
-                | StatusCode::BAD_GATEWAY
-                | StatusCode::SERVICE_UNAVAILABLE
-                | StatusCode::GATEWAY_TIMEOUT
-        )
+    /// # Errors
+    /// Returns an error if the request fails or the API rejects it
+    pub async fn end_session(&self) -> Result<()> {
+        self.stop_listening();
+        let Some(id) = self
+            .session_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        else {
+            return Ok(());
+        };
+
+        let status = RetryPolicy::none()
+            .run("Ending upstream session", |_| async {
+                let response = self
+                    .client
+                    .delete(self.base_url.as_str())
+                    .header(SESSION_HEADER, &id)
+                    .timeout(self.config.timeout)
+                    .send()
+                    .await
+                    .map_err(|e| Failure::from_send_error(&e, "Failed to end upstream session"))?;
+
+                let status = response.status();
+                if status.is_success() || status == StatusCode::METHOD_NOT_ALLOWED {
+                    return Ok(status);
+                }
+                Err(Failure::from_status(
+                    status,
+                    response.headers(),
+                    anyhow::anyhow!("Failed to end upstream session {id}: HTTP {status}"),
+                ))
+            })
+            .await?;
+        info!("Ended upstream session {} ({})", id, status);
+        Ok(())
     }
 
     /// Extracts the main content type from a `HeaderMap`, stripping parameters like charset.
@@ -237,340 +606,883 @@ impl BunDocsClient {
     ///
     /// # Errors
     /// Returns an error if all retry attempts fail or a non-retryable error occurs
-    #[allow(
-        clippy::too_many_lines,
-        reason = "complex retry logic with error handling"
-    )]
     pub async fn forward_request(&self, request: Value) -> Result<Value> {
-        debug!("Forwarding request to Bun Docs API");
-
-        let mut last_error: Option<anyhow::Error> = None;
-
-        for attempt in 1_usize..=MAX_RETRIES {
-            // Build request each attempt
-            let rb = self
-                .client
-                .post(self.base_url.as_str())
-                .header(reqwest::header::CONTENT_TYPE, "application/json")
-                .header(
-                    reqwest::header::ACCEPT,
-                    "application/json, text/event-stream",
-                )
-                .json(&request)
-                .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS));
-
-            match rb.send().await {
-                Ok(response) => {
-                    let status = response.status();
-                    info!(
-                        "Bun Docs API response status: {} (attempt {} of {})",
-                        status, attempt, MAX_RETRIES
-                    );
-
-                    let headers = response.headers().clone();
-                    let content_type = Self::main_content_type(&headers);
-
-                    if status.is_success() {
-                        // Success: decide how to parse based on content type
-                        if content_type.starts_with("text/event-stream") {
-                            debug!("Parsing SSE stream");
-                            return self.parse_sse_response(response).await;
-                        }
-                        debug!("Parsing regular JSON response");
-                        return response
-                            .json()
-                            .await
-                            .context("Failed to parse JSON response");
-                    }
-                    // Read body (truncated) for context
-                    let bytes = response.bytes().await.unwrap_or_else(|error| {
-                        warn!("Failed to read error response body: {}", error);
-                        Bytes::default()
-                    });
-                    let limited_bytes: &[u8] = if bytes.len() > MAX_ERROR_BODY_SIZE {
-                        &bytes[..MAX_ERROR_BODY_SIZE]
-                    } else {
-                        &bytes
-                    };
-                    let body = String::from_utf8_lossy(limited_bytes);
-                    let body_snippet = Self::truncate_utf8(&body, MAX_ERROR_SNIPPET_SIZE);
-                    let header_summary = Self::summarize_headers(&headers);
-
-                    let error = anyhow::anyhow!(
-                        "Bun Docs API error: status={} content_type={} headers=[{}] body_snippet=\"{}\"",
-                        status,
-                        if content_type.is_empty() {
-                            "<none>"
-                        } else {
-                            &content_type
-                        },
-                        header_summary,
-                        body_snippet
-                    );
-
-                    // Retry on transient server statuses
-                    if Self::is_transient_status(status) && attempt < MAX_RETRIES {
-                        warn!(
-                            "Transient HTTP status {}, retrying (attempt {})",
-                            status,
-                            attempt + 1
-                        );
-                        let delay = Self::backoff_delay_ms(attempt);
-                        tokio::time::sleep(Duration::from_millis(delay)).await;
-                        last_error = Some(error);
-                        continue;
-                    }
-
-                    return Err(error);
-                }
-                Err(error) => {
-                    // Connection/timeout/etc. Retry if transient
-                    let is_transient =
-                        error.is_connect() || error.is_timeout() || error.is_request();
-                    let err = anyhow::anyhow!("Failed to send request to Bun Docs API: {error}");
-
-                    if is_transient && attempt < MAX_RETRIES {
-                        warn!(
-                            "Network error: {}. Retrying (attempt {} of {})",
-                            err,
-                            attempt + 1,
-                            MAX_RETRIES
-                        );
-                        let delay = Self::backoff_delay_ms(attempt);
-                        tokio::time::sleep(Duration::from_millis(delay)).await;
-                        last_error = Some(err);
-                        continue;
-                    }
-
-                    return Err(err);
-                }
-            }
-        }
-
-        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Unknown error sending request")))
+        self.forward_request_with_retry(request, &self.config.retry)
+            .await
     }
 
-    /// Parses a Server-Sent Events (SSE) response stream from the Bun Docs API.
-    ///
-    /// This function consumes the HTTP response body as an SSE stream, looking for
-    /// `message` or `completion` events that contain a complete JSON-RPC response.
-    /// It stops processing after the first valid JSON-RPC response is found.
+    /// Forward a JSON-RPC request to the Bun Docs API, relaying the progress and log
+    /// notifications the API streams before its response
     ///
     /// # Arguments
-    /// * `response` - The `reqwest::Response` object, expected to contain an SSE stream.
+    /// * `request` - JSON-RPC request object
+    /// * `relay` - Forwards notifications to the MCP client (see [`NotificationRelay`])
     ///
     /// # Returns
-    /// A `Result` which on success contains the parsed `serde_json::Value` representing
-    /// the JSON-RPC response. On failure, it returns an `anyhow::Error` if no valid
-    /// JSON-RPC response is found or if there's an error processing the stream.
+    /// JSON-RPC response from the API
     ///
     /// # Errors
-    /// Returns an error if:
-    /// - The SSE stream encounters an error.
-    /// - No valid JSON-RPC response (i.e., an object with a `result` or `error` field)
-    ///   is found within the stream.
-    /// - JSON parsing of an SSE event's data fails.
-    async fn parse_sse_response(&self, response: reqwest::Response) -> Result<Value> {
-        let mut event_stream = response.bytes_stream().eventsource();
-        let mut json_response: Option<Value> = None;
-
-        loop {
-            let event_result = event_stream.next().await;
-            let Some(event_result) = event_result else {
-                break;
-            };
-            match event_result {
-                Ok(event) => {
-                    debug!("SSE event type: {:?}", event.event);
-
-                    // Only handle message-like events; ignore heartbeats/others
-                    let event_type = if event.event.is_empty() {
-                        "message"
-                    } else {
-                        event.event.as_str()
-                    };
-                    if event_type != "message" && event_type != "completion" {
-                        debug!("Skipping SSE event type: {}", event_type);
-                        continue;
-                    }
-
-                    let data = event.data;
-                    if !data.is_empty() {
-                        match serde_json::from_str::<Value>(&data) {
-                            Ok(parsed) => {
-                                debug!("Parsed SSE data successfully");
-
-                                // Note: this implementation expects a complete JSON-RPC object in one event.
-                                // If the server streams partial deltas, we do not accumulate them here.
-                                // Adjust if protocol changes to delta streaming.
-                                if parsed.get("result").is_some() || parsed.get("error").is_some() {
-                                    json_response = Some(parsed);
-                                    // Found the JSON-RPC response, we can stop
-                                    break;
-                                }
-                            }
-                            Err(error) => {
-                                warn!("Failed to parse SSE data as JSON: {}", error);
-                                debug!("SSE data: {}", &data[..data.len().min(200_usize)]);
-                            }
-                        }
-                    }
-                }
-                Err(error) => {
-                    warn!("SSE stream error: {}", error);
-                    break;
-                }
-            }
-        }
-
-        json_response.ok_or_else(|| anyhow::anyhow!("No valid JSON-RPC response in SSE stream"))
+    /// Returns an error if all retry attempts fail or a non-retryable error occurs
+    pub async fn forward_request_with_relay(
+        &self,
+        request: Value,
+        relay: &NotificationRelay,
+    ) -> Result<Value> {
+        self.forward(request, &self.config.retry, Some(relay)).await
     }
 
-    /// Fetch a documentation page as raw Markdown/MDX
+    /// Forward a JSON-RPC request to the Bun Docs API, retrying according to `retry`
+    /// instead of the client's default policy
     ///
-    /// Sends an HTTP GET request with `Accept: text/markdown` header to retrieve
-    /// the raw MDX source of a documentation page.
+    /// A `tools/call` whose answer is cached is served without contacting the API. In
+    /// offline mode, or once the API has failed, an expired answer is served instead
+    /// (marked stale, see [`cache::mark_stale`]) and refreshed in the background.
     ///
     /// # Arguments
-    /// * `url` - The full URL of the documentation page to fetch
+    /// * `request` - JSON-RPC request object
+    /// * `retry` - Retry policy for this call
     ///
     /// # Returns
-    /// Raw Markdown/MDX content as a String
+    /// JSON-RPC response from the API
     ///
     /// # Errors
-    /// Returns an error if:
-    /// - The HTTP request fails
-    /// - The server returns a non-success status code
-    /// - The response body cannot be read as UTF-8 text
-    pub async fn fetch_doc_markdown(&self, url: &str) -> Result<String> {
-        debug!("Fetching MDX for URL: {}", url);
-
-        let response = self
-            .client
-            .get(url)
-            .header(reqwest::header::ACCEPT, "text/markdown")
-            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
-            .send()
-            .await
-            .context("Failed to send request for markdown")?;
+    /// Returns an error if all retry attempts fail or a non-retryable error occurs and
+    /// nothing is cached for the request
+    pub async fn forward_request_with_retry(
+        &self,
+        request: Value,
+        retry: &RetryPolicy,
+    ) -> Result<Value> {
+        self.forward(request, retry, None).await
+    }
 
-        let status = response.status();
-        if !status.is_success() {
+    /// Forwards a request through the cache, see [`Self::forward_request_with_retry`].
+    async fn forward(
+        &self,
+        request: Value,
+        retry: &RetryPolicy,
+        relay: Option<&NotificationRelay>,
+    ) -> Result<Value> {
+        let cache_key = cache::tool_call_key(&request);
+        let lookup_key = cache_key
+            .as_deref()
+            .filter(|_| !cache::bypasses_cache(&request));
+        if let Some(key) = lookup_key {
+            if let Some(cached) = self.cached_response(key) {
+                return Ok(with_request_id(cached, &request));
+            }
+            if let Some(reason) = self.degraded_reason()
+                && let Some(stale) = self.stale_response(key, reason)
+            {
+                self.refresh_response_in_background(key, &request);
+                return Ok(with_request_id(stale, &request));
+            }
+        }
+        if self.config.offline {
             return Err(anyhow::anyhow!(
-                "Failed to fetch markdown: HTTP {status} for URL: {url}"
+                "Offline mode: no cached response for this request"
             ));
         }
 
-        let text = response
-            .text()
-            .await
-            .context("Failed to read markdown response body")?;
-
-        debug!("Successfully fetched {} bytes of MDX", text.len());
-        Ok(text)
+        // Identical requests in flight share one upstream call
+        let (shared_request, shared_key) = (&request, &cache_key);
+        let fetched = self
+            .flights
+            .run(
+                &single_flight::request_key(&request),
+                relay,
+                |shared_relay| async move {
+                    let response = self
+                        .fetch_response(shared_request, retry, Some(&shared_relay))
+                        .await?;
+                    if let Some(key) = shared_key {
+                        self.store_response(key.clone(), &response);
+                    }
+                    Ok(response)
+                },
+            )
+            .await;
+        match fetched {
+            Ok(response) => Ok(with_request_id(response, &request)),
+            Err(error) => {
+                let Some(stale) = lookup_key
+                    .and_then(|key| self.stale_response(key, "the Bun Docs API is unreachable"))
+                else {
+                    return Err(error);
+                };
+                self.mark_unreachable(&error);
+                Ok(with_request_id(stale, &request))
+            }
+        }
     }
-}
 
-#[cfg(test)]
-#[allow(clippy::expect_used, reason = "tests can use expect()")]
-#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
-#[allow(clippy::indexing_slicing, reason = "tests use array indexing")]
-#[allow(clippy::default_numeric_fallback, reason = "test literals")]
-mod tests {
-    use super::*;
-    use serde_json::json;
-    use std::time::Instant;
+    /// Sends a JSON-RPC request to the API, retrying according to `retry`.
+    async fn fetch_response(
+        &self,
+        request: &Value,
+        retry: &RetryPolicy,
+        relay: Option<&NotificationRelay>,
+    ) -> Result<Value> {
+        debug!("Forwarding request to Bun Docs API");
 
-    #[test]
-    fn client_creation() {
-        let client = BunDocsClient::new();
-        assert_eq!(client.base_url.as_str(), BUN_DOCS_API);
+        let response = self
+            .guarded("Bun Docs API request", retry, |attempt| {
+                self.send_request(request, attempt, retry.attempts(), relay)
+            })
+            .await?;
+        self.mark_reachable();
+        Ok(response)
     }
 
-    #[test]
-    fn client_default() {
-        let client = BunDocsClient::default();
-        assert_eq!(client.base_url.as_str(), BUN_DOCS_API);
+    /// Stores a successful `tools/call` response in memory and on disk.
+    fn store_response(&self, key: String, response: &Value) {
+        if !cache::is_cacheable_response(response) {
+            return;
+        }
+        let body = response.to_string();
+        if let Some(disk) = &self.disk {
+            disk.put(&DiskEntry::new(
+                self.base_url.as_str(),
+                &key,
+                Validators::default(),
+                body.clone(),
+            ));
+        }
+        self.responses.insert(key, response.clone(), body.len());
     }
 
-    #[test]
-    fn client_with_base_url() {
-        let custom_url = "https://example.com/api";
-        let client = BunDocsClient::with_base_url(custom_url).expect("valid URL should parse");
-        assert_eq!(client.base_url.as_str(), custom_url);
+    /// Looks up a cached `tools/call` response, in memory first and then on disk.
+    ///
+    /// Fresh disk entries are promoted to the memory cache.
+    fn cached_response(&self, key: &str) -> Option<Value> {
+        if let Some(cached) = self.responses.get(key) {
+            debug!("Cache hit: {}", key);
+            return Some(cached);
+        }
+        let disk = self.disk.as_ref()?;
+        let entry = disk
+            .get(self.base_url.as_str(), key)
+            .filter(|entry| disk.is_fresh(entry))?;
+        let response: Value = serde_json::from_str(&entry.body).ok()?;
+        debug!("Disk cache hit: {}", key);
+        self.responses
+            .insert(key.to_owned(), response.clone(), entry.body.len());
+        Some(response)
     }
 
-    #[test]
-    fn client_with_base_url_invalid() {
-        let result = BunDocsClient::with_base_url("not a valid url");
-        assert!(result.is_err());
+    /// Looks up a `tools/call` response whatever its age and marks it stale.
+    ///
+    /// # Arguments
+    /// * `key` - Cache key of the request
+    /// * `reason` - Why no fresh response is fetched, shown to the reader
+    fn stale_response(&self, key: &str, reason: &str) -> Option<Value> {
+        let (response, age) = self.responses.get_stale(key).or_else(|| {
+            let entry = self.disk.as_ref()?.get(self.base_url.as_str(), key)?;
+            let response = serde_json::from_str(&entry.body).ok()?;
+            Some((response, entry.age()))
+        })?;
+        warn!(
+            "Serving stale response ({} s old) because {}",
+            age.as_secs(),
+            reason
+        );
+        Some(cache::mark_stale(response, age, reason))
     }
 
-    #[test]
-    fn backoff_delay_milliseconds() {
-        assert_eq!(BunDocsClient::backoff_delay_ms(1_usize), 200_u64);
-        assert_eq!(BunDocsClient::backoff_delay_ms(2_usize), 400_u64);
-        assert_eq!(BunDocsClient::backoff_delay_ms(3_usize), 800_u64);
-        assert_eq!(BunDocsClient::backoff_delay_ms(4_usize), 1000_u64); // capped
+    /// Looks up a cached documentation page, in memory first and then on disk.
+    ///
+    /// Fresh disk entries are promoted to the memory cache.
+    fn cached_page(&self, url: &str) -> Option<String> {
+        if let Some(cached) = self.pages.get(url) {
+            debug!("Cache hit: {}", url);
+            return Some(cached.body);
+        }
+        let disk = self.disk.as_ref()?;
+        let entry = disk.get(url, "").filter(|entry| disk.is_fresh(entry))?;
+        debug!("Disk cache hit: {}", url);
+        let size = entry.body.len();
+        let page = CachedPage {
+            body: entry.body,
+            validators: entry.validators,
+        };
+        self.pages.insert(url.to_owned(), page.clone(), size);
+        Some(page.body)
     }
 
-    #[test]
-    fn is_transient_status() {
-        assert!(BunDocsClient::is_transient_status(
-            StatusCode::TOO_MANY_REQUESTS
-        ));
-        assert!(BunDocsClient::is_transient_status(
-            StatusCode::INTERNAL_SERVER_ERROR
-        ));
-        assert!(BunDocsClient::is_transient_status(StatusCode::BAD_GATEWAY));
-        assert!(BunDocsClient::is_transient_status(
-            StatusCode::SERVICE_UNAVAILABLE
-        ));
-        assert!(BunDocsClient::is_transient_status(
-            StatusCode::GATEWAY_TIMEOUT
-        ));
-        assert!(!BunDocsClient::is_transient_status(StatusCode::NOT_FOUND));
-        assert!(!BunDocsClient::is_transient_status(StatusCode::BAD_REQUEST));
+    /// Looks up a documentation page whatever its age, in memory first and then on
+    /// disk.
+    ///
+    /// # Returns
+    /// The page and how long ago it was fetched
+    fn any_page(&self, url: &str) -> Option<(CachedPage, Duration)> {
+        self.pages.get_stale(url).or_else(|| {
+            let entry = self.disk.as_ref()?.get(url, "")?;
+            let age = entry.age();
+            let page = CachedPage {
+                body: entry.body,
+                validators: entry.validators,
+            };
+            Some((page, age))
+        })
     }
 
-    #[test]
-    fn main_content_type() {
-        use reqwest::header::HeaderValue;
-
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            HeaderValue::from_str("application/json; charset=utf-8").expect("valid header value"),
-        );
-        assert_eq!(
-            BunDocsClient::main_content_type(&headers),
-            "application/json"
+    /// Looks up a documentation page whatever its age and marks it stale.
+    fn stale_page(&self, url: &str, reason: &str) -> Option<String> {
+        let (page, age) = self.any_page(url)?;
+        warn!(
+            "Serving stale page {} ({} s old) because {}",
+            url,
+            age.as_secs(),
+            reason
         );
+        Some(cache::mark_stale_page(&page.body, age, reason))
+    }
 
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            HeaderValue::from_str("text/event-stream").expect("valid header value"),
-        );
-        assert_eq!(
-            BunDocsClient::main_content_type(&headers),
-            "text/event-stream"
-        );
+    /// Returns why cached answers should be served without waiting for the API, if
+    /// they should.
+    fn degraded_reason(&self) -> Option<&'static str> {
+        if self.config.offline {
+            Some("offline mode is enabled")
+        } else if self.degraded.load(Ordering::Relaxed) {
+            Some("the Bun Docs API is unreachable")
+        } else {
+            None
+        }
+    }
 
-        let empty_headers = HeaderMap::new();
-        assert_eq!(BunDocsClient::main_content_type(&empty_headers), "");
+    /// Records that the API failed and cached responses are being served.
+    fn mark_unreachable(&self, error: &anyhow::Error) {
+        if !self.degraded.swap(true, Ordering::Relaxed) {
+            warn!(
+                "Bun Docs API unavailable ({:#}), serving cached responses",
+                error
+            );
+        }
     }
 
-    #[test]
-    fn summarize_headers() {
-        use reqwest::header::HeaderValue;
+    /// Records that the API answered, leaving degraded mode.
+    fn mark_reachable(&self) {
+        if self.degraded.swap(false, Ordering::Relaxed) {
+            info!("Bun Docs API reachable again");
+        }
+    }
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            HeaderValue::from_str("application/json").expect("valid header value"),
-        );
-        headers.insert(
-            reqwest::header::CONTENT_LENGTH,
+    /// Refreshes a cached `tools/call` response in the background.
+    fn refresh_response_in_background(&self, key: &str, request: &Value) {
+        let request = request.clone();
+        let cache_key = key.to_owned();
+        self.spawn_refresh(key, |client| async move {
+            let response = client
+                .fetch_response(&request, &RetryPolicy::none(), None)
+                .await?;
+            client.store_response(cache_key, &response);
+            Ok(())
+        });
+    }
+
+    /// Refreshes a cached documentation page in the background.
+    fn refresh_page_in_background(&self, url: &str) {
+        let url = url.to_owned();
+        self.spawn_refresh(&url.clone(), |client| async move {
+            let cached = client.any_page(&url).map(|(page, _)| page);
+            let page = client
+                .fetch_page(&url, &RetryPolicy::none(), cached.as_ref())
+                .await?;
+            client.store_page(&url, page);
+            Ok(())
+        });
+    }
+
+    /// Runs `refresh` on a clone of this client unless offline or a refresh of `key`
+    /// is already running. Failures are logged at debug level.
+    fn spawn_refresh<F, Fut>(&self, key: &str, refresh: F)
+    where
+        F: FnOnce(Self) -> Fut,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        if self.config.offline
+            || !self
+                .refreshing
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(key.to_owned())
+        {
+            return;
+        }
+        let client = self.clone();
+        let key = key.to_owned();
+        let refreshed = refresh(self.clone());
+        tokio::spawn(async move {
+            match refreshed.await {
+                Ok(()) => debug!("Refreshed cache entry {}", key),
+                Err(error) => debug!("Background refresh of {} failed: {:#}", key, error),
+            }
+            client
+                .refreshing
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&key);
+        });
+    }
+
+    /// Runs `attempt` according to `retry` through the circuit breaker.
+    ///
+    /// The call counts as failed when its last attempt failed transiently; any other
+    /// outcome shows the API is up.
+    ///
+    /// # Errors
+    /// Returns [`circuit::CircuitOpen`] without calling `attempt` while the circuit is
+    /// open, or the error [`RetryPolicy::run`] returns
+    async fn guarded<T, F, Fut>(&self, what: &str, retry: &RetryPolicy, mut attempt: F) -> Result<T>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T, Failure>>,
+    {
+        let permit = self.circuit.acquire()?;
+        let transient = AtomicBool::new(false);
+        let result = retry
+            .run(what, |number| {
+                let attempted = attempt(number);
+                let transient = &transient;
+                async move {
+                    let outcome = attempted.await;
+                    transient.store(
+                        matches!(outcome, Err(Failure::Transient { .. })),
+                        Ordering::Relaxed,
+                    );
+                    outcome
+                }
+            })
+            .await;
+        if result.is_err() && transient.load(Ordering::Relaxed) {
+            permit.failed();
+        } else {
+            permit.succeeded();
+        }
+        result
+    }
+
+    /// Makes one attempt at forwarding a JSON-RPC request.
+    ///
+    /// # Arguments
+    /// * `request` - JSON-RPC request object
+    /// * `attempt` - Number of this attempt, for logging
+    /// * `attempts` - Maximum number of attempts, for logging
+    /// * `relay` - Receives notifications streamed before the response
+    ///
+    /// # Returns
+    /// JSON-RPC response from the API, or a [`Failure`] classifying the error
+    async fn send_request(
+        &self,
+        request: &Value,
+        attempt: u32,
+        attempts: u32,
+        relay: Option<&NotificationRelay>,
+    ) -> Result<Value, Failure> {
+        let mut rb = self
+            .client
+            .post(self.base_url.as_str())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(
+                reqwest::header::ACCEPT,
+                "application/json, text/event-stream",
+            )
+            .json(request)
+            .timeout(self.config.timeout);
+        if let Some(id) = self.session_id() {
+            rb = rb.header(SESSION_HEADER, id);
+        }
+
+        let response = rb
+            .send()
+            .await
+            .map_err(|e| Failure::from_send_error(&e, "Failed to send request to Bun Docs API"))?;
+
+        let status = response.status();
+        info!(
+            "Bun Docs API response status: {} (attempt {} of {})",
+            status, attempt, attempts
+        );
+
+        let headers = response.headers().clone();
+        let content_type = Self::main_content_type(&headers);
+
+        if status.is_success() {
+            self.remember_session(&headers);
+            // Success: decide how to parse based on content type
+            if content_type.starts_with("text/event-stream") {
+                debug!("Parsing SSE stream");
+                return self
+                    .parse_sse_response(response, request.get("id"), relay)
+                    .await;
+            }
+            debug!("Parsing regular JSON response");
+            let bytes = body::read_limited(
+                response,
+                self.config.max_response_size,
+                "Failed to read JSON response",
+            )
+            .await?;
+            return serde_json::from_slice(&bytes)
+                .context("Failed to parse JSON response")
+                .map_err(Failure::Permanent);
+        }
+        // Read body (truncated) for context
+        let bytes = body::read_prefix(response, MAX_ERROR_BODY_SIZE).await;
+        let body = String::from_utf8_lossy(&bytes);
+        let body_snippet = Self::truncate_utf8(&body, MAX_ERROR_SNIPPET_SIZE);
+        let header_summary = Self::summarize_headers(&headers);
+
+        let error = anyhow::anyhow!(
+            "Bun Docs API error: status={} content_type={} headers=[{}] body_snippet=\"{}\"",
+            status,
+            if content_type.is_empty() {
+                "<none>"
+            } else {
+                &content_type
+            },
+            header_summary,
+            body_snippet
+        );
+
+        // Transient server statuses are retried by the policy
+        Err(Failure::from_status(status, &headers, error))
+    }
+
+    /// Parses a Server-Sent Events (SSE) response stream from the Bun Docs API.
+    ///
+    /// This function consumes the HTTP response body as an SSE stream and assembles
+    /// the JSON-RPC response to `request_id` with [`sse::SseAssembler`], skipping
+    /// notifications and other messages sent before it. If the stream breaks after an
+    /// event with an ID, it is resumed with [`Self::resume_sse_stream`] up to
+    /// [`MAX_SSE_RESUMES`] times.
+    ///
+    /// # Arguments
+    /// * `response` - The `reqwest::Response` object, expected to contain an SSE stream.
+    /// * `request_id` - ID of the request being answered, if any
+    /// * `relay` - Receives progress and log notifications sent before the response
+    ///
+    /// # Returns
+    /// A `Result` which on success contains the parsed `serde_json::Value` representing
+    /// the JSON-RPC response.
+    ///
+    /// # Errors
+    /// Returns a transient [`Failure`], so the whole request is retried, if the stream
+    /// fails and cannot be resumed. Returns a permanent one if:
+    /// - The stream ends without an event ID before a JSON-RPC response (an object
+    ///   with a `result` or `error` field) for `request_id` is complete.
+    /// - A message grows beyond [`sse::MAX_PENDING_DATA_SIZE`].
+    async fn parse_sse_response(
+        &self,
+        response: reqwest::Response,
+        request_id: Option<&Value>,
+        relay: Option<&NotificationRelay>,
+    ) -> Result<Value, Failure> {
+        let mut assembler = sse::SseAssembler::new(request_id.cloned());
+        let mut outcome = self.feed_sse(response, &mut assembler, relay).await?;
+        let mut resumptions = 0_u32;
+        loop {
+            let (cause, failed) = match outcome {
+                sse::Outcome::Response(message) => return Ok(message),
+                sse::Outcome::Ended => ("stream ended".to_owned(), false),
+                sse::Outcome::Failed(cause) => (cause, true),
+            };
+            let Some(last_event_id) = assembler.last_event_id().map(str::to_owned) else {
+                let error = assembler.incomplete(&cause);
+                return Err(if failed {
+                    Failure::Transient {
+                        error,
+                        retry_after: None,
+                    }
+                } else {
+                    Failure::Permanent(error)
+                });
+            };
+            if resumptions >= MAX_SSE_RESUMES {
+                warn!("SSE stream broke again after {} resumptions", resumptions);
+                return Err(Failure::Transient {
+                    error: assembler.incomplete(&cause),
+                    retry_after: None,
+                });
+            }
+            resumptions += 1_u32;
+
+            info!(
+                "SSE stream interrupted ({}); resuming after event {} (attempt {} of {})",
+                cause, last_event_id, resumptions, MAX_SSE_RESUMES
+            );
+            let stream = match self
+                .resume_sse_stream(&last_event_id, assembler.retry())
+                .await
+            {
+                Ok(stream) => stream,
+                Err(error) => {
+                    warn!(
+                        "Cannot resume SSE stream, retrying the request: {:#}",
+                        error
+                    );
+                    return Err(Failure::Transient {
+                        error: assembler.incomplete(&cause),
+                        retry_after: None,
+                    });
+                }
+            };
+            outcome = self.feed_sse(stream, &mut assembler, relay).await?;
+        }
+    }
+
+    /// Feeds one SSE response stream to `assembler`, limited to
+    /// [`ClientConfig::max_response_size`] bytes.
+    ///
+    /// # Errors
+    /// Returns a permanent [`Failure`] if the stream exceeds the size limit or a
+    /// message grows beyond [`sse::MAX_PENDING_DATA_SIZE`]
+    async fn feed_sse(
+        &self,
+        response: reqwest::Response,
+        assembler: &mut sse::SseAssembler,
+        relay: Option<&NotificationRelay>,
+    ) -> Result<sse::Outcome, Failure> {
+        let mut stream =
+            body::LimitedStream::new(response.bytes_stream(), self.config.max_response_size);
+        let outcome = sse::feed(&mut stream, assembler, relay)
+            .await
+            .map_err(Failure::Permanent)?;
+        if let Some(too_large) = stream.too_large() {
+            return Err(too_large.into_failure("SSE response stream"));
+        }
+        Ok(outcome)
+    }
+
+    /// Reopens a broken SSE stream with a `GET` carrying `Last-Event-ID`.
+    ///
+    /// Waits for the reconnection delay the server announced with `retry:` (at most
+    /// [`MAX_SSE_RESUME_DELAY_MS`]) first.
+    ///
+    /// # Arguments
+    /// * `last_event_id` - ID of the last event received
+    /// * `retry` - Reconnection delay announced by the server, if any
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the server does not answer with an
+    /// SSE stream, e.g. `405 Method Not Allowed` when it does not support resumption
+    async fn resume_sse_stream(
+        &self,
+        last_event_id: &str,
+        retry: Option<Duration>,
+    ) -> Result<reqwest::Response> {
+        if let Some(delay) = retry {
+            tokio::time::sleep(delay.min(Duration::from_millis(MAX_SSE_RESUME_DELAY_MS))).await;
+        }
+        let mut rb = self
+            .client
+            .get(self.base_url.as_str())
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .header(LAST_EVENT_ID_HEADER, last_event_id)
+            .timeout(self.config.timeout);
+        if let Some(id) = self.session_id() {
+            rb = rb.header(SESSION_HEADER, id);
+        }
+
+        let response = rb.send().await.context("Failed to resume SSE stream")?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("server answered HTTP {status}"));
+        }
+        let content_type = Self::main_content_type(response.headers());
+        if !content_type.starts_with("text/event-stream") {
+            return Err(anyhow::anyhow!(
+                "server answered {content_type:?} instead of an SSE stream"
+            ));
+        }
+        Ok(response)
+    }
+
+    /// Fetch a documentation page as raw Markdown/MDX
+    ///
+    /// Sends an HTTP GET request with `Accept: text/markdown` header to retrieve
+    /// the raw MDX source of a documentation page. Transient failures are retried
+    /// with the client's default policy.
+    ///
+    /// # Arguments
+    /// * `url` - The full URL of the documentation page to fetch
+    ///
+    /// # Returns
+    /// Raw Markdown/MDX content as a String
+    ///
+    /// # Errors
+    /// Returns an error if:
+    /// - The URL is refused by the client's [`PagePolicy`] ([`PageRejected`])
+    /// - The HTTP request fails
+    /// - The server returns a non-success status code
+    /// - The response body cannot be read or is too large
+    pub async fn fetch_doc_markdown(&self, url: &str) -> Result<String> {
+        self.fetch_doc_markdown_with_retry(url, &self.config.retry)
+            .await
+    }
+
+    /// Fetch a documentation page as raw Markdown/MDX, retrying according to `retry`
+    /// instead of the client's default policy
+    ///
+    /// Like [`Self::forward_request_with_retry`], an expired page is served (with a
+    /// leading stale note) in offline mode or once the API has failed.
+    ///
+    /// # Arguments
+    /// * `url` - The full URL of the documentation page to fetch
+    /// * `retry` - Retry policy for this call
+    ///
+    /// # Returns
+    /// Raw Markdown/MDX content as a String
+    ///
+    /// # Errors
+    /// Returns [`PageRejected`] if the URL, its address or a redirect is refused by the
+    /// client's [`PagePolicy`]. Returns an error if all retry attempts fail or a
+    /// non-retryable error occurs and the page is not cached
+    pub async fn fetch_doc_markdown_with_retry(
+        &self,
+        url: &str,
+        retry: &RetryPolicy,
+    ) -> Result<String> {
+        let parsed = Url::parse(url).with_context(|| format!("Invalid page URL: {url}"))?;
+        self.page_policy.check(&parsed)?;
+        if let Some(cached) = self.cached_page(url) {
+            return Ok(cached);
+        }
+        if let Some(reason) = self.degraded_reason()
+            && let Some(stale) = self.stale_page(url, reason)
+        {
+            self.refresh_page_in_background(url);
+            return Ok(stale);
+        }
+        if self.config.offline {
+            return Err(anyhow::anyhow!("Offline mode: {url} is not cached"));
+        }
+
+        // An expired copy is revalidated instead of downloaded again
+        let cached = self.any_page(url).map(|(page, _)| page);
+        match self.fetch_page(url, retry, cached.as_ref()).await {
+            Ok(page) => {
+                let text = page.body.clone();
+                self.store_page(url, page);
+                Ok(text)
+            }
+            Err(error) if error.downcast_ref::<PageRejected>().is_some() => Err(error),
+            Err(error) => {
+                let Some(stale) = self.stale_page(url, "the Bun Docs API is unreachable") else {
+                    return Err(error);
+                };
+                self.mark_unreachable(&error);
+                Ok(stale)
+            }
+        }
+    }
+
+    /// Fetches a documentation page, retrying according to `retry`.
+    ///
+    /// With a `cached` copy, its validators are sent as `If-None-Match` and
+    /// `If-Modified-Since`, and a `304 Not Modified` answer returns that copy.
+    ///
+    /// # Returns
+    /// The page and the validators to revalidate it with later
+    async fn fetch_page(
+        &self,
+        url: &str,
+        retry: &RetryPolicy,
+        cached: Option<&CachedPage>,
+    ) -> Result<CachedPage> {
+        debug!("Fetching MDX for URL: {}", url);
+
+        let page = self
+            .guarded("Markdown fetch", retry, |_| async {
+                let mut rb = self
+                    .page_client
+                    .get(url)
+                    .header(reqwest::header::ACCEPT, "text/markdown")
+                    .timeout(self.config.timeout);
+                if let Some(cached) = cached {
+                    if let Some(etag) = &cached.validators.etag {
+                        rb = rb.header(reqwest::header::IF_NONE_MATCH, etag);
+                    }
+                    if let Some(last_modified) = &cached.validators.last_modified {
+                        rb = rb.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+                    }
+                }
+                let response = rb.send().await.map_err(|e| {
+                    // A refused address or redirect will be refused again
+                    page_policy::rejection(&e).map_or_else(
+                        || Failure::from_send_error(&e, "Failed to send request for markdown"),
+                        |rejected| Failure::Permanent(anyhow::Error::new(rejected.clone())),
+                    )
+                })?;
+
+                let status = response.status();
+                if status == StatusCode::NOT_MODIFIED
+                    && let Some(cached) = cached
+                {
+                    debug!("MDX not modified: {}", url);
+                    return Ok(CachedPage {
+                        body: cached.body.clone(),
+                        validators: validators(response.headers(), &cached.validators),
+                    });
+                }
+                if !status.is_success() {
+                    return Err(Failure::from_status(
+                        status,
+                        response.headers(),
+                        anyhow::anyhow!("Failed to fetch markdown: HTTP {status} for URL: {url}"),
+                    ));
+                }
+
+                let validators = validators(response.headers(), &Validators::default());
+                let bytes = body::read_limited(
+                    response,
+                    self.config.max_page_size,
+                    "Failed to read markdown response body",
+                )
+                .await?;
+                let body = String::from_utf8_lossy(&bytes).into_owned();
+                debug!("Successfully fetched {} bytes of MDX", body.len());
+                Ok(CachedPage { body, validators })
+            })
+            .await?;
+
+        self.mark_reachable();
+        Ok(page)
+    }
+
+    /// Stores a fetched documentation page in memory and on disk.
+    fn store_page(&self, url: &str, page: CachedPage) {
+        if let Some(disk) = &self.disk {
+            disk.put(&DiskEntry::new(
+                url,
+                "",
+                page.validators.clone(),
+                page.body.clone(),
+            ));
+        }
+        let size = page.body.len();
+        self.pages.insert(url.to_owned(), page, size);
+    }
+}
+
+/// Reads the validators of a response, keeping those of `previous` that it does not
+/// repeat (a `304` may omit them).
+fn validators(headers: &HeaderMap, previous: &Validators) -> Validators {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    };
+    Validators {
+        etag: header(reqwest::header::ETAG).or_else(|| previous.etag.clone()),
+        last_modified: header(reqwest::header::LAST_MODIFIED)
+            .or_else(|| previous.last_modified.clone()),
+    }
+}
+
+/// Re-stamps a cached, stale or shared response with the ID of the request it answers.
+fn with_request_id(mut response: Value, request: &Value) -> Value {
+    if let (Some(id), Some(object)) = (request.get("id"), response.as_object_mut()) {
+        object.insert("id".to_owned(), id.clone());
+    }
+    response
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+#[allow(clippy::indexing_slicing, reason = "tests use array indexing")]
+#[allow(clippy::default_numeric_fallback, reason = "test literals")]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Instant;
+
+    #[test]
+    fn client_creation() {
+        let client = BunDocsClient::new();
+        assert_eq!(client.base_url.as_str(), BUN_DOCS_API);
+    }
+
+    #[test]
+    fn client_default() {
+        let client = BunDocsClient::default();
+        assert_eq!(client.base_url.as_str(), BUN_DOCS_API);
+    }
+
+    #[test]
+    fn client_with_base_url() {
+        let custom_url = "https://example.com/api";
+        let client = BunDocsClient::with_base_url(custom_url).expect("valid URL should parse");
+        assert_eq!(client.base_url.as_str(), custom_url);
+    }
+
+    #[test]
+    fn client_with_base_url_invalid() {
+        let result = BunDocsClient::with_base_url("not a valid url");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn client_config_builder() {
+        let config = ClientConfig::default()
+            .base_url("http://localhost:3000/mcp")
+            .timeout(Duration::from_secs(30))
+            .retry(RetryPolicy::none());
+        assert_eq!(config.base_url, "http://localhost:3000/mcp");
+        assert_eq!(config.timeout, Duration::from_secs(30));
+        assert_eq!(config.retry.attempts(), 1);
+
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        assert_eq!(client.base_url().as_str(), "http://localhost:3000/mcp");
+    }
+
+    #[test]
+    fn client_with_config_rejects_non_http_scheme() {
+        let result =
+            BunDocsClient::with_config(ClientConfig::default().base_url("ftp://mirror/mcp"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn main_content_type() {
+        use reqwest::header::HeaderValue;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            HeaderValue::from_str("application/json; charset=utf-8").expect("valid header value"),
+        );
+        assert_eq!(
+            BunDocsClient::main_content_type(&headers),
+            "application/json"
+        );
+
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            HeaderValue::from_str("text/event-stream").expect("valid header value"),
+        );
+        assert_eq!(
+            BunDocsClient::main_content_type(&headers),
+            "text/event-stream"
+        );
+
+        let empty_headers = HeaderMap::new();
+        assert_eq!(BunDocsClient::main_content_type(&empty_headers), "");
+    }
+
+    #[test]
+    fn summarize_headers() {
+        use reqwest::header::HeaderValue;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            HeaderValue::from_str("application/json").expect("valid header value"),
+        );
+        headers.insert(
+            reqwest::header::CONTENT_LENGTH,
             HeaderValue::from_str("123").expect("valid header value"),
         );
 
@@ -636,36 +1548,206 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn forward_request_tools_call() {
+    async fn forward_request_assembles_streamed_sse_response() {
         let mut server = mockito::Server::new_async().await;
 
         let mock = server
             .mock("POST", "/")
             .with_status(200_usize)
-            .with_header("content-type", "application/json")
-            .with_body(r#"{"result": {"content": [{"type": "text", "text": "Bun.serve() documentation..."}]}}"#)
+            .with_header("content-type", "text/event-stream")
+            .with_body(concat!(
+                "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n",
+                "id: 1\ndata: {\"jsonrpc\":\"2.0\",\"id\":1,\n",
+                "data: \"result\":{\"tools\":\n\n",
+                "id: 2\ndata: []}}\n\n",
+            ))
             .expect(1_usize)
             .create_async()
             .await;
 
         let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let request = json!({
-            "jsonrpc": "2.0",
-            "id": 2_i32,
-            "method": "tools/call",
-            "params": {
-                "name": "SearchBun",
-                "arguments": {
-                    "query": "Bun.serve"
-                }
-            }
-        });
-
-        let result = client.forward_request(request).await;
+        let response = client
+            .forward_request(json!({"jsonrpc": "2.0", "id": 1_i32, "method": "tools/list"}))
+            .await
+            .expect("assembled response");
 
         mock.assert_async().await;
         drop(server);
-        assert!(
+        assert_eq!(response["id"], 1_i32);
+        assert_eq!(response["result"]["tools"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn interrupted_sse_stream_is_resumed_with_last_event_id() {
+        let mut server = mockito::Server::new_async().await;
+
+        let post = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "text/event-stream")
+            .with_header(SESSION_HEADER, "upstream-1")
+            .with_body(concat!(
+                "retry: 10\n\n",
+                "id: 1\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/message\"}\n\n",
+            ))
+            .expect(1_usize)
+            .create_async()
+            .await;
+        let resume = server
+            .mock("GET", "/")
+            .match_header(LAST_EVENT_ID_HEADER, "1")
+            .match_header(SESSION_HEADER, "upstream-1")
+            .match_header("accept", "text/event-stream")
+            .with_status(200_usize)
+            .with_header("content-type", "text/event-stream")
+            .with_body("id: 2\ndata: {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"tools\":[]}}\n\n")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let response = client
+            .forward_request_with_retry(
+                json!({"jsonrpc": "2.0", "id": 1_i32, "method": "tools/list"}),
+                &RetryPolicy::none(),
+            )
+            .await
+            .expect("resumed response");
+
+        post.assert_async().await;
+        resume.assert_async().await;
+        drop(server);
+        assert_eq!(response["result"]["tools"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn unresumable_sse_stream_falls_back_to_full_retry() {
+        let mut server = mockito::Server::new_async().await;
+
+        let post = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "text/event-stream")
+            .with_body(
+                "id: 1\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/message\"}\n\n",
+            )
+            .expect(2_usize)
+            .create_async()
+            .await;
+        let resume = server
+            .mock("GET", "/")
+            .with_status(405_usize)
+            .expect(2_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let retry = RetryPolicy::default()
+            .max_attempts(2)
+            .backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let error = client
+            .forward_request_with_retry(
+                json!({"jsonrpc": "2.0", "id": 1_i32, "method": "tools/list"}),
+                &retry,
+            )
+            .await
+            .expect_err("stream never completes");
+
+        post.assert_async().await;
+        resume.assert_async().await;
+        drop(server);
+        assert!(
+            error.to_string().contains("No valid JSON-RPC response"),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn sse_stream_without_event_ids_is_not_resumed() {
+        let mut server = mockito::Server::new_async().await;
+
+        let post = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "text/event-stream")
+            .with_body("data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/message\"}\n\n")
+            .expect(1_usize)
+            .create_async()
+            .await;
+        let resume = server.mock("GET", "/").expect(0_usize).create_async().await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let result = client
+            .forward_request(json!({"jsonrpc": "2.0", "id": 1_i32, "method": "tools/list"}))
+            .await;
+
+        post.assert_async().await;
+        resume.assert_async().await;
+        drop(server);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_requests_share_one_upstream_call() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":{"tools":[]}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let request = |id: Value| json!({"jsonrpc": "2.0", "id": id, "method": "tools/list"});
+        let (first, second, third) = tokio::join!(
+            client.forward_request(request(json!(1))),
+            client.forward_request(request(json!(2))),
+            client.forward_request(request(json!("three"))),
+        );
+
+        mock.assert_async().await;
+        drop(server);
+        assert_eq!(first.expect("shared response")["id"], 1);
+        assert_eq!(second.expect("shared response")["id"], 2);
+        let third = third.expect("shared response");
+        assert_eq!(third["id"], "three");
+        assert_eq!(third["result"]["tools"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn forward_request_tools_call() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {"content": [{"type": "text", "text": "Bun.serve() documentation..."}]}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 2_i32,
+            "method": "tools/call",
+            "params": {
+                "name": "SearchBun",
+                "arguments": {
+                    "query": "Bun.serve"
+                }
+            }
+        });
+
+        let result = client.forward_request(request).await;
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(
             result.is_ok(),
             "Should successfully forward tools/call request"
         );
@@ -986,314 +2068,1330 @@ mod tests {
         let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
         let request = json!({"method": "tools/list"});
 
-        let result = client.forward_request(request).await;
+        let result = client.forward_request(request).await;
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(result.is_err(), "Should fail without retry on 404");
+        let error = result.expect_err("should be an error");
+        assert!(error.to_string().contains("404"));
+    }
+
+    #[tokio::test]
+    async fn retry_on_429_rate_limit() {
+        let mut server = mockito::Server::new_async().await;
+
+        // First request gets rate limited
+        let mock1 = server
+            .mock("POST", "/")
+            .with_status(429_usize)
+            .with_header("content-type", "text/plain")
+            .with_body("Too Many Requests")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        // Second request succeeds
+        let mock2 = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {"data": "success"}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let request = json!({"method": "test"});
+
+        let result = client.forward_request(request).await;
+
+        mock1.assert_async().await;
+        mock2.assert_async().await;
+        drop(server);
+        assert!(result.is_ok(), "Should succeed after retrying 429");
+    }
+
+    #[tokio::test]
+    async fn retry_on_500_internal_error() {
+        let mut server = mockito::Server::new_async().await;
+
+        // First request fails with 500
+        let mock1 = server
+            .mock("POST", "/")
+            .with_status(500_usize)
+            .with_header("content-type", "text/plain")
+            .with_body("Internal Server Error")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        // Second request succeeds
+        let mock2 = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let request = json!({"method": "test"});
+
+        let result = client.forward_request(request).await;
+
+        mock1.assert_async().await;
+        mock2.assert_async().await;
+        drop(server);
+        assert!(result.is_ok(), "Should succeed after retrying 500");
+    }
+
+    #[tokio::test]
+    async fn retry_on_502_bad_gateway() {
+        let mut server = mockito::Server::new_async().await;
+
+        // Simulate bad gateway then recovery
+        let mock1 = server
+            .mock("POST", "/")
+            .with_status(502_usize)
+            .with_body("Bad Gateway")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let mock2 = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let request = json!({"method": "test"});
+
+        let result = client.forward_request(request).await;
+
+        mock1.assert_async().await;
+        mock2.assert_async().await;
+        drop(server);
+        let _response = result.expect("successful response after retry");
+    }
+
+    #[tokio::test]
+    async fn retry_timing_bounded_by_backoff() {
+        let mut server = mockito::Server::new_async().await;
+
+        // All requests fail to test backoff timing
+        let mock = server
+            .mock("POST", "/")
+            .with_status(503_usize)
+            .with_body("Unavailable")
+            .expect(3_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let request = json!({"method": "test"});
+
+        let start = Instant::now();
+        let _result = client.forward_request(request).await;
+        let elapsed = start.elapsed();
+
+        mock.assert_async().await;
+        drop(server);
+
+        // With 3 attempts the jittered waits are at most 200 ms and 400 ms:
+        // Total should be at most 600 ms (200 + 400)
+        // But allow some margin for execution time
+        assert!(
+            elapsed.as_millis() < 1500_u128,
+            "Expected at most 600 ms for backoff, got {}ms",
+            elapsed.as_millis()
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_honors_retry_after_seconds() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock1 = server
+            .mock("POST", "/")
+            .with_status(503_usize)
+            .with_header("retry-after", "1")
+            .with_body("Unavailable")
+            .expect(1_usize)
+            .create_async()
+            .await;
+        let mock2 = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+
+        let start = Instant::now();
+        let result = client.forward_request(json!({"method": "test"})).await;
+        let elapsed = start.elapsed();
+
+        mock1.assert_async().await;
+        mock2.assert_async().await;
+        drop(server);
+        let _response = result.expect("successful response after Retry-After");
+        assert!(
+            elapsed >= Duration::from_millis(950),
+            "Expected to wait about 1 s, got {}ms",
+            elapsed.as_millis()
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_after_is_capped() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock1 = server
+            .mock("POST", "/")
+            .with_status(429_usize)
+            .with_header("retry-after", "3600")
+            .with_body("Too Many Requests")
+            .expect(1_usize)
+            .create_async()
+            .await;
+        let mock2 = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .retry(RetryPolicy::default().retry_after_max(Duration::from_millis(50)));
+        let client = BunDocsClient::with_config(config).expect("valid mock server URL");
+
+        let start = Instant::now();
+        let result = client.forward_request(json!({"method": "test"})).await;
+
+        mock1.assert_async().await;
+        mock2.assert_async().await;
+        drop(server);
+        let _response = result.expect("successful response after capped wait");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn configured_max_retries_limits_attempts() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/")
+            .with_status(503_usize)
+            .with_body("Unavailable")
+            .expect(5_usize)
+            .create_async()
+            .await;
+
+        let config = ClientConfig::default().base_url(server.url()).retry(
+            RetryPolicy::default()
+                .max_attempts(5)
+                .backoff(Duration::from_millis(1), Duration::from_millis(5)),
+        );
+        let client = BunDocsClient::with_config(config).expect("valid mock server URL");
+
+        let start = Instant::now();
+        let result = client.forward_request(json!({"method": "test"})).await;
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(result.is_err());
+        // Short configured backoff, not the 200 ms default
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn configured_timeout_applies_to_markdown_fetch() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind listener");
+        let addr = listener.local_addr().expect("local addr");
+        // Accept the connection but never answer
+        let stalled = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.expect("accept");
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+
+        let config = ClientConfig::default()
+            .base_url(format!("http://{addr}/"))
+            .timeout(Duration::from_millis(100));
+        let client = BunDocsClient::with_config(config).expect("valid URL");
+
+        let start = Instant::now();
+        let result = client
+            .fetch_doc_markdown(&format!("http://{addr}/page"))
+            .await;
+
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        stalled.abort();
+    }
+
+    #[tokio::test]
+    async fn fetch_doc_markdown_success() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("GET", "/docs/page")
+            .match_header("accept", "text/markdown")
+            .with_status(200_usize)
+            .with_header("content-type", "text/markdown")
+            .with_body("# Test MDX\n\nSome content")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let url = format!("{}/docs/page", server.url());
+
+        let result = client.fetch_doc_markdown(&url).await;
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(result.is_ok());
+        let mdx = result.expect("successful MDX fetch");
+        assert!(mdx.contains("# Test MDX"));
+        assert!(mdx.contains("Some content"));
+    }
+
+    #[tokio::test]
+    async fn oversized_json_response_is_rejected() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"tools": []}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .max_response_size(16_usize);
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        let error = client
+            .forward_request(json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .await
+            .expect_err("body is over the limit");
+
+        mock.assert_async().await;
+        drop(server);
+        assert_eq!(
+            error.downcast_ref::<body::ResponseTooLarge>(),
+            Some(&body::ResponseTooLarge { limit: 16_usize })
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_sse_stream_is_rejected_without_retry() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "text/event-stream")
+            .with_chunked_body(|writer| {
+                for _ in 0_u32..100_u32 {
+                    writer.write_all(b"event: heartbeat\ndata: {}\n\n")?;
+                }
+                Ok(())
+            })
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .max_response_size(1_000_usize);
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        let error = client
+            .forward_request(json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .await
+            .expect_err("stream is over the limit");
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(error.to_string().contains("SSE response stream"), "{error}");
+        assert!(error.downcast_ref::<body::ResponseTooLarge>().is_some());
+    }
+
+    #[tokio::test]
+    async fn oversized_streamed_page_is_rejected() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/docs/page")
+            .with_status(200_usize)
+            .with_header("content-type", "text/markdown")
+            .with_chunked_body(|writer| {
+                for _ in 0_u32..100_u32 {
+                    writer.write_all(b"# Heading\n\nParagraph\n\n")?;
+                }
+                Ok(())
+            })
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .max_page_size(100_usize);
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        let error = client
+            .fetch_doc_markdown(&format!("{}/docs/page", server.url()))
+            .await
+            .expect_err("page is over the limit");
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(error.to_string().contains("markdown"), "{error}");
+        assert!(error.downcast_ref::<body::ResponseTooLarge>().is_some());
+    }
+
+    #[tokio::test]
+    async fn page_redirect_outside_the_policy_is_refused() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/docs/page")
+            .with_status(302_usize)
+            .with_header("location", "http://127.0.0.1:1/internal")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .retry(RetryPolicy::default().max_attempts(3_u32));
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        let error = client
+            .fetch_doc_markdown(&format!("{}/docs/page", server.url()))
+            .await
+            .expect_err("redirect target is refused");
+
+        // Refusals are not retried
+        mock.assert_async().await;
+        drop(server);
+        let rejected = error
+            .downcast_ref::<PageRejected>()
+            .expect("typed rejection");
+        assert_eq!(rejected.target, "http://127.0.0.1:1/internal");
+        assert!(!client.degraded.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn page_outside_the_policy_is_refused_before_fetching() {
+        let client = BunDocsClient::new();
+        for url in ["http://bun.com/docs", "https://localhost/docs", "not a url"] {
+            client
+                .fetch_doc_markdown(url)
+                .await
+                .expect_err("URL is refused");
+        }
+        let error = client
+            .fetch_doc_markdown("https://10.0.0.1/docs")
+            .await
+            .expect_err("private address");
+        assert!(error.downcast_ref::<PageRejected>().is_some(), "{error}");
+    }
+
+    #[tokio::test]
+    async fn fetch_doc_markdown_404_error() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("GET", "/docs/missing")
+            .with_status(404_usize)
+            .with_body("Not Found")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let url = format!("{}/docs/missing", server.url());
+
+        let result = client.fetch_doc_markdown(&url).await;
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(result.is_err());
+        let error = result.expect_err("should be 404 error");
+        assert!(error.to_string().contains("404"));
+    }
+
+    #[tokio::test]
+    async fn fetch_doc_markdown_500_error() {
+        let mut server = mockito::Server::new_async().await;
+
+        // 500 is transient, so every attempt is used up
+        let mock = server
+            .mock("GET", "/docs/error")
+            .with_status(500_usize)
+            .with_body("Internal Server Error")
+            .expect(3_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let url = format!("{}/docs/error", server.url());
+
+        let result = client.fetch_doc_markdown(&url).await;
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(result.is_err());
+        let error = result.expect_err("should be 500 error");
+        assert!(error.to_string().contains("500"));
+    }
+
+    #[tokio::test]
+    async fn fetch_doc_markdown_retries_transient_failure() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock1 = server
+            .mock("GET", "/docs/page")
+            .with_status(503_usize)
+            .with_body("Service Unavailable")
+            .expect(1_usize)
+            .create_async()
+            .await;
+        let mock2 = server
+            .mock("GET", "/docs/page")
+            .with_status(200_usize)
+            .with_header("content-type", "text/markdown")
+            .with_body("# Recovered")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let url = format!("{}/docs/page", server.url());
+
+        let result = client.fetch_doc_markdown(&url).await;
+
+        mock1.assert_async().await;
+        mock2.assert_async().await;
+        drop(server);
+        assert_eq!(result.expect("markdown after retry"), "# Recovered");
+    }
+
+    #[tokio::test]
+    async fn fetch_doc_markdown_retry_override() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("GET", "/docs/page")
+            .with_status(503_usize)
+            .with_body("Service Unavailable")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let url = format!("{}/docs/page", server.url());
+
+        let result = client
+            .fetch_doc_markdown_with_retry(&url, &RetryPolicy::none())
+            .await;
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(
+            result
+                .expect_err("single attempt")
+                .to_string()
+                .contains("503")
+        );
+    }
+
+    #[tokio::test]
+    async fn forward_request_retry_override() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/")
+            .with_status(502_usize)
+            .with_body("Bad Gateway")
+            .expect(4_usize)
+            .create_async()
+            .await;
+
+        // The client default (3 attempts) is overridden for this call only
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let retry = RetryPolicy::default()
+            .max_attempts(4)
+            .backoff(Duration::from_millis(1), Duration::from_millis(1));
+
+        let result = client
+            .forward_request_with_retry(json!({"method": "test"}), &retry)
+            .await;
+
+        mock.assert_async().await;
+        drop(server);
+        assert!(
+            result
+                .expect_err("all attempts fail")
+                .to_string()
+                .contains("502")
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_with_transient_http_failure_logging() {
+        let mut server = mockito::Server::new_async().await;
+
+        // First attempt: 503 error (transient)
+        let mock1 = server
+            .mock("POST", "/")
+            .with_status(503_usize)
+            .with_header("content-type", "text/plain")
+            .with_body("Service Unavailable")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        // Second attempt: Success
+        let mock2 = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {"tools": []}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let request = json!({"method": "tools/list"});
+
+        let result = client.forward_request(request).await;
+
+        mock1.assert_async().await;
+        mock2.assert_async().await;
+        drop(server);
+
+        assert!(result.is_ok(), "Should succeed after transient 503 retry");
+        let response = result.expect("successful response");
+        assert!(response.get("result").is_some());
+        // Verifies src/http.rs line 315-319: warn!("Transient HTTP status...")
+        // Verifies line 321: backoff_delay calculation
+        // Verifies line 322: sleep execution
+    }
+
+    #[tokio::test]
+    async fn retry_on_multiple_transient_failures() {
+        let mut server = mockito::Server::new_async().await;
+
+        // First attempt: 502 Bad Gateway
+        let mock1 = server
+            .mock("POST", "/")
+            .with_status(502_usize)
+            .with_body("Bad Gateway")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        // Second attempt: 503 Service Unavailable
+        let mock2 = server
+            .mock("POST", "/")
+            .with_status(503_usize)
+            .with_body("Service Unavailable")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        // Third attempt: 504 Gateway Timeout (transient)
+        let mock3 = server
+            .mock("POST", "/")
+            .with_status(504_usize)
+            .with_body("Gateway Timeout")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let request = json!({"method": "test"});
+
+        let result = client.forward_request(request).await;
+
+        // All three mocks should have been called (exhausted retries)
+        mock1.assert_async().await;
+        mock2.assert_async().await;
+        mock3.assert_async().await;
+        drop(server);
+
+        assert!(result.is_err(), "Should fail after exhausting retries");
+        let error = result.expect_err("should be an error");
+        assert!(error.to_string().contains("504"));
+        // Verifies src/http.rs line 314: is_transient_status check for all 5xx codes
+        // Verifies line 317-318: retry condition check (attempt < MAX_RETRIES)
+        // Verifies line 321-322: backoff delays between attempts
+    }
+
+    #[tokio::test]
+    async fn upstream_session_id_is_sent_back_and_ended() {
+        let mut server = mockito::Server::new_async().await;
+
+        let initialize = server
+            .mock("POST", "/")
+            .match_header(SESSION_HEADER, mockito::Matcher::Missing)
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_header("mcp-session-id", "upstream-1")
+            .with_body(r#"{"result": {}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+        let follow_up = server
+            .mock("POST", "/")
+            .match_header(SESSION_HEADER, "upstream-1")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {"tools": []}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+        let delete = server
+            .mock("DELETE", "/")
+            .match_header(SESSION_HEADER, "upstream-1")
+            .with_status(204_usize)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        client
+            .forward_request(json!({"method": "initialize"}))
+            .await
+            .expect("initialize succeeds");
+        client
+            .forward_request(json!({"method": "tools/list"}))
+            .await
+            .expect("follow-up succeeds");
+        client.end_session().await.expect("session ended");
+        // The session is forgotten once ended
+        client.end_session().await.expect("nothing left to end");
+
+        initialize.assert_async().await;
+        follow_up.assert_async().await;
+        delete.assert_async().await;
+        drop(server);
+    }
+
+    #[tokio::test]
+    async fn end_session_without_session_is_noop() {
+        let mut server = mockito::Server::new_async().await;
+        let delete = server
+            .mock("DELETE", "/")
+            .expect(0_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        client.end_session().await.expect("no-op");
+
+        delete.assert_async().await;
+        drop(server);
+    }
+
+    #[tokio::test]
+    async fn end_session_tolerates_method_not_allowed() {
+        let mut server = mockito::Server::new_async().await;
+        let delete = server
+            .mock("DELETE", "/")
+            .with_status(405_usize)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        *client.session_id.lock().unwrap() = Some("upstream-2".to_owned());
+        client.end_session().await.expect("405 is not an error");
+
+        delete.assert_async().await;
+        drop(server);
+    }
+
+    fn search_request(id: i32, meta: &Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": {
+                "name": "SearchBun",
+                "arguments": {"query": "Bun.serve"},
+                "_meta": meta
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn repeated_tools_call_is_served_from_cache() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"content": []}}"#)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let first = client
+            .forward_request(search_request(1_i32, &json!({})))
+            .await
+            .expect("first call reaches upstream");
+        let second = client
+            .forward_request(search_request(2_i32, &json!({"progressToken": "p"})))
+            .await
+            .expect("second call is cached");
+
+        mock.assert_async().await;
+        drop(server);
+        assert_eq!(first["id"], 1_i32);
+        assert_eq!(second["id"], 2_i32);
+        assert_eq!(second["result"], first["result"]);
+        let stats = client.cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn no_cache_meta_bypasses_cache() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"content": []}}"#)
+            .expect(2_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        for id in [1_i32, 2_i32] {
+            client
+                .forward_request(search_request(id, &json!({cache::NO_CACHE_META: true})))
+                .await
+                .expect("call reaches upstream");
+        }
+
+        mock.assert_async().await;
+        drop(server);
+        assert_eq!(client.cache_stats().hits, 0);
+    }
+
+    #[tokio::test]
+    async fn tool_errors_are_not_cached() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"content": [], "isError": true}}"#)
+            .expect(2_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        for id in [1_i32, 2_i32] {
+            client
+                .forward_request(search_request(id, &json!({})))
+                .await
+                .expect("call reaches upstream");
+        }
+
+        mock.assert_async().await;
+        drop(server);
+        assert_eq!(client.cache_stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn disabled_cache_always_forwards() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"content": []}}"#)
+            .expect(2_usize)
+            .create_async()
+            .await;
+
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .cache(CacheConfig::default().max_entries(0));
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        for id in [1_i32, 2_i32] {
+            client
+                .forward_request(search_request(id, &json!({})))
+                .await
+                .expect("call reaches upstream");
+        }
+
+        mock.assert_async().await;
+        drop(server);
+    }
+
+    #[tokio::test]
+    async fn fetched_pages_are_cached() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/docs/page")
+            .with_status(200_usize)
+            .with_body("# Cached page")
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+        let url = format!("{}/docs/page", server.url());
+        let first = client.fetch_doc_markdown(&url).await.expect("page fetched");
+        let second = client.fetch_doc_markdown(&url).await.expect("page cached");
 
         mock.assert_async().await;
         drop(server);
-        assert!(result.is_err(), "Should fail without retry on 404");
-        let error = result.expect_err("should be an error");
-        assert!(error.to_string().contains("404"));
+        assert_eq!(first, second);
+        assert_eq!(client.cache_stats().hits, 1);
     }
 
     #[tokio::test]
-    async fn retry_on_429_rate_limit() {
+    async fn disk_cache_warms_a_new_client() {
         let mut server = mockito::Server::new_async().await;
-
-        // First request gets rate limited
-        let mock1 = server
+        let search = server
             .mock("POST", "/")
-            .with_status(429_usize)
-            .with_header("content-type", "text/plain")
-            .with_body("Too Many Requests")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"content": []}}"#)
             .expect(1_usize)
             .create_async()
             .await;
-
-        // Second request succeeds
-        let mock2 = server
-            .mock("POST", "/")
+        let page = server
+            .mock("GET", "/docs/page")
             .with_status(200_usize)
-            .with_header("content-type", "application/json")
-            .with_body(r#"{"result": {"data": "success"}}"#)
+            .with_header("etag", "\"abc\"")
+            .with_body("# Persisted page")
             .expect(1_usize)
             .create_async()
             .await;
 
-        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let request = json!({"method": "test"});
+        let dir = tempfile::tempdir().unwrap();
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .disk_cache(DiskCacheConfig::new(dir.path()));
+        let url = format!("{}/docs/page", server.url());
 
-        let result = client.forward_request(request).await;
+        let first = BunDocsClient::with_config(config.clone()).expect("valid config");
+        first
+            .forward_request(search_request(1_i32, &json!({})))
+            .await
+            .expect("search reaches upstream");
+        first.fetch_doc_markdown(&url).await.expect("page fetched");
 
-        mock1.assert_async().await;
-        mock2.assert_async().await;
+        let second = BunDocsClient::with_config(config).expect("valid config");
+        let response = second
+            .forward_request(search_request(7_i32, &json!({})))
+            .await
+            .expect("search served from disk");
+        let text = second
+            .fetch_doc_markdown(&url)
+            .await
+            .expect("page from disk");
+
+        search.assert_async().await;
+        page.assert_async().await;
         drop(server);
-        assert!(result.is_ok(), "Should succeed after retrying 429");
+        assert_eq!(response["id"], 7_i32);
+        assert_eq!(text, "# Persisted page");
+        let stored = DiskCache::open(DiskCacheConfig::new(dir.path()))
+            .unwrap()
+            .get(&url, "")
+            .expect("page entry on disk");
+        assert_eq!(stored.validators.etag.as_deref(), Some("\"abc\""));
+    }
+
+    #[test]
+    fn unusable_disk_cache_is_disabled() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let config = ClientConfig::default().disk_cache(DiskCacheConfig::new(file.path()));
+        let client = BunDocsClient::with_config(config).expect("client still created");
+        assert!(client.disk.is_none());
+    }
+
+    /// Waits until no background refresh is running.
+    async fn wait_for_refreshes(client: &BunDocsClient) {
+        for _ in 0..200 {
+            if client.refreshing.lock().unwrap().is_empty() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("background refresh did not finish");
     }
 
     #[tokio::test]
-    async fn retry_on_500_internal_error() {
+    async fn offline_mode_serves_only_cached_answers() {
         let mut server = mockito::Server::new_async().await;
-
-        // First request fails with 500
-        let mock1 = server
+        let search = server
             .mock("POST", "/")
-            .with_status(500_usize)
-            .with_header("content-type", "text/plain")
-            .with_body("Internal Server Error")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"content": []}}"#)
             .expect(1_usize)
             .create_async()
             .await;
-
-        // Second request succeeds
-        let mock2 = server
-            .mock("POST", "/")
+        let page = server
+            .mock("GET", "/docs/page")
             .with_status(200_usize)
-            .with_header("content-type", "application/json")
-            .with_body(r#"{"result": {}}"#)
+            .with_body("# Page")
             .expect(1_usize)
             .create_async()
             .await;
 
-        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let request = json!({"method": "test"});
+        let dir = tempfile::tempdir().unwrap();
+        // Entries are never fresh, so the offline client can only serve them stale
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .disk_cache(DiskCacheConfig::new(dir.path()).ttl(Duration::ZERO));
+        let url = format!("{}/docs/page", server.url());
+        let online = BunDocsClient::with_config(config.clone()).expect("valid config");
+        online
+            .forward_request(search_request(1_i32, &json!({})))
+            .await
+            .expect("search reaches upstream");
+        online.fetch_doc_markdown(&url).await.expect("page fetched");
 
-        let result = client.forward_request(request).await;
+        let offline = BunDocsClient::with_config(config.offline(true)).expect("valid config");
+        let response = offline
+            .forward_request(search_request(2_i32, &json!({})))
+            .await
+            .expect("stale search served");
+        let text = offline
+            .fetch_doc_markdown(&url)
+            .await
+            .expect("stale page served");
+        let missing = offline
+            .forward_request(json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "tools/call",
+                "params": {"name": "SearchBun", "arguments": {"query": "never cached"}}
+            }))
+            .await;
 
-        mock1.assert_async().await;
-        mock2.assert_async().await;
+        search.assert_async().await;
+        page.assert_async().await;
         drop(server);
-        assert!(result.is_ok(), "Should succeed after retrying 500");
+        assert_eq!(response["id"], 2_i32);
+        assert_eq!(response["result"]["_meta"][cache::STALE_META], true);
+        let note = response["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(note.contains("offline mode"), "{note}");
+        assert!(text.starts_with("<!-- Stale result"), "{text}");
+        assert!(text.ends_with("# Page"));
+        let error = missing.expect_err("nothing cached");
+        assert!(error.to_string().contains("Offline mode"), "{error}");
     }
 
     #[tokio::test]
-    async fn retry_on_502_bad_gateway() {
+    async fn failing_upstream_serves_stale_and_refreshes_in_background() {
         let mut server = mockito::Server::new_async().await;
-
-        // Simulate bad gateway then recovery
-        let mock1 = server
+        let ok = server
             .mock("POST", "/")
-            .with_status(502_usize)
-            .with_body("Bad Gateway")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"content": [{"type": "text", "text": "v1"}]}}"#)
             .expect(1_usize)
             .create_async()
             .await;
 
-        let mock2 = server
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .retry(RetryPolicy::none())
+            .cache(CacheConfig::default().ttl(Duration::from_millis(1)));
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        client
+            .forward_request(search_request(1_i32, &json!({})))
+            .await
+            .expect("first call reaches upstream");
+        ok.assert_async().await;
+        ok.remove_async().await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        // Upstream fails: the expired answer is served and degraded mode starts
+        let down = server
+            .mock("POST", "/")
+            .with_status(503_usize)
+            .expect(2_usize)
+            .create_async()
+            .await;
+        let stale = client
+            .forward_request(search_request(2_i32, &json!({})))
+            .await
+            .expect("stale answer instead of the error");
+        assert_eq!(stale["id"], 2_i32);
+        assert_eq!(stale["result"]["_meta"][cache::STALE_META], true);
+        assert!(client.degraded.load(Ordering::Relaxed));
+
+        // While degraded, the stale answer comes back at once and is refreshed behind it
+        client
+            .forward_request(search_request(3_i32, &json!({})))
+            .await
+            .expect("stale answer");
+        wait_for_refreshes(&client).await;
+        down.assert_async().await;
+        down.remove_async().await;
+
+        let up = server
             .mock("POST", "/")
             .with_status(200_usize)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"result": {}}"#)
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"content": [{"type": "text", "text": "v2"}]}}"#)
             .expect(1_usize)
             .create_async()
             .await;
+        let still_stale = client
+            .forward_request(search_request(4_i32, &json!({})))
+            .await
+            .expect("stale answer");
+        assert_eq!(still_stale["result"]["content"][1]["text"], "v1");
+        wait_for_refreshes(&client).await;
 
-        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let request = json!({"method": "test"});
-
-        let result = client.forward_request(request).await;
-
-        mock1.assert_async().await;
-        mock2.assert_async().await;
+        up.assert_async().await;
         drop(server);
-        let _response = result.expect("successful response after retry");
+        assert!(!client.degraded.load(Ordering::Relaxed));
+        let (refreshed, _) = client
+            .responses
+            .get_stale(&cache::tool_call_key(&search_request(5_i32, &json!({}))).unwrap())
+            .unwrap();
+        assert_eq!(refreshed["result"]["content"][0]["text"], "v2");
     }
 
     #[tokio::test]
-    async fn retry_timing_exponential_backoff() {
+    async fn failure_without_cached_answer_is_an_error() {
         let mut server = mockito::Server::new_async().await;
-
-        // All requests fail to test backoff timing
         let mock = server
             .mock("POST", "/")
             .with_status(503_usize)
-            .with_body("Unavailable")
-            .expect(3_usize)
+            .expect(1_usize)
             .create_async()
             .await;
 
-        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let request = json!({"method": "test"});
-
-        let start = Instant::now();
-        let _result = client.forward_request(request).await;
-        let elapsed = start.elapsed();
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .retry(RetryPolicy::none());
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        let result = client
+            .forward_request(search_request(1_i32, &json!({})))
+            .await;
 
         mock.assert_async().await;
         drop(server);
-
-        // With 3 attempts and delays of 200 ms, 400 ms:
-        // Total should be at least 600 ms (200 + 400)
-        // But allow some margin for execution time
-        assert!(
-            elapsed.as_millis() >= 550_u128,
-            "Expected at least 600 ms for backoff, got {}ms",
-            elapsed.as_millis()
-        );
+        assert!(result.is_err());
+        assert!(!client.degraded.load(Ordering::Relaxed));
     }
 
     #[tokio::test]
-    async fn fetch_doc_markdown_success() {
+    async fn open_circuit_fails_fast_until_probe_succeeds() {
         let mut server = mockito::Server::new_async().await;
-
-        let mock = server
-            .mock("GET", "/docs/page")
-            .match_header("accept", "text/markdown")
-            .with_status(200_usize)
-            .with_header("content-type", "text/markdown")
-            .with_body("# Test MDX\n\nSome content")
-            .expect(1_usize)
+        let down = server
+            .mock("POST", "/")
+            .with_status(503_usize)
+            .expect(2_usize)
             .create_async()
             .await;
 
-        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let url = format!("{}/docs/page", server.url());
-
-        let result = client.fetch_doc_markdown(&url).await;
-
-        mock.assert_async().await;
-        drop(server);
-        assert!(result.is_ok());
-        let mdx = result.expect("successful MDX fetch");
-        assert!(mdx.contains("# Test MDX"));
-        assert!(mdx.contains("Some content"));
-    }
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .retry(RetryPolicy::none())
+            .circuit(
+                CircuitConfig::default()
+                    .failure_threshold(2_u32)
+                    .cooldown(Duration::from_millis(100)),
+            );
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        for id in 1_i32..=2_i32 {
+            client
+                .forward_request(search_request(id, &json!({})))
+                .await
+                .expect_err("upstream is down");
+        }
+        assert_eq!(client.circuit_status().state, circuit::CircuitState::Open);
 
-    #[tokio::test]
-    async fn fetch_doc_markdown_404_error() {
-        let mut server = mockito::Server::new_async().await;
+        // While open, calls fail without reaching upstream
+        let error = client
+            .forward_request(search_request(3_i32, &json!({})))
+            .await
+            .expect_err("circuit is open");
+        assert!(error.to_string().contains("circuit is open"), "{error}");
+        client
+            .fetch_doc_markdown(&format!("{}/docs/page", server.url()))
+            .await
+            .expect_err("circuit is open");
+        down.assert_async().await;
+        down.remove_async().await;
 
-        let mock = server
-            .mock("GET", "/docs/missing")
-            .with_status(404_usize)
-            .with_body("Not Found")
+        // After the cool-down a probe goes through and closes the circuit
+        let up = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"content": []}}"#)
             .expect(1_usize)
             .create_async()
             .await;
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(
+            client.circuit_status().state,
+            circuit::CircuitState::HalfOpen
+        );
+        client
+            .forward_request(search_request(4_i32, &json!({})))
+            .await
+            .expect("probe succeeds");
 
-        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let url = format!("{}/docs/missing", server.url());
-
-        let result = client.fetch_doc_markdown(&url).await;
-
-        mock.assert_async().await;
+        up.assert_async().await;
         drop(server);
-        assert!(result.is_err());
-        let error = result.expect_err("should be 404 error");
-        assert!(error.to_string().contains("404"));
+        let status = client.circuit_status();
+        assert_eq!(status.state, circuit::CircuitState::Closed);
+        assert_eq!(status.consecutive_failures, 0_u32);
     }
 
+    const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
     #[tokio::test]
-    async fn fetch_doc_markdown_500_error() {
+    async fn expired_page_is_revalidated_with_validators() {
         let mut server = mockito::Server::new_async().await;
-
-        let mock = server
-            .mock("GET", "/docs/error")
-            .with_status(500_usize)
-            .with_body("Internal Server Error")
+        let full = server
+            .mock("GET", "/docs/page")
+            .with_status(200_usize)
+            .with_header("etag", "\"abc\"")
+            .with_header("last-modified", LAST_MODIFIED)
+            .with_body("# Page")
             .expect(1_usize)
             .create_async()
             .await;
 
-        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let url = format!("{}/docs/error", server.url());
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .cache(CacheConfig::default().ttl(Duration::from_millis(1)));
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        let url = format!("{}/docs/page", server.url());
+        client.fetch_doc_markdown(&url).await.expect("page fetched");
+        full.assert_async().await;
+        full.remove_async().await;
 
-        let result = client.fetch_doc_markdown(&url).await;
+        // A 304 without validators keeps the ones already known
+        let not_modified = server
+            .mock("GET", "/docs/page")
+            .match_header("if-none-match", "\"abc\"")
+            .match_header("if-modified-since", LAST_MODIFIED)
+            .with_status(304_usize)
+            .expect(2_usize)
+            .create_async()
+            .await;
+        for _ in 0..2 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let text = client.fetch_doc_markdown(&url).await.expect("revalidated");
+            assert_eq!(text, "# Page");
+        }
 
-        mock.assert_async().await;
+        not_modified.assert_async().await;
         drop(server);
-        assert!(result.is_err());
-        let error = result.expect_err("should be 500 error");
-        assert!(error.to_string().contains("500"));
     }
 
     #[tokio::test]
-    async fn retry_with_transient_http_failure_logging() {
+    async fn changed_page_replaces_cached_copy() {
         let mut server = mockito::Server::new_async().await;
-
-        // First attempt: 503 error (transient)
-        let mock1 = server
-            .mock("POST", "/")
-            .with_status(503_usize)
-            .with_header("content-type", "text/plain")
-            .with_body("Service Unavailable")
+        let old = server
+            .mock("GET", "/docs/page")
+            .with_status(200_usize)
+            .with_header("etag", "\"v1\"")
+            .with_body("# Old")
             .expect(1_usize)
             .create_async()
             .await;
 
-        // Second attempt: Success
-        let mock2 = server
-            .mock("POST", "/")
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .cache(CacheConfig::default().ttl(Duration::from_millis(1)));
+        let client = BunDocsClient::with_config(config).expect("valid config");
+        let url = format!("{}/docs/page", server.url());
+        client.fetch_doc_markdown(&url).await.expect("page fetched");
+        old.assert_async().await;
+        old.remove_async().await;
+
+        let new = server
+            .mock("GET", "/docs/page")
+            .match_header("if-none-match", "\"v1\"")
             .with_status(200_usize)
-            .with_header("content-type", "application/json")
-            .with_body(r#"{"result": {"tools": []}}"#)
+            .with_header("etag", "\"v2\"")
+            .with_body("# New")
             .expect(1_usize)
             .create_async()
             .await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let text = client.fetch_doc_markdown(&url).await.expect("page changed");
 
-        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let request = json!({"method": "tools/list"});
-
-        let result = client.forward_request(request).await;
-
-        mock1.assert_async().await;
-        mock2.assert_async().await;
+        new.assert_async().await;
         drop(server);
-
-        assert!(result.is_ok(), "Should succeed after transient 503 retry");
-        let response = result.expect("successful response");
-        assert!(response.get("result").is_some());
-        // Verifies src/http.rs line 315-319: warn!("Transient HTTP status...")
-        // Verifies line 321: backoff_delay_ms calculation
-        // Verifies line 322: sleep execution
+        assert_eq!(text, "# New");
+        let (page, _) = client.pages.get_stale(&url).unwrap();
+        assert_eq!(page.validators.etag.as_deref(), Some("\"v2\""));
     }
 
     #[tokio::test]
-    async fn retry_on_multiple_transient_failures() {
+    async fn persisted_validators_are_used_by_a_new_client() {
         let mut server = mockito::Server::new_async().await;
-
-        // First attempt: 502 Bad Gateway
-        let mock1 = server
-            .mock("POST", "/")
-            .with_status(502_usize)
-            .with_body("Bad Gateway")
+        let full = server
+            .mock("GET", "/docs/page")
+            .with_status(200_usize)
+            .with_header("last-modified", LAST_MODIFIED)
+            .with_body("# Persisted")
             .expect(1_usize)
             .create_async()
             .await;
 
-        // Second attempt: 503 Service Unavailable
-        let mock2 = server
-            .mock("POST", "/")
-            .with_status(503_usize)
-            .with_body("Service Unavailable")
-            .expect(1_usize)
-            .create_async()
-            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let config = ClientConfig::default()
+            .base_url(server.url())
+            .disk_cache(DiskCacheConfig::new(dir.path()).ttl(Duration::ZERO));
+        let url = format!("{}/docs/page", server.url());
+        BunDocsClient::with_config(config.clone())
+            .expect("valid config")
+            .fetch_doc_markdown(&url)
+            .await
+            .expect("page fetched");
+        full.assert_async().await;
+        full.remove_async().await;
 
-        // Third attempt: 504 Gateway Timeout (transient)
-        let mock3 = server
-            .mock("POST", "/")
-            .with_status(504_usize)
-            .with_body("Gateway Timeout")
+        let not_modified = server
+            .mock("GET", "/docs/page")
+            .match_header("if-modified-since", LAST_MODIFIED)
+            .with_status(304_usize)
             .expect(1_usize)
             .create_async()
             .await;
+        let text = BunDocsClient::with_config(config)
+            .expect("valid config")
+            .fetch_doc_markdown(&url)
+            .await
+            .expect("revalidated from disk");
 
-        let client = BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-        let request = json!({"method": "test"});
-
-        let result = client.forward_request(request).await;
-
-        // All three mocks should have been called (exhausted retries)
-        mock1.assert_async().await;
-        mock2.assert_async().await;
-        mock3.assert_async().await;
+        not_modified.assert_async().await;
         drop(server);
-
-        assert!(result.is_err(), "Should fail after exhausting retries");
-        let error = result.expect_err("should be an error");
-        assert!(error.to_string().contains("504"));
-        // Verifies src/http.rs line 314: is_transient_status check for all 5xx codes
-        // Verifies line 317-318: retry condition check (attempt < MAX_RETRIES)
-        // Verifies line 321-322: backoff delays between attempts
+        assert_eq!(text, "# Persisted");
     }
 }
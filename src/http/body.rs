@@ -0,0 +1,207 @@
+//! Size limits for response bodies read from the Bun Docs API.
+//!
+//! A misbehaving or malicious server could send an endless body, so no body is read
+//! without a limit:
+//!
+//! - **JSON responses and pages** are read with [`read_limited`], which rejects a
+//!   `Content-Length` above the limit up front and otherwise counts bytes as they
+//!   stream in, failing with [`ResponseTooLarge`] as soon as the limit is passed.
+//! - **SSE streams** are wrapped in a [`LimitedStream`], which fails the stream once
+//!   more bytes than the limit have arrived on it.
+//! - **Error bodies** are only needed for a log snippet, so [`read_prefix`] keeps the
+//!   first bytes and stops reading.
+
+use super::retry::Failure;
+use bytes::Bytes;
+use core::fmt;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::{Stream, StreamExt as _};
+use tracing::warn;
+
+/// Default maximum size of a JSON-RPC response body or SSE response stream (bytes)
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Default maximum size of a fetched documentation page (bytes)
+pub const DEFAULT_MAX_PAGE_SIZE: usize = 4 * 1024 * 1024;
+
+/// Error returned when a response body grows beyond its size limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseTooLarge {
+    /// The limit that was exceeded, in bytes.
+    pub limit: usize,
+}
+
+impl fmt::Display for ResponseTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "response too large: body exceeds the limit of {} bytes",
+            self.limit
+        )
+    }
+}
+
+impl core::error::Error for ResponseTooLarge {}
+
+impl ResponseTooLarge {
+    /// Converts the error into a permanent [`Failure`], since retrying would fetch
+    /// the same body again.
+    pub fn into_failure(self, what: &str) -> Failure {
+        Failure::Permanent(anyhow::Error::new(self).context(what.to_owned()))
+    }
+}
+
+/// Reads a whole response body, failing once it grows beyond `limit` bytes.
+///
+/// # Arguments
+/// * `response` - Response whose body is read
+/// * `limit` - Maximum body size in bytes
+/// * `what` - Short description of the body for error messages
+///
+/// # Errors
+/// Returns a permanent [`Failure`] carrying [`ResponseTooLarge`] if the body is too
+/// large, or a transient one if reading it fails
+pub async fn read_limited(
+    response: reqwest::Response,
+    limit: usize,
+    what: &str,
+) -> Result<Vec<u8>, Failure> {
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(ResponseTooLarge { limit }.into_failure(what));
+    }
+    let mut body = Vec::new();
+    let mut chunks = response.bytes_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| Failure::from_send_error(&e, what))?;
+        if body.len() + chunk.len() > limit {
+            return Err(ResponseTooLarge { limit }.into_failure(what));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Reads at most the first `limit` bytes of a response body.
+///
+/// Failures are logged and end the body early.
+pub async fn read_prefix(response: reqwest::Response, limit: usize) -> Vec<u8> {
+    let mut body = Vec::new();
+    let mut chunks = response.bytes_stream();
+    while body.len() < limit {
+        match chunks.next().await {
+            Some(Ok(chunk)) => {
+                let take = chunk.len().min(limit - body.len());
+                body.extend_from_slice(&chunk[..take]);
+            }
+            Some(Err(error)) => {
+                warn!("Failed to read error response body: {}", error);
+                break;
+            }
+            None => break,
+        }
+    }
+    body
+}
+
+/// Byte stream that fails once more than `limit` bytes have passed through it
+pub struct LimitedStream<S> {
+    /// Underlying byte stream.
+    inner: S,
+    /// Maximum number of bytes passed through.
+    limit: usize,
+    /// Bytes passed through so far.
+    read: usize,
+    /// Set once the limit was exceeded; the stream ends after reporting it.
+    exceeded: bool,
+}
+
+impl<S> LimitedStream<S> {
+    /// Wraps `inner`, allowing at most `limit` bytes through.
+    pub const fn new(inner: S, limit: usize) -> Self {
+        Self {
+            inner,
+            limit,
+            read: 0_usize,
+            exceeded: false,
+        }
+    }
+
+    /// Returns the error if the stream failed because it exceeded its limit.
+    pub const fn too_large(&self) -> Option<ResponseTooLarge> {
+        if self.exceeded {
+            Some(ResponseTooLarge { limit: self.limit })
+        } else {
+            None
+        }
+    }
+}
+
+impl<S, E> Stream for LimitedStream<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<anyhow::Error>,
+{
+    type Item = Result<Bytes, anyhow::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.exceeded {
+            return Poll::Ready(None);
+        }
+        match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.read += chunk.len();
+                if self.read > self.limit {
+                    self.exceeded = true;
+                    let limit = self.limit;
+                    return Poll::Ready(Some(Err(anyhow::Error::new(ResponseTooLarge { limit }))));
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(Some(Err(error))) => Poll::Ready(Some(Err(error.into()))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+
+    fn chunks(sizes: &[usize]) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Unpin {
+        futures::stream::iter(sizes.iter().map(|&size| Ok(Bytes::from(vec![b'x'; size]))))
+    }
+
+    #[tokio::test]
+    async fn limited_stream_passes_bodies_within_the_limit() {
+        let mut stream = LimitedStream::new(chunks(&[4_usize, 6_usize]), 10_usize);
+        let mut total = 0_usize;
+        while let Some(chunk) = stream.next().await {
+            total += chunk.unwrap().len();
+        }
+        assert_eq!(total, 10_usize);
+        assert!(stream.too_large().is_none());
+    }
+
+    #[tokio::test]
+    async fn limited_stream_fails_once_the_limit_is_exceeded() {
+        let mut stream = LimitedStream::new(chunks(&[4_usize, 6_usize, 1_usize]), 8_usize);
+        stream.next().await.unwrap().unwrap();
+        let error = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ResponseTooLarge>(),
+            Some(&ResponseTooLarge { limit: 8_usize })
+        );
+        assert!(stream.next().await.is_none(), "stream ends after the error");
+        assert_eq!(
+            stream.too_large(),
+            Some(ResponseTooLarge { limit: 8_usize })
+        );
+    }
+}
@@ -0,0 +1,521 @@
+//! In-memory response cache for [`super::BunDocsClient`].
+//!
+//! Agents tend to repeat the same search many times in one session, so successful
+//! `tools/call` responses and fetched documentation pages are kept in a
+//! [`ResponseCache`]:
+//!
+//! - **Keys** - tool calls are keyed on the tool name plus the arguments serialized
+//!   with sorted keys (so argument order does not matter, and `_meta` is ignored);
+//!   pages are keyed on their URL.
+//! - **Expiry** - entries older than the TTL are treated as misses, but kept until
+//!   evicted so they can still be served, marked stale, while upstream is
+//!   unreachable (see [`mark_stale`]).
+//! - **Size limits** - both the number of entries and their total size in bytes are
+//!   bounded; the least recently used entries are evicted first.
+//! - **Bypass** - a `tools/call` whose `params._meta` contains
+//!   `"bun-docs-mcp-proxy/no-cache": true` skips the lookup and refreshes the entry.
+//!
+//! Hits and misses are counted and reported through [`ResponseCache::stats`].
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Default maximum number of cached responses
+pub const DEFAULT_CACHE_ENTRIES: usize = 256_usize;
+
+/// Default maximum total size of cached responses (bytes)
+pub const DEFAULT_CACHE_MAX_BYTES: usize = 8_000_000_usize;
+
+/// Default time a cached response stays fresh (seconds)
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 300_u64;
+
+/// `_meta` key that makes a `tools/call` bypass the cache
+pub const NO_CACHE_META: &str = "bun-docs-mcp-proxy/no-cache";
+
+/// `_meta` key set on results served from an expired cache entry
+pub const STALE_META: &str = "bun-docs-mcp-proxy/stale";
+
+/// Size limits and expiry for a [`ResponseCache`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Maximum number of entries; 0 disables the cache.
+    max_entries: usize,
+    /// Maximum total size of all entries in bytes.
+    max_bytes: usize,
+    /// How long an entry stays fresh.
+    ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: DEFAULT_CACHE_ENTRIES,
+            max_bytes: DEFAULT_CACHE_MAX_BYTES,
+            ttl: Duration::from_secs(DEFAULT_CACHE_TTL_SECS),
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Sets the maximum number of entries; 0 disables the cache.
+    #[must_use]
+    pub const fn max_entries(mut self, entries: usize) -> Self {
+        self.max_entries = entries;
+        self
+    }
+
+    /// Sets the maximum total size of all entries in bytes.
+    #[must_use]
+    pub const fn max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Sets how long an entry stays fresh.
+    #[must_use]
+    pub const fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Returns whether anything is cached at all.
+    const fn is_enabled(&self) -> bool {
+        self.max_entries > 0_usize && self.max_bytes > 0_usize && !self.ttl.is_zero()
+    }
+}
+
+/// Counters and current size of a [`ResponseCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups answered from the cache.
+    pub hits: u64,
+    /// Lookups that found nothing fresh.
+    pub misses: u64,
+    /// Entries currently stored.
+    pub entries: usize,
+    /// Total size of the stored entries in bytes.
+    pub bytes: usize,
+}
+
+impl core::ops::Add for CacheStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+            entries: self.entries + other.entries,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+}
+
+/// LRU cache with a TTL, shared by all requests of one client
+pub struct ResponseCache<V> {
+    /// Size limits and expiry.
+    config: CacheConfig,
+    /// Stored entries.
+    state: Mutex<CacheState<V>>,
+    /// Lookups answered from the cache.
+    hits: AtomicU64,
+    /// Lookups that found nothing fresh.
+    misses: AtomicU64,
+}
+
+/// Entries of a [`ResponseCache`] and their bookkeeping.
+struct CacheState<V> {
+    /// Entries by key.
+    entries: HashMap<String, CacheEntry<V>>,
+    /// Total size of all entries in bytes.
+    bytes: usize,
+    /// Counter ordering entries by last use.
+    clock: u64,
+}
+
+/// One cached value.
+struct CacheEntry<V> {
+    /// The cached value.
+    value: V,
+    /// Size of the value in bytes.
+    size: usize,
+    /// When the value was stored.
+    stored: Instant,
+    /// Value of the clock when the entry was last used.
+    used: u64,
+}
+
+impl<V: Clone> ResponseCache<V> {
+    /// Creates an empty cache.
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                bytes: 0_usize,
+                clock: 0_u64,
+            }),
+            hits: AtomicU64::new(0_u64),
+            misses: AtomicU64::new(0_u64),
+        }
+    }
+
+    /// Looks up a fresh entry; expired entries count as misses.
+    pub fn get(&self, key: &str) -> Option<V> {
+        if !self.config.is_enabled() {
+            return None;
+        }
+        let fresh = self
+            .get_stale(key)
+            .filter(|(_, age)| *age < self.config.ttl)
+            .map(|(value, _)| value);
+
+        let counter = if fresh.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1_u64, Ordering::Relaxed);
+        fresh
+    }
+
+    /// Looks up an entry whatever its age, without counting a hit or miss.
+    ///
+    /// # Returns
+    /// The value and how long ago it was stored
+    pub fn get_stale(&self, key: &str) -> Option<(V, Duration)> {
+        if !self.config.is_enabled() {
+            return None;
+        }
+        let mut guard = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let state = &mut *guard;
+        state.clock += 1_u64;
+        let found = state.entries.get_mut(key).map(|entry| {
+            entry.used = state.clock;
+            (entry.value.clone(), entry.stored.elapsed())
+        });
+        drop(guard);
+        found
+    }
+
+    /// Stores `value`, evicting the least recently used entries to stay within limits.
+    ///
+    /// Values larger than the whole cache are not stored.
+    ///
+    /// # Arguments
+    /// * `key` - Cache key
+    /// * `value` - Value to store
+    /// * `size` - Size of the value in bytes, counted against the byte limit
+    pub fn insert(&self, key: String, value: V, size: usize) {
+        if !self.config.is_enabled() || size > self.config.max_bytes {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.remove(&key);
+        while state.entries.len() >= self.config.max_entries
+            || state.bytes + size > self.config.max_bytes
+        {
+            if !state.evict_least_recently_used() {
+                break;
+            }
+        }
+        state.clock += 1_u64;
+        let used = state.clock;
+        state.bytes += size;
+        state.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                size,
+                stored: Instant::now(),
+                used,
+            },
+        );
+    }
+
+    /// Returns the hit/miss counters and current size.
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: state.entries.len(),
+            bytes: state.bytes,
+        }
+    }
+}
+
+impl<V> CacheState<V> {
+    /// Removes the entry for `key`, if any.
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.size;
+        }
+    }
+
+    /// Removes the least recently used entry.
+    ///
+    /// # Returns
+    /// `false` if the cache was already empty
+    fn evict_least_recently_used(&mut self) -> bool {
+        let Some(key) = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.used)
+            .map(|(key, _)| key.clone())
+        else {
+            return false;
+        };
+        self.remove(&key);
+        true
+    }
+}
+
+/// Builds the cache key for a JSON-RPC request, if its response may be cached.
+///
+/// Only `tools/call` requests are cacheable. The key combines the tool name with the
+/// arguments serialized with sorted object keys; `_meta` is not part of the key.
+pub fn tool_call_key(request: &Value) -> Option<String> {
+    if request.get("method").and_then(Value::as_str) != Some("tools/call") {
+        return None;
+    }
+    let params = request.get("params")?;
+    let name = params.get("name")?.as_str()?;
+    let arguments = params.get("arguments").unwrap_or(&Value::Null);
+    // `serde_json::Map` keeps its keys sorted, so equal arguments serialize equally
+    Some(format!("tools/call {name} {arguments}"))
+}
+
+/// Returns whether a request asks to bypass the cache via `params._meta`.
+pub fn bypasses_cache(request: &Value) -> bool {
+    request
+        .pointer("/params/_meta")
+        .and_then(|meta| meta.get(NO_CACHE_META))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Marks a cached `tools/call` response as stale.
+///
+/// Prepends a note to the result content, so the reader sees it, and sets
+/// [`STALE_META`] in the result's `_meta`.
+///
+/// # Arguments
+/// * `response` - Cached JSON-RPC response
+/// * `age` - How long ago the response was fetched
+/// * `reason` - Why no fresh response was fetched, e.g. "offline mode is enabled"
+pub fn mark_stale(mut response: Value, age: Duration, reason: &str) -> Value {
+    let Some(result) = response.get_mut("result").and_then(Value::as_object_mut) else {
+        return response;
+    };
+    if let Some(content) = result.get_mut("content").and_then(Value::as_array_mut) {
+        content.insert(
+            0_usize,
+            serde_json::json!({"type": "text", "text": stale_note(age, reason)}),
+        );
+    }
+    let meta = result
+        .entry("_meta")
+        .or_insert_with(|| Value::Object(serde_json::Map::new()));
+    if let Some(meta) = meta.as_object_mut() {
+        meta.insert(STALE_META.to_owned(), Value::Bool(true));
+    }
+    response
+}
+
+/// Marks a cached documentation page as stale with a leading HTML comment.
+pub fn mark_stale_page(page: &str, age: Duration, reason: &str) -> String {
+    format!("<!-- {} -->\n\n{page}", stale_note(age, reason))
+}
+
+/// Describes why and since when a cached answer is being served.
+fn stale_note(age: Duration, reason: &str) -> String {
+    let secs = age.as_secs();
+    let (amount, unit) = match secs {
+        0..60 => (secs, "second"),
+        60..3_600 => (secs / 60, "minute"),
+        3_600..86_400 => (secs / 3_600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    format!(
+        "Stale result: served from a cache entry fetched {amount} {unit}{plural} ago because {reason}; it may be out of date."
+    )
+}
+
+/// Returns whether a JSON-RPC response is a success worth caching.
+///
+/// Errors, including tool results flagged with `isError`, are not cached.
+pub fn is_cacheable_response(response: &Value) -> bool {
+    response
+        .get("result")
+        .is_some_and(|result| result.get("isError").and_then(Value::as_bool) != Some(true))
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cache(entries: usize, bytes: usize) -> ResponseCache<String> {
+        ResponseCache::new(CacheConfig::default().max_entries(entries).max_bytes(bytes))
+    }
+
+    #[test]
+    fn hit_and_miss_are_counted() {
+        let cache = cache(4, 1000);
+        assert_eq!(cache.get("a"), None);
+        cache.insert("a".to_owned(), "one".to_owned(), 3);
+        assert_eq!(cache.get("a").as_deref(), Some("one"));
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.bytes, 3);
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let cache = cache(2, 1000);
+        cache.insert("a".to_owned(), "a".to_owned(), 1);
+        cache.insert("b".to_owned(), "b".to_owned(), 1);
+        // Touch "a" so "b" becomes the least recently used
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_owned(), "c".to_owned(), 1);
+
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+
+    #[test]
+    fn byte_limit_evicts_and_rejects_oversized_values() {
+        let cache = cache(10, 10);
+        cache.insert("a".to_owned(), "a".to_owned(), 6);
+        cache.insert("b".to_owned(), "b".to_owned(), 6);
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+
+        cache.insert("huge".to_owned(), "huge".to_owned(), 11);
+        assert!(cache.get("huge").is_none());
+        assert_eq!(cache.stats().bytes, 6);
+    }
+
+    #[test]
+    fn replacing_an_entry_keeps_byte_count() {
+        let cache = cache(10, 100);
+        cache.insert("a".to_owned(), "old".to_owned(), 30);
+        cache.insert("a".to_owned(), "new".to_owned(), 20);
+        assert_eq!(cache.get("a").as_deref(), Some("new"));
+        assert_eq!(cache.stats().bytes, 20);
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn expired_entries_are_misses_but_kept_for_stale_use() {
+        let cache: ResponseCache<String> =
+            ResponseCache::new(CacheConfig::default().ttl(Duration::from_millis(20)));
+        cache.insert("a".to_owned(), "a".to_owned(), 1);
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.stats().misses, 1);
+
+        let (value, age) = cache.get_stale("a").expect("expired entry kept");
+        assert_eq!(value, "a");
+        assert!(age >= Duration::from_millis(40));
+        assert!(cache.get_stale("b").is_none());
+    }
+
+    #[test]
+    fn stale_responses_are_marked() {
+        let response = json!({"id": 1, "result": {"content": [{"type": "text", "text": "docs"}]}});
+        let marked = mark_stale(
+            response,
+            Duration::from_secs(150),
+            "offline mode is enabled",
+        );
+
+        let content = marked["result"]["content"].as_array().unwrap();
+        assert_eq!(content.len(), 2);
+        let note = content[0]["text"].as_str().unwrap();
+        assert!(note.contains("2 minutes ago"), "{note}");
+        assert!(note.contains("offline mode is enabled"), "{note}");
+        assert_eq!(content[1]["text"], "docs");
+        assert_eq!(marked["result"]["_meta"][STALE_META], true);
+
+        let error = json!({"error": {"code": -32603, "message": "boom"}});
+        assert_eq!(mark_stale(error.clone(), Duration::ZERO, "x"), error);
+    }
+
+    #[test]
+    fn stale_note_units() {
+        assert!(stale_note(Duration::from_secs(1), "r").contains("1 second ago"));
+        assert!(stale_note(Duration::from_hours(2), "r").contains("2 hours ago"));
+        assert!(stale_note(Duration::from_hours(24), "r").contains("1 day ago"));
+        assert!(mark_stale_page("# Page", Duration::ZERO, "r").ends_with("-->\n\n# Page"));
+    }
+
+    #[test]
+    fn disabled_cache_stores_nothing() {
+        let cache: ResponseCache<String> =
+            ResponseCache::new(CacheConfig::default().max_entries(0));
+        cache.insert("a".to_owned(), "a".to_owned(), 1);
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn tool_call_key_ignores_argument_order_and_meta() {
+        let first = json!({
+            "id": 1,
+            "method": "tools/call",
+            "params": {"name": "SearchBun", "arguments": {"query": "Bun.serve", "limit": 5}}
+        });
+        let second = json!({
+            "id": 2,
+            "method": "tools/call",
+            "params": {
+                "_meta": {"progressToken": "t"},
+                "arguments": {"limit": 5, "query": "Bun.serve"},
+                "name": "SearchBun"
+            }
+        });
+        assert_eq!(tool_call_key(&first), tool_call_key(&second));
+        assert!(tool_call_key(&first).unwrap().contains("SearchBun"));
+
+        let other = json!({
+            "method": "tools/call",
+            "params": {"name": "SearchBun", "arguments": {"query": "Bun.file"}}
+        });
+        assert_ne!(tool_call_key(&first), tool_call_key(&other));
+        assert_eq!(tool_call_key(&json!({"method": "tools/list"})), None);
+    }
+
+    #[test]
+    fn bypass_flag_in_meta() {
+        let request = json!({
+            "method": "tools/call",
+            "params": {"name": "SearchBun", "_meta": {NO_CACHE_META: true}}
+        });
+        assert!(bypasses_cache(&request));
+        assert!(!bypasses_cache(
+            &json!({"method": "tools/call", "params": {}})
+        ));
+    }
+
+    #[test]
+    fn only_successful_results_are_cacheable() {
+        assert!(is_cacheable_response(&json!({"result": {"content": []}})));
+        assert!(!is_cacheable_response(
+            &json!({"result": {"content": [], "isError": true}})
+        ));
+        assert!(!is_cacheable_response(
+            &json!({"error": {"code": -32603, "message": "boom"}})
+        ));
+    }
+}
@@ -0,0 +1,376 @@
+//! Circuit breaker shared by every upstream call of [`super::BunDocsClient`].
+//!
+//! While the Bun Docs API is down, running each call through the full retry schedule
+//! makes every tool call take seconds to fail. A [`CircuitBreaker`] counts calls that
+//! failed even after retrying and moves between three states:
+//!
+//! - **Closed** - calls go through. After [`CircuitConfig::failure_threshold`]
+//!   consecutive failed calls the circuit opens.
+//! - **Open** - calls fail at once with [`CircuitOpen`] (the client then serves
+//!   cached answers where it has them) until the cool-down has passed.
+//! - **Half-open** - a single probe call goes through while the others keep failing
+//!   fast. If it succeeds the circuit closes, otherwise it opens for another
+//!   cool-down.
+//!
+//! A call only counts as failed when its last attempt failed transiently (network
+//! error, 429 or 5xx); any other answer shows the API is up. Every state change is
+//! logged, and [`CircuitBreaker::status`] reports the current state.
+
+use core::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Default number of consecutive failed calls that opens the circuit
+pub const DEFAULT_CIRCUIT_THRESHOLD: u32 = 5_u32;
+
+/// Default time the circuit stays open before a probe call is let through (seconds)
+pub const DEFAULT_CIRCUIT_COOLDOWN_SECS: u64 = 30_u64;
+
+/// Failure threshold and cool-down of a [`CircuitBreaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitConfig {
+    /// Consecutive failed calls that open the circuit; 0 never opens it.
+    failure_threshold: u32,
+    /// How long the circuit stays open before a probe call is let through.
+    cooldown: Duration,
+}
+
+impl Default for CircuitConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: DEFAULT_CIRCUIT_THRESHOLD,
+            cooldown: Duration::from_secs(DEFAULT_CIRCUIT_COOLDOWN_SECS),
+        }
+    }
+}
+
+impl CircuitConfig {
+    /// Sets the number of consecutive failed calls that opens the circuit; 0 never
+    /// opens it.
+    #[must_use]
+    pub const fn failure_threshold(mut self, failures: u32) -> Self {
+        self.failure_threshold = failures;
+        self
+    }
+
+    /// Sets how long the circuit stays open before a probe call is let through.
+    #[must_use]
+    pub const fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+}
+
+/// State of a [`CircuitBreaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through.
+    Closed,
+    /// Calls fail fast until the cool-down has passed.
+    Open,
+    /// The cool-down has passed; the next call probes the API.
+    HalfOpen,
+}
+
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half-open",
+        })
+    }
+}
+
+/// Snapshot of a [`CircuitBreaker`], see [`CircuitBreaker::status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitStatus {
+    /// Current state.
+    pub state: CircuitState,
+    /// Failed calls since the last successful one.
+    pub consecutive_failures: u32,
+    /// Time left until a probe call is let through, while open.
+    pub retry_in: Option<Duration>,
+}
+
+/// Error returned instead of calling the API while the circuit is open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitOpen {
+    /// Time left until a probe call is let through; zero while a probe is running.
+    pub retry_in: Duration,
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.retry_in.is_zero() {
+            f.write_str("Bun Docs API circuit is half-open and a probe request is running")
+        } else {
+            write!(
+                f,
+                "Bun Docs API circuit is open after repeated failures; retrying in {} s",
+                self.retry_in.as_secs().max(1_u64)
+            )
+        }
+    }
+}
+
+impl core::error::Error for CircuitOpen {}
+
+/// Mutable state of a [`CircuitBreaker`].
+#[derive(Debug, Default)]
+struct Circuit {
+    /// Failed calls since the last successful one.
+    failures: u32,
+    /// When the circuit last opened, while it is open or half-open.
+    opened_at: Option<Instant>,
+    /// Set while a half-open probe call is running.
+    probing: bool,
+}
+
+/// Closed/open/half-open circuit breaker, shared by all calls of one client
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// Failure threshold and cool-down.
+    config: CircuitConfig,
+    /// Current state.
+    circuit: Mutex<Circuit>,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed circuit breaker.
+    pub fn new(config: CircuitConfig) -> Self {
+        Self {
+            config,
+            circuit: Mutex::new(Circuit::default()),
+        }
+    }
+
+    /// Asks to make a call.
+    ///
+    /// # Returns
+    /// A [`Permit`] to report the outcome of the call on
+    ///
+    /// # Errors
+    /// Returns [`CircuitOpen`] while the circuit is open, or while it is half-open and
+    /// another call is already probing the API
+    pub fn acquire(&self) -> Result<Permit<'_>, CircuitOpen> {
+        let mut circuit = self.lock();
+        let Some(opened_at) = circuit.opened_at else {
+            return Ok(Permit::new(self, false));
+        };
+        let retry_in = self.config.cooldown.saturating_sub(opened_at.elapsed());
+        if !retry_in.is_zero() {
+            return Err(CircuitOpen { retry_in });
+        }
+        if circuit.probing {
+            return Err(CircuitOpen {
+                retry_in: Duration::ZERO,
+            });
+        }
+        circuit.probing = true;
+        drop(circuit);
+        info!("Bun Docs API circuit half-open: probing with the next request");
+        Ok(Permit::new(self, true))
+    }
+
+    /// Returns the current state, failure count and remaining cool-down.
+    pub fn status(&self) -> CircuitStatus {
+        let circuit = self.lock();
+        let consecutive_failures = circuit.failures;
+        let remaining = circuit
+            .opened_at
+            .map(|opened_at| self.config.cooldown.saturating_sub(opened_at.elapsed()));
+        drop(circuit);
+        let state = match remaining {
+            None => CircuitState::Closed,
+            Some(left) if !left.is_zero() => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        };
+        CircuitStatus {
+            state,
+            consecutive_failures,
+            retry_in: remaining.filter(|left| !left.is_zero()),
+        }
+    }
+
+    /// Records a successful call, closing the circuit.
+    fn record_success(&self) {
+        let mut circuit = self.lock();
+        let was_open = circuit.opened_at.is_some();
+        *circuit = Circuit::default();
+        drop(circuit);
+        if was_open {
+            info!("Bun Docs API circuit closed: the API answered again");
+        }
+    }
+
+    /// Records a failed call, opening the circuit at the threshold or when a probe
+    /// failed.
+    fn record_failure(&self, probe: bool) {
+        let mut circuit = self.lock();
+        circuit.failures = circuit.failures.saturating_add(1_u32);
+        let failures = circuit.failures;
+        if probe {
+            circuit.probing = false;
+            circuit.opened_at = Some(Instant::now());
+            drop(circuit);
+            warn!(
+                "Bun Docs API circuit re-opened: probe request failed; failing fast for {} s",
+                self.config.cooldown.as_secs()
+            );
+        } else if circuit.opened_at.is_none()
+            && self.config.failure_threshold > 0_u32
+            && failures >= self.config.failure_threshold
+        {
+            circuit.opened_at = Some(Instant::now());
+            drop(circuit);
+            warn!(
+                "Bun Docs API circuit opened after {} consecutive failures; failing fast for {} s",
+                failures,
+                self.config.cooldown.as_secs()
+            );
+        }
+    }
+
+    /// Lets another call probe the API after a probe was cancelled.
+    fn release_probe(&self) {
+        self.lock().probing = false;
+    }
+
+    /// Locks the state, ignoring poisoning.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Circuit> {
+        self.circuit.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Permission to make one call; report its outcome with [`Permit::succeeded`] or
+/// [`Permit::failed`]. Dropping it unreported (e.g. on cancellation) counts neither
+/// way.
+#[derive(Debug)]
+pub struct Permit<'breaker> {
+    /// Circuit breaker that granted the permit.
+    breaker: &'breaker CircuitBreaker,
+    /// Whether this call probes a half-open circuit.
+    probe: bool,
+    /// Set once the outcome has been reported.
+    settled: bool,
+}
+
+impl<'breaker> Permit<'breaker> {
+    /// Creates an unreported permit.
+    const fn new(breaker: &'breaker CircuitBreaker, probe: bool) -> Self {
+        Self {
+            breaker,
+            probe,
+            settled: false,
+        }
+    }
+
+    /// Reports that the API answered.
+    pub fn succeeded(mut self) {
+        self.settled = true;
+        self.breaker.record_success();
+    }
+
+    /// Reports that the call failed even after retrying.
+    pub fn failed(mut self) {
+        self.settled = true;
+        self.breaker.record_failure(self.probe);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if !self.settled && self.probe {
+            self.breaker.release_probe();
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+
+    fn breaker(threshold: u32, cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(
+            CircuitConfig::default()
+                .failure_threshold(threshold)
+                .cooldown(cooldown),
+        )
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let circuit = breaker(2_u32, Duration::from_secs(30));
+        circuit.acquire().unwrap().failed();
+        assert_eq!(circuit.status().state, CircuitState::Closed);
+        circuit.acquire().unwrap().failed();
+
+        let status = circuit.status();
+        assert_eq!(status.state, CircuitState::Open);
+        assert_eq!(status.consecutive_failures, 2_u32);
+        assert!(status.retry_in.is_some());
+        let open = circuit.acquire().unwrap_err();
+        assert!(open.retry_in > Duration::ZERO);
+        assert!(open.to_string().contains("circuit is open"));
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let circuit = breaker(2_u32, Duration::from_secs(30));
+        circuit.acquire().unwrap().failed();
+        circuit.acquire().unwrap().succeeded();
+        circuit.acquire().unwrap().failed();
+        assert_eq!(circuit.status().state, CircuitState::Closed);
+        assert_eq!(circuit.status().consecutive_failures, 1_u32);
+    }
+
+    #[test]
+    fn half_open_lets_one_probe_through() {
+        let circuit = breaker(1_u32, Duration::ZERO);
+        circuit.acquire().unwrap().failed();
+        assert_eq!(circuit.status().state, CircuitState::HalfOpen);
+
+        let probe = circuit.acquire().unwrap();
+        assert_eq!(circuit.acquire().unwrap_err().retry_in, Duration::ZERO);
+        probe.succeeded();
+        assert_eq!(circuit.status().state, CircuitState::Closed);
+        assert_eq!(circuit.status().consecutive_failures, 0_u32);
+    }
+
+    #[test]
+    fn failed_probe_reopens_the_circuit() {
+        let circuit = breaker(1_u32, Duration::from_millis(20));
+        circuit.acquire().unwrap().failed();
+        std::thread::sleep(Duration::from_millis(30));
+
+        circuit.acquire().unwrap().failed();
+        assert_eq!(circuit.status().state, CircuitState::Open);
+        circuit.acquire().unwrap_err();
+    }
+
+    #[test]
+    fn cancelled_probe_lets_another_call_probe() {
+        let circuit = breaker(1_u32, Duration::ZERO);
+        circuit.acquire().unwrap().failed();
+
+        drop(circuit.acquire().unwrap());
+        circuit
+            .acquire()
+            .expect("probe slot is free again")
+            .succeeded();
+        assert_eq!(circuit.status().state, CircuitState::Closed);
+    }
+
+    #[test]
+    fn zero_threshold_never_opens() {
+        let circuit = breaker(0_u32, Duration::from_secs(30));
+        for _ in 0_u32..10_u32 {
+            circuit.acquire().unwrap().failed();
+        }
+        assert_eq!(circuit.status().state, CircuitState::Closed);
+    }
+}
@@ -0,0 +1,565 @@
+//! Persistent response cache shared by all proxy processes of a user.
+//!
+//! The in-memory [`super::ResponseCache`] is lost when an editor session ends. The
+//! [`DiskCache`] keeps successful `tools/call` responses and documentation pages under
+//! `$XDG_CACHE_HOME/bun-docs-mcp-proxy` (see [`default_dir`]) so a new session starts
+//! warm:
+//!
+//! - **Entries** - one JSON file per entry, named after a hash of the source URL and
+//!   cache key, holding the body plus metadata (fetch time, [`Validators`], source
+//!   URL).
+//! - **Atomic writes** - entries are written to a temporary file and renamed into
+//!   place, so concurrent proxies never read half-written files.
+//! - **Size cap** - once the entries exceed the byte limit the least recently used
+//!   ones (by file modification time, refreshed on every hit) are deleted.
+//! - **Schema** - every entry records [`CACHE_SCHEMA`]; entries written by another
+//!   version are migrated on read, or dropped if they cannot be.
+//!
+//! Disk errors never fail a request: they are logged and the entry is treated as
+//! missing.
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// Name of the cache directory below the user's cache home
+const CACHE_DIR_NAME: &str = "bun-docs-mcp-proxy";
+
+/// Default maximum total size of the on-disk cache (bytes)
+pub const DEFAULT_DISK_CACHE_MAX_BYTES: u64 = 50_000_000_u64;
+
+/// Default time an on-disk entry stays fresh (seconds)
+pub const DEFAULT_DISK_CACHE_TTL_SECS: u64 = 86_400_u64;
+
+/// Version of the on-disk entry format
+pub const CACHE_SCHEMA: u64 = 2_u64;
+
+/// Extension of entry files
+const ENTRY_EXTENSION: &str = "json";
+
+/// Extension of files that are still being written
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Returns the default cache directory.
+///
+/// Uses `$XDG_CACHE_HOME`, falling back to `~/.cache` (or `%LOCALAPPDATA%` on
+/// Windows). Returns `None` if none of them is set.
+pub fn default_dir() -> Option<PathBuf> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    let base = non_empty("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| non_empty("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(base.join(CACHE_DIR_NAME))
+}
+
+/// Location, size limit and expiry of a [`DiskCache`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskCacheConfig {
+    /// Directory holding the entry files.
+    dir: PathBuf,
+    /// Maximum total size of all entry files in bytes.
+    max_bytes: u64,
+    /// How long an entry stays fresh.
+    ttl: Duration,
+}
+
+impl DiskCacheConfig {
+    /// Creates a configuration with default limits for the cache in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: DEFAULT_DISK_CACHE_MAX_BYTES,
+            ttl: Duration::from_secs(DEFAULT_DISK_CACHE_TTL_SECS),
+        }
+    }
+
+    /// Sets the maximum total size of all entry files in bytes.
+    #[must_use]
+    pub const fn max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Sets how long an entry stays fresh.
+    #[must_use]
+    pub const fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+/// Validators returned with a response, sent back on conditional requests
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    /// `ETag` header, sent back as `If-None-Match`.
+    pub etag: Option<String>,
+    /// `Last-Modified` header, sent back as `If-Modified-Since`.
+    pub last_modified: Option<String>,
+}
+
+/// One cached response as stored on disk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskEntry {
+    /// Version of the entry format, see [`CACHE_SCHEMA`].
+    pub schema: u64,
+    /// URL the body was fetched from.
+    pub url: String,
+    /// Cache key within that URL (empty for pages).
+    pub key: String,
+    /// When the body was fetched, in seconds since the Unix epoch.
+    pub fetched_at: u64,
+    /// Validators returned with the body.
+    #[serde(flatten)]
+    pub validators: Validators,
+    /// The cached response body.
+    pub body: String,
+}
+
+impl DiskEntry {
+    /// Creates an entry fetched now.
+    pub fn new(url: &str, key: &str, validators: Validators, body: String) -> Self {
+        Self {
+            schema: CACHE_SCHEMA,
+            url: url.to_owned(),
+            key: key.to_owned(),
+            fetched_at: unix_now(),
+            validators,
+            body,
+        }
+    }
+
+    /// Returns how long ago the body was fetched.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.fetched_at))
+    }
+}
+
+/// On-disk LRU cache with a size cap
+pub struct DiskCache {
+    /// Location, size limit and expiry.
+    config: DiskCacheConfig,
+    /// Total size of the entry files, counted on first use.
+    bytes: Mutex<Option<u64>>,
+}
+
+impl DiskCache {
+    /// Opens the cache, creating its directory if needed.
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created
+    pub fn open(config: DiskCacheConfig) -> Result<Self> {
+        fs::create_dir_all(&config.dir).with_context(|| {
+            format!("Failed to create cache directory {}", config.dir.display())
+        })?;
+        Ok(Self {
+            config,
+            bytes: Mutex::new(None),
+        })
+    }
+
+    /// Returns the directory holding the entry files.
+    pub fn dir(&self) -> &Path {
+        &self.config.dir
+    }
+
+    /// Returns whether `entry` is younger than the configured TTL.
+    pub fn is_fresh(&self, entry: &DiskEntry) -> bool {
+        entry.age() < self.config.ttl
+    }
+
+    /// Looks up the entry for `key` fetched from `url`, whatever its age.
+    ///
+    /// A hit marks the entry as recently used. Unreadable entries and entries that
+    /// cannot be migrated to [`CACHE_SCHEMA`] are deleted.
+    pub fn get(&self, url: &str, key: &str) -> Option<DiskEntry> {
+        let path = self.entry_path(url, key);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) => {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Failed to read cache entry {}: {}", path.display(), error);
+                }
+                return None;
+            }
+        };
+
+        let Some(entry) = serde_json::from_str::<Value>(&text)
+            .ok()
+            .and_then(migrate)
+            .and_then(|value| serde_json::from_value::<DiskEntry>(value).ok())
+        else {
+            debug!("Dropping unreadable cache entry {}", path.display());
+            self.remove(&path);
+            return None;
+        };
+        // Different URL/key pairs can hash to the same file name
+        if entry.url != url || entry.key != key {
+            return None;
+        }
+
+        if let Err(error) = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            debug!(
+                "Failed to mark cache entry {} as used: {}",
+                path.display(),
+                error
+            );
+        }
+        Some(entry)
+    }
+
+    /// Stores `entry`, then evicts the least recently used entries if the cache
+    /// grew beyond its size limit. Failures are logged and otherwise ignored.
+    pub fn put(&self, entry: &DiskEntry) {
+        if let Err(error) = self.try_put(entry) {
+            warn!("Failed to write cache entry for {}: {:#}", entry.url, error);
+        }
+    }
+
+    /// Writes `entry` atomically and enforces the size limit.
+    fn try_put(&self, entry: &DiskEntry) -> Result<()> {
+        let text = serde_json::to_string(entry).context("Failed to serialize cache entry")?;
+        let size = text.len() as u64;
+        if size > self.config.max_bytes {
+            debug!("Not caching {} bytes from {} on disk", size, entry.url);
+            return Ok(());
+        }
+
+        let path = self.entry_path(&entry.url, &entry.key);
+        let temp = path.with_extension(format!(
+            "{}.{}.{TEMP_EXTENSION}",
+            std::process::id(),
+            getrandom::u64().unwrap_or_default()
+        ));
+        let written = File::create(&temp).and_then(|mut file| {
+            file.write_all(text.as_bytes())?;
+            file.sync_all()
+        });
+        let replaced = fs::metadata(&path).map_or(0_u64, |metadata| metadata.len());
+        if let Err(error) = written.and_then(|()| fs::rename(&temp, &path)) {
+            self.remove(&temp);
+            return Err(error)
+                .with_context(|| format!("Failed to write cache entry {}", path.display()));
+        }
+
+        let mut bytes = self.bytes.lock().unwrap_or_else(PoisonError::into_inner);
+        // The first write counts the files already on disk, including this one
+        let total = bytes.map_or_else(
+            || self.scan().iter().map(|file| file.size).sum(),
+            |total| total.saturating_sub(replaced) + size,
+        );
+        *bytes = Some(if total > self.config.max_bytes {
+            self.evict(total)
+        } else {
+            total
+        });
+        drop(bytes);
+        Ok(())
+    }
+
+    /// Deletes the least recently used entry files until the total size is within
+    /// the limit.
+    ///
+    /// # Returns
+    /// The total size of the remaining files
+    fn evict(&self, total: u64) -> u64 {
+        let mut files = self.scan();
+        files.sort_by_key(|file| file.used);
+        // The counter may be stale if other processes share the directory
+        let mut remaining: u64 = files.iter().map(|file| file.size).sum();
+        debug!(
+            "Disk cache holds {} bytes (expected {}), limit {}",
+            remaining, total, self.config.max_bytes
+        );
+        for file in files {
+            if remaining <= self.config.max_bytes {
+                break;
+            }
+            debug!("Evicting cache entry {}", file.path.display());
+            self.remove(&file.path);
+            remaining = remaining.saturating_sub(file.size);
+        }
+        remaining
+    }
+
+    /// Lists the entry files with their size and last use.
+    fn scan(&self) -> Vec<EntryFile> {
+        let Ok(read_dir) = fs::read_dir(&self.config.dir) else {
+            return Vec::new();
+        };
+        read_dir
+            .filter_map(Result::ok)
+            .filter(|dir_entry| {
+                dir_entry.path().extension().and_then(|ext| ext.to_str()) == Some(ENTRY_EXTENSION)
+            })
+            .filter_map(|dir_entry| {
+                let metadata = dir_entry.metadata().ok()?;
+                Some(EntryFile {
+                    path: dir_entry.path(),
+                    size: metadata.len(),
+                    used: metadata.modified().unwrap_or(UNIX_EPOCH),
+                })
+            })
+            .collect()
+    }
+
+    /// Deletes a file, logging failures other than it being gone already.
+    fn remove(&self, path: &Path) {
+        if let Err(error) = fs::remove_file(path)
+            && error.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!(
+                "Failed to remove {} from {}: {}",
+                path.display(),
+                self.dir().display(),
+                error
+            );
+        }
+    }
+
+    /// Returns the file holding the entry for `key` fetched from `url`.
+    fn entry_path(&self, url: &str, key: &str) -> PathBuf {
+        self.config
+            .dir
+            .join(format!("{:016x}.{ENTRY_EXTENSION}", fnv1a(url, key)))
+    }
+}
+
+/// An entry file found while scanning the cache directory.
+struct EntryFile {
+    /// Path of the file.
+    path: PathBuf,
+    /// Size of the file in bytes.
+    size: u64,
+    /// Last modification, refreshed on every hit.
+    used: SystemTime,
+}
+
+/// Upgrades a stored entry to [`CACHE_SCHEMA`].
+///
+/// # Returns
+/// The entry in the current format, or `None` if it comes from an unknown version
+/// and should be dropped
+fn migrate(mut entry: Value) -> Option<Value> {
+    // Add a step converting the previous format whenever `CACHE_SCHEMA` is bumped
+    loop {
+        match entry.get("schema")?.as_u64()? {
+            CACHE_SCHEMA => return Some(entry),
+            // Version 1 stored the `ETag` but not `Last-Modified`
+            1 => {
+                let object = entry.as_object_mut()?;
+                object.insert("last_modified".to_owned(), Value::Null);
+                object.insert("schema".to_owned(), Value::from(2_u64));
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of a URL and cache key, used for file names.
+fn fnv1a(url: &str, key: &str) -> u64 {
+    url.bytes()
+        .chain([b'\n'])
+        .chain(key.bytes())
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3_u64)
+        })
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0_u64, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn open(dir: &Path, max_bytes: u64) -> DiskCache {
+        DiskCache::open(DiskCacheConfig::new(dir).max_bytes(max_bytes)).unwrap()
+    }
+
+    fn entry(url: &str, body: &str) -> DiskEntry {
+        let validators = Validators {
+            etag: Some("\"v1\"".to_owned()),
+            last_modified: None,
+        };
+        DiskEntry::new(url, "", validators, body.to_owned())
+    }
+
+    #[test]
+    fn entries_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        open(dir.path(), 10_000).put(&entry("https://bun.com/docs/a", "# A"));
+
+        let cache = open(dir.path(), 10_000);
+        let stored = cache
+            .get("https://bun.com/docs/a", "")
+            .expect("entry on disk");
+        assert_eq!(stored.body, "# A");
+        assert_eq!(stored.validators.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(stored.schema, CACHE_SCHEMA);
+        assert!(cache.is_fresh(&stored));
+        assert!(cache.get("https://bun.com/docs/b", "").is_none());
+    }
+
+    #[test]
+    fn writes_leave_no_temporary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), 10_000);
+        cache.put(&entry("https://bun.com/docs/a", "one"));
+        cache.put(&entry("https://bun.com/docs/a", "two"));
+
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|file| file.unwrap().file_name())
+            .collect();
+        assert_eq!(names.len(), 1);
+        assert_eq!(cache.get("https://bun.com/docs/a", "").unwrap().body, "two");
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = "x".repeat(200);
+        let size = serde_json::to_string(&entry("https://bun.com/docs/a", &body))
+            .unwrap()
+            .len() as u64;
+        let cache = open(dir.path(), size * 2);
+
+        cache.put(&entry("https://bun.com/docs/a", &body));
+        cache.put(&entry("https://bun.com/docs/b", &body));
+        // Make "b" the least recently used by backdating it
+        let old = SystemTime::now() - Duration::from_hours(1);
+        File::options()
+            .write(true)
+            .open(cache.entry_path("https://bun.com/docs/b", ""))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        cache.put(&entry("https://bun.com/docs/c", &body));
+
+        assert!(cache.get("https://bun.com/docs/a", "").is_some());
+        assert!(cache.get("https://bun.com/docs/b", "").is_none());
+        assert!(cache.get("https://bun.com/docs/c", "").is_some());
+    }
+
+    #[test]
+    fn oversized_entries_are_not_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), 10);
+        cache.put(&entry(
+            "https://bun.com/docs/a",
+            "far too large for the cache",
+        ));
+        assert!(cache.get("https://bun.com/docs/a", "").is_none());
+    }
+
+    #[test]
+    fn stale_entries_are_returned_but_not_fresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache =
+            DiskCache::open(DiskCacheConfig::new(dir.path()).ttl(Duration::from_mins(1))).unwrap();
+        let mut old = entry("https://bun.com/docs/a", "old");
+        old.fetched_at -= 3600;
+        cache.put(&old);
+
+        let stored = cache.get("https://bun.com/docs/a", "").unwrap();
+        assert!(!cache.is_fresh(&stored));
+        assert!(stored.age() >= Duration::from_hours(1));
+    }
+
+    #[test]
+    fn unknown_schema_and_garbage_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), 10_000);
+
+        let future = cache.entry_path("https://bun.com/docs/a", "");
+        let mut value = serde_json::to_value(entry("https://bun.com/docs/a", "a")).unwrap();
+        value["schema"] = json!(CACHE_SCHEMA + 1);
+        fs::write(&future, value.to_string()).unwrap();
+        let garbage = cache.entry_path("https://bun.com/docs/b", "");
+        fs::write(&garbage, "not json").unwrap();
+
+        assert!(cache.get("https://bun.com/docs/a", "").is_none());
+        assert!(cache.get("https://bun.com/docs/b", "").is_none());
+        assert!(!future.exists());
+        assert!(!garbage.exists());
+    }
+
+    #[test]
+    fn version_1_entries_are_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), 10_000);
+        let v1 = json!({
+            "schema": 1,
+            "url": "https://bun.com/docs/a",
+            "key": "",
+            "fetched_at": 1_700_000_000_u64,
+            "etag": "\"old\"",
+            "body": "# A"
+        });
+        fs::write(
+            cache.entry_path("https://bun.com/docs/a", ""),
+            v1.to_string(),
+        )
+        .unwrap();
+
+        let stored = cache
+            .get("https://bun.com/docs/a", "")
+            .expect("migrated entry");
+        assert_eq!(stored.schema, CACHE_SCHEMA);
+        assert_eq!(stored.validators.etag.as_deref(), Some("\"old\""));
+        assert_eq!(stored.validators.last_modified, None);
+        assert_eq!(stored.body, "# A");
+    }
+
+    #[test]
+    fn keys_within_one_url_are_separate() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), 10_000);
+        cache.put(&DiskEntry::new(
+            "https://bun.com/docs/mcp",
+            "a",
+            Validators::default(),
+            "A".to_owned(),
+        ));
+        cache.put(&DiskEntry::new(
+            "https://bun.com/docs/mcp",
+            "b",
+            Validators::default(),
+            "B".to_owned(),
+        ));
+
+        assert_eq!(
+            cache.get("https://bun.com/docs/mcp", "a").unwrap().body,
+            "A"
+        );
+        assert_eq!(
+            cache.get("https://bun.com/docs/mcp", "b").unwrap().body,
+            "B"
+        );
+    }
+
+    #[test]
+    fn default_dir_ends_with_cache_name() {
+        if let Some(dir) = default_dir() {
+            assert!(dir.ends_with(CACHE_DIR_NAME));
+        }
+    }
+}
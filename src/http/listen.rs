@@ -0,0 +1,424 @@
+//! Listens on the upstream GET SSE channel for server-initiated messages.
+//!
+//! Streamable HTTP lets the server push messages over a long-lived `GET` stream tied
+//! to the session. Once the API assigns an `Mcp-Session-Id`, [`BunDocsClient`] opens
+//! that stream in the background and keeps it open:
+//!
+//! - **Reconnects** - a dropped stream is reopened after a jittered backoff (or the
+//!   server's `retry:` delay), carrying `Last-Event-ID` so missed events are replayed.
+//!   A connection is also reopened once more than
+//!   [`super::ClientConfig::max_response_size`] bytes have arrived on it.
+//! - **Stops** - when the server answers `405 Method Not Allowed` (no stream offered)
+//!   or `404 Not Found` (session expired), and when the session ends.
+//! - **Routes notifications** - `notifications/tools/list_changed` refreshes the
+//!   cached upstream tool list (see [`BunDocsClient::tool_list`]); it and other
+//!   notifications are then published to [`BunDocsClient::subscribe`] receivers, which
+//!   relay them to downstream clients. Progress and cancellation notifications belong
+//!   to individual requests and are not published. Server requests cannot be
+//!   answered by the proxy and are ignored.
+
+use super::body::LimitedStream;
+use super::{BunDocsClient, LAST_EVENT_ID_HEADER, RetryPolicy, SESSION_HEADER};
+use anyhow::{Context as _, Result};
+use eventsource_stream::{Event, Eventsource as _};
+use futures::StreamExt as _;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use std::sync::PoisonError;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+/// Initial delay before reopening a dropped upstream message stream (milliseconds)
+pub const LISTEN_BACKOFF_BASE_MS: u64 = 1_000_u64;
+
+/// Maximum delay before reopening a dropped upstream message stream (milliseconds)
+pub const LISTEN_BACKOFF_MAX_MS: u64 = 60_000_u64;
+
+/// Capacity of the channel publishing upstream notifications
+pub const EVENT_CHANNEL_CAPACITY: usize = 64_usize;
+
+/// Notification announcing that the server's tool list changed
+pub const TOOLS_LIST_CHANGED: &str = "notifications/tools/list_changed";
+
+/// Notifications tied to individual requests, which are never published
+const REQUEST_NOTIFICATIONS: [&str; 2] = ["notifications/progress", "notifications/cancelled"];
+
+impl BunDocsClient {
+    /// Returns a receiver for the notifications the API pushes on its message stream.
+    pub fn subscribe(&self) -> broadcast::Receiver<Value> {
+        self.events.subscribe()
+    }
+
+    /// Returns the upstream `tools/list` result, once a
+    /// `notifications/tools/list_changed` has caused it to be fetched.
+    pub fn tool_list(&self) -> Option<Value> {
+        self.tools
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Starts listening on the upstream message stream, unless already listening.
+    pub(super) fn start_listening(&self) {
+        let mut listener = self.listener.lock().unwrap_or_else(PoisonError::into_inner);
+        if listener.as_ref().is_some_and(|task| !task.is_finished()) {
+            return;
+        }
+        *listener = Some(tokio::spawn(self.clone().listen()));
+    }
+
+    /// Stops listening on the upstream message stream.
+    pub(super) fn stop_listening(&self) {
+        let task = self
+            .listener
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(task) = task {
+            task.abort();
+        }
+    }
+
+    /// Keeps the upstream message stream open for as long as the session lasts.
+    async fn listen(self) {
+        let backoff = RetryPolicy::default().backoff(
+            Duration::from_millis(LISTEN_BACKOFF_BASE_MS),
+            Duration::from_millis(LISTEN_BACKOFF_MAX_MS),
+        );
+        let mut failures = 0_u32;
+        let mut last_event_id: Option<String> = None;
+        let mut retry: Option<Duration> = None;
+
+        while let Some(session) = self.session_id() {
+            match self
+                .open_message_stream(&session, last_event_id.as_deref())
+                .await
+            {
+                Ok(Some(response)) => {
+                    info!("Listening for upstream messages on session {}", session);
+                    let mut events =
+                        LimitedStream::new(response.bytes_stream(), self.config.max_response_size)
+                            .eventsource();
+                    while let Some(event) = events.next().await {
+                        let event = match event {
+                            Ok(event) => event,
+                            Err(error) => {
+                                warn!("Upstream message stream failed: {}", error);
+                                break;
+                            }
+                        };
+                        failures = 0_u32;
+                        if !event.id.is_empty() {
+                            last_event_id = Some(event.id.clone());
+                        }
+                        if event.retry.is_some() {
+                            retry = event.retry;
+                        }
+                        self.route_event(&event).await;
+                    }
+                    debug!("Upstream message stream closed");
+                }
+                Ok(None) => return,
+                Err(error) => warn!("Cannot open upstream message stream: {:#}", error),
+            }
+
+            failures = failures.saturating_add(1_u32);
+            let delay = backoff.delay(failures, retry);
+            debug!(
+                "Reopening upstream message stream in {} ms",
+                delay.as_millis()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Opens the upstream message stream with a `GET`.
+    ///
+    /// # Returns
+    /// The streaming response, or `None` if the server does not offer a stream for
+    /// the session
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the server answers with another
+    /// error status, so the stream should be retried later
+    async fn open_message_stream(
+        &self,
+        session: &str,
+        last_event_id: Option<&str>,
+    ) -> Result<Option<reqwest::Response>> {
+        let mut rb = self
+            .client
+            .get(self.base_url.as_str())
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .header(SESSION_HEADER, session);
+        if let Some(id) = last_event_id {
+            rb = rb.header(LAST_EVENT_ID_HEADER, id);
+        }
+
+        let response = rb
+            .send()
+            .await
+            .context("Failed to open upstream message stream")?;
+        let status = response.status();
+        if status == StatusCode::METHOD_NOT_ALLOWED || status == StatusCode::NOT_FOUND {
+            info!(
+                "Upstream offers no message stream for session {} (HTTP {})",
+                session, status
+            );
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("HTTP {status}"));
+        }
+        let content_type = Self::main_content_type(response.headers());
+        if !content_type.starts_with("text/event-stream") {
+            warn!(
+                "Upstream answered {:?} instead of a message stream",
+                content_type
+            );
+            return Ok(None);
+        }
+        Ok(Some(response))
+    }
+
+    /// Routes the messages carried by one event of the message stream.
+    async fn route_event(&self, event: &Event) {
+        if !event.event.is_empty() && event.event != "message" {
+            debug!("Skipping upstream event type: {}", event.event);
+            return;
+        }
+        if event.data.trim().is_empty() {
+            return;
+        }
+        let messages = match serde_json::from_str::<Value>(&event.data) {
+            Ok(Value::Array(batch)) => batch,
+            Ok(message) => vec![message],
+            Err(error) => {
+                warn!("Dropping invalid upstream message ({})", error);
+                return;
+            }
+        };
+        for message in messages {
+            self.route_message(message).await;
+        }
+    }
+
+    /// Handles one server-initiated message.
+    async fn route_message(&self, message: Value) {
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            debug!("Skipping upstream response on the message stream");
+            return;
+        };
+        if message.get("id").is_some() {
+            warn!(
+                "Ignoring upstream request {}: the proxy cannot answer it",
+                method
+            );
+            return;
+        }
+        if REQUEST_NOTIFICATIONS.contains(&method) {
+            debug!("Skipping upstream notification {}", method);
+            return;
+        }
+
+        info!("Upstream notification: {}", method);
+        if method == TOOLS_LIST_CHANGED
+            && let Err(error) = self.refresh_tool_list().await
+        {
+            warn!("Failed to refresh the upstream tool list: {:#}", error);
+        }
+        if self.events.send(message).is_err() {
+            debug!("No downstream client to notify");
+        }
+    }
+
+    /// Fetches the upstream tool list and caches it.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the response carries no tool list
+    async fn refresh_tool_list(&self) -> Result<()> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "bun-docs-mcp-proxy/tools-list",
+            "method": "tools/list"
+        });
+        let response = self
+            .fetch_response(&request, &self.config.retry, None)
+            .await?;
+        let result = response
+            .get("result")
+            .filter(|result| result.get("tools").is_some_and(Value::is_array))
+            .context("Upstream tools/list response has no tool list")?;
+        debug!("Refreshed the upstream tool list");
+        *self.tools.lock().unwrap_or_else(PoisonError::into_inner) = Some(result.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    /// Mocks a `tools/call` answer that assigns upstream session `s1`.
+    async fn mock_session(server: &mut mockito::Server) -> mockito::Mock {
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({"method": "tools/call"})))
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_header(SESSION_HEADER, "s1")
+            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":{"content":[]}}"#)
+            .create_async()
+            .await
+    }
+
+    /// Sends a `tools/call`, which establishes the session and starts listening.
+    /// Distinct queries keep the response cache out of the way.
+    async fn establish_session(client: &BunDocsClient, query: &str) {
+        client
+            .forward_request(json!({
+                "jsonrpc": "2.0",
+                "id": 1_i32,
+                "method": "tools/call",
+                "params": {"name": "SearchBun", "arguments": {"query": query}}
+            }))
+            .await
+            .expect("tools/call succeeds");
+    }
+
+    /// Waits until the listener task has returned.
+    async fn wait_for_listener(client: &BunDocsClient) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client
+                .listener
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_none_or(|task| !task.is_finished())
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("listener stops");
+    }
+
+    #[tokio::test]
+    async fn tools_list_changed_refreshes_tool_list_and_notifies() {
+        let mut server = mockito::Server::new_async().await;
+        let session = mock_session(&mut server).await;
+        let stream = server
+            .mock("GET", "/")
+            .match_header(SESSION_HEADER, "s1")
+            .match_header("accept", "text/event-stream")
+            .with_status(200_usize)
+            .with_header("content-type", "text/event-stream")
+            .with_body(concat!(
+                "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n",
+                "data: {\"jsonrpc\":\"2.0\",\"id\":9,\"method\":\"roots/list\"}\n\n",
+                "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/tools/list_changed\"}\n\n",
+                "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/message\",\"params\":{\"data\":\"hi\"}}\n\n",
+            ))
+            .create_async()
+            .await;
+        let tools = server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({"method": "tools/list"})))
+            .match_header(SESSION_HEADER, "s1")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc":"2.0","id":"x","result":{"tools":[{"name":"SearchBun"},{"name":"NewTool"}]}}"#)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).unwrap();
+        let mut events = client.subscribe();
+        establish_session(&client, "serve").await;
+
+        let first = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("notification arrives")
+            .unwrap();
+        assert_eq!(first["method"], TOOLS_LIST_CHANGED);
+        let second = events.recv().await.unwrap();
+        assert_eq!(second["method"], "notifications/message");
+        assert_eq!(
+            client.tool_list().unwrap()["tools"][1_usize]["name"],
+            "NewTool"
+        );
+
+        client.stop_listening();
+        session.assert_async().await;
+        stream.assert_async().await;
+        tools.assert_async().await;
+        drop(server);
+    }
+
+    #[tokio::test]
+    async fn dropped_stream_is_reopened_with_last_event_id() {
+        let mut server = mockito::Server::new_async().await;
+        let _session = mock_session(&mut server).await;
+        let first = server
+            .mock("GET", "/")
+            .match_header(LAST_EVENT_ID_HEADER, Matcher::Missing)
+            .with_status(200_usize)
+            .with_header("content-type", "text/event-stream")
+            .with_body("retry: 10\nid: e1\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/message\"}\n\n")
+            .expect(1_usize)
+            .create_async()
+            .await;
+        let reopened = server
+            .mock("GET", "/")
+            .match_header(LAST_EVENT_ID_HEADER, "e1")
+            .with_status(405_usize)
+            .expect(1_usize)
+            .create_async()
+            .await;
+
+        let client = BunDocsClient::with_base_url(&server.url()).unwrap();
+        establish_session(&client, "serve").await;
+        wait_for_listener(&client).await;
+
+        first.assert_async().await;
+        reopened.assert_async().await;
+        drop(server);
+    }
+
+    #[tokio::test]
+    async fn no_stream_without_session_or_when_unsupported() {
+        let mut server = mockito::Server::new_async().await;
+        let call = server
+            .mock("POST", "/")
+            .with_status(200_usize)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":{"content":[]}}"#)
+            .create_async()
+            .await;
+        let stream = server.mock("GET", "/").expect(0_usize).create_async().await;
+
+        // Without a session there is nothing to listen on
+        let client = BunDocsClient::with_base_url(&server.url()).unwrap();
+        establish_session(&client, "serve").await;
+        assert!(client.listener.lock().unwrap().is_none());
+        stream.assert_async().await;
+        call.remove_async().await;
+        stream.remove_async().await;
+
+        // A 405 means the server offers no stream; it is not retried
+        let _session = mock_session(&mut server).await;
+        let unsupported = server
+            .mock("GET", "/")
+            .with_status(405_usize)
+            .expect(1_usize)
+            .create_async()
+            .await;
+        establish_session(&client, "file").await;
+        wait_for_listener(&client).await;
+        unsupported.assert_async().await;
+        drop(server);
+    }
+}
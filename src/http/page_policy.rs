@@ -0,0 +1,403 @@
+//! Which documentation page URLs [`super::BunDocsClient`] may fetch.
+//!
+//! Markdown output fetches every URL found after `Link:` in the search results, so a
+//! tampered or compromised upstream could point the proxy at internal services. A
+//! [`PagePolicy`] limits page fetches to:
+//!
+//! - **Allowed hosts** - [`DEFAULT_PAGE_HOST`] and its subdomains by default; `*`
+//!   allows any host.
+//! - **HTTPS** - plain `http` URLs are refused unless allowed.
+//! - **Public addresses** - host names are resolved by [`GuardedResolver`], which
+//!   drops loopback, private, link-local and other non-public addresses (including
+//!   private IPv4 addresses embedded in IPv6 ones), and IP literals are checked
+//!   directly, unless private addresses are allowed.
+//! - **Redirects** - every redirect target is checked again (see
+//!   [`PagePolicy::redirect_policy`]).
+//!
+//! Pages on the configured upstream's own origin are always allowed, so a local
+//! mirror keeps working. Refused URLs fail with [`PageRejected`].
+
+use core::fmt;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use reqwest::Url;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+/// Host (with its subdomains) pages may be fetched from by default
+pub const DEFAULT_PAGE_HOST: &str = "bun.com";
+
+/// Maximum number of redirects followed for one page
+const MAX_REDIRECTS: usize = 10_usize;
+
+/// Hosts, schemes and addresses documentation pages may be fetched from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PagePolicy {
+    /// Allowed hosts, each also allowing its subdomains; `*` allows any host.
+    allowed_hosts: Vec<String>,
+    /// Allow plain `http` URLs.
+    allow_http: bool,
+    /// Allow loopback, private and other non-public addresses.
+    allow_private: bool,
+    /// Origin of the configured upstream, which is always allowed.
+    trusted_origin: Option<String>,
+}
+
+impl Default for PagePolicy {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: vec![DEFAULT_PAGE_HOST.to_owned()],
+            allow_http: false,
+            allow_private: false,
+            trusted_origin: None,
+        }
+    }
+}
+
+/// Error returned for a page URL the [`PagePolicy`] refuses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRejected {
+    /// The refused URL or host.
+    pub target: String,
+    /// Why it was refused.
+    pub reason: String,
+}
+
+impl fmt::Display for PageRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "refused to fetch {}: {}", self.target, self.reason)
+    }
+}
+
+impl core::error::Error for PageRejected {}
+
+impl PagePolicy {
+    /// Sets the allowed hosts, each also allowing its subdomains; `*` allows any host.
+    #[must_use]
+    pub fn allowed_hosts<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_hosts = hosts
+            .into_iter()
+            .map(|host| {
+                host.into()
+                    .trim()
+                    .trim_end_matches('.')
+                    .to_ascii_lowercase()
+            })
+            .collect();
+        self
+    }
+
+    /// Allows plain `http` page URLs.
+    #[must_use]
+    pub const fn allow_http(mut self, allow: bool) -> Self {
+        self.allow_http = allow;
+        self
+    }
+
+    /// Allows pages on loopback, private and other non-public addresses.
+    #[must_use]
+    pub const fn allow_private(mut self, allow: bool) -> Self {
+        self.allow_private = allow;
+        self
+    }
+
+    /// Always allows pages on the origin of `url`, the configured upstream.
+    #[must_use]
+    pub(super) fn trust_origin_of(mut self, url: &Url) -> Self {
+        self.trusted_origin = Some(url.origin().ascii_serialization());
+        self
+    }
+
+    /// Checks a page URL before it is fetched, or a redirect target.
+    ///
+    /// # Errors
+    /// Returns [`PageRejected`] if the scheme, host or IP address is not allowed
+    pub fn check(&self, url: &Url) -> Result<(), PageRejected> {
+        if self
+            .trusted_origin
+            .as_deref()
+            .is_some_and(|origin| url.origin().ascii_serialization() == origin)
+        {
+            return Ok(());
+        }
+        let reject = |reason: String| PageRejected {
+            target: url.to_string(),
+            reason,
+        };
+        match url.scheme() {
+            "https" => {}
+            "http" if self.allow_http => {}
+            scheme => return Err(reject(format!("scheme {scheme:?} is not allowed"))),
+        }
+        let Some(host) = url.host_str() else {
+            return Err(reject("URL has no host".to_owned()));
+        };
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+        if !self.is_allowed_host(&host) {
+            return Err(reject(format!("host {host} is not in the allowlist")));
+        }
+        if !self.allow_private
+            && let Ok(ip) = host.parse::<IpAddr>()
+            && !is_public(ip)
+        {
+            return Err(reject(format!("{ip} is not a public address")));
+        }
+        Ok(())
+    }
+
+    /// Returns whether `host` is an allowed host or one of its subdomains.
+    fn is_allowed_host(&self, host: &str) -> bool {
+        self.allowed_hosts.iter().any(|allowed| {
+            allowed == "*"
+                || host == allowed
+                || host
+                    .strip_suffix(allowed.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+
+    /// Builds the redirect policy checking every redirect target.
+    pub(super) fn redirect_policy(&self) -> reqwest::redirect::Policy {
+        let policy = self.clone();
+        reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error(format!("more than {MAX_REDIRECTS} redirects"));
+            }
+            match policy.check(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(rejected) => attempt.error(rejected),
+            }
+        })
+    }
+
+    /// Builds the resolver dropping non-public addresses, unless they are allowed.
+    pub(super) fn resolver(&self) -> Option<GuardedResolver> {
+        if self.allow_private {
+            return None;
+        }
+        let trusted_host = self
+            .trusted_origin
+            .as_deref()
+            .and_then(|origin| Url::parse(origin).ok())
+            .and_then(|origin| origin.host_str().map(str::to_owned));
+        Some(GuardedResolver { trusted_host })
+    }
+}
+
+/// DNS resolver that drops non-public addresses, so a public host name cannot lead
+/// to an internal service
+#[derive(Debug, Clone)]
+pub struct GuardedResolver {
+    /// Host of the configured upstream, resolved without filtering.
+    trusted_host: Option<String>,
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let trusted = self
+            .trusted_host
+            .as_deref()
+            .is_some_and(|host| host.eq_ignore_ascii_case(name.as_str()));
+        Box::pin(async move {
+            let host = name.as_str().to_owned();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0_u16))
+                .await?
+                .filter(|addr| trusted || is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(Box::new(PageRejected {
+                    target: host,
+                    reason: "host resolves only to non-public addresses".to_owned(),
+                })
+                    as Box<dyn core::error::Error + Send + Sync>);
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Finds the [`PageRejected`] error behind a failed page request, if any.
+pub fn rejection<'error>(
+    error: &'error (dyn core::error::Error + 'static),
+) -> Option<&'error PageRejected> {
+    let mut source = Some(error);
+    while let Some(current) = source {
+        if let Some(rejected) = current.downcast_ref::<PageRejected>() {
+            return Some(rejected);
+        }
+        source = current.source();
+    }
+    None
+}
+
+/// Returns whether `ip` is a public unicast address.
+pub const fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_v4(v4),
+        IpAddr::V6(v6) => is_public_v6(v6),
+    }
+}
+
+/// Returns whether `ip` is a public unicast IPv6 address.
+///
+/// An IPv4 address embedded in `ip` is checked as IPv4, so a private IPv4 address
+/// cannot pass the check in IPv6 form.
+const fn is_public_v6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = embedded_v4(ip) {
+        return is_public_v4(v4);
+    }
+    let [first, second, third, ..] = ip.segments();
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // Deprecated site-local and local-use NAT64
+        || (first & 0xffc0_u16) == 0xfec0_u16
+        || (first == 0x0064_u16 && second == 0xff9b_u16 && third == 0x0001_u16))
+}
+
+/// Returns the IPv4 address embedded in an IPv4-mapped (`::ffff:0:0/96`),
+/// IPv4-compatible (`::/96`), NAT64 (`64:ff9b::/96`) or 6to4 (`2002::/16`) address.
+const fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    match ip.segments() {
+        [0x0064, 0xff9b, 0, 0, 0, 0, high, low] | [0x2002, high, low, ..] => {
+            let [a, b] = high.to_be_bytes();
+            let [c, d] = low.to_be_bytes();
+            Some(Ipv4Addr::new(a, b, c, d))
+        }
+        _ => ip.to_ipv4(),
+    }
+}
+
+/// Returns whether `ip` is a public unicast IPv4 address.
+const fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // "This network", shared address space (CGNAT), benchmarking and reserved
+        || first == 0_u8
+        || (first == 100_u8 && (second & 0xc0_u8) == 64_u8)
+        || (first == 198_u8 && (second & 0xfe_u8) == 18_u8)
+        || first >= 240_u8)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+
+    fn url(text: &str) -> Url {
+        Url::parse(text).unwrap()
+    }
+
+    #[test]
+    fn default_policy_allows_bun_com_over_https() {
+        let policy = PagePolicy::default();
+        policy.check(&url("https://bun.com/docs/api/http")).unwrap();
+        policy.check(&url("https://www.bun.com/docs")).unwrap();
+
+        let http = policy.check(&url("http://bun.com/docs")).unwrap_err();
+        assert!(http.reason.contains("scheme"), "{http}");
+        let other = policy.check(&url("https://evil.com/bun.com")).unwrap_err();
+        assert!(other.reason.contains("allowlist"), "{other}");
+        let suffix = policy.check(&url("https://notbun.com/docs")).unwrap_err();
+        assert!(suffix.reason.contains("allowlist"), "{suffix}");
+        policy.check(&url("file:///etc/passwd")).unwrap_err();
+    }
+
+    #[test]
+    fn private_ip_literals_are_rejected_even_when_allowed() {
+        let policy = PagePolicy::default().allowed_hosts(["*"]).allow_http(true);
+        for target in [
+            "http://127.0.0.1:8080/",
+            "http://10.0.0.1/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]/",
+            "http://[::ffff:192.168.0.1]/",
+            "http://[::7f00:1]/",
+            "http://[64:ff9b::a00:1]/",
+        ] {
+            let rejected = policy.check(&url(target)).unwrap_err();
+            assert!(rejected.reason.contains("public"), "{rejected}");
+        }
+        policy.check(&url("http://93.184.215.14/")).unwrap();
+        PagePolicy::default()
+            .allowed_hosts(["*"])
+            .allow_http(true)
+            .allow_private(true)
+            .check(&url("http://127.0.0.1:8080/"))
+            .unwrap();
+    }
+
+    #[test]
+    fn upstream_origin_is_trusted() {
+        let policy = PagePolicy::default().trust_origin_of(&url("http://127.0.0.1:3000/mcp"));
+        policy
+            .check(&url("http://127.0.0.1:3000/docs/page"))
+            .unwrap();
+        policy
+            .check(&url("http://127.0.0.1:3001/docs/page"))
+            .unwrap_err();
+    }
+
+    #[test]
+    fn public_address_classification() {
+        for private in [
+            "0.1.2.3",
+            "100.64.0.1",
+            "172.16.0.1",
+            "198.18.0.1",
+            "240.0.0.1",
+            "fe80::1",
+            "fd00::1",
+            // Private IPv4 embedded in IPv6: mapped, compatible, NAT64 and 6to4
+            "::ffff:10.0.0.1",
+            "::7f00:1",
+            "::192.168.1.1",
+            "64:ff9b::a00:1",
+            "64:ff9b::127.0.0.1",
+            "2002:a00:1::1",
+            "2002:c0a8:101::",
+            // Deprecated site-local and local-use NAT64
+            "fec0::1",
+            "64:ff9b:1::808:808",
+        ] {
+            assert!(!is_public(private.parse().unwrap()), "{private}");
+        }
+        for public in [
+            "1.1.1.1",
+            "100.128.0.1",
+            "2606:4700::1111",
+            "::ffff:1.1.1.1",
+            "64:ff9b::808:808",
+            "2002:808:808::1",
+        ] {
+            assert!(is_public(public.parse().unwrap()), "{public}");
+        }
+    }
+
+    #[tokio::test]
+    async fn resolver_drops_non_public_addresses() {
+        let resolver = PagePolicy::default().resolver().unwrap();
+        let error = resolver
+            .resolve("localhost".parse().unwrap())
+            .await
+            .err()
+            .expect("localhost is not public");
+        assert!(rejection(error.as_ref()).is_some(), "{error}");
+    }
+}
@@ -0,0 +1,458 @@
+//! Retry policy shared by every outbound request of [`super::BunDocsClient`].
+//!
+//! A [`RetryPolicy`] decides which failures are worth another attempt, how long to
+//! wait in between and how many attempts to make:
+//!
+//! - **Transient failures** - network errors (connect, timeout, request) and the
+//!   statuses `429`, `500`, `502`, `503` and `504`. Everything else fails at once.
+//! - **Backoff** - exponential (200 ms → 400 ms → 800 ms, capped at 1 s by default)
+//!   with full jitter: each wait is a random delay between zero and the backoff, so
+//!   many proxies retrying at once do not collide again in lockstep.
+//! - **`Retry-After`** - when a transient response carries the header (delta-seconds
+//!   or an HTTP-date), that wait is used instead, capped at [`RETRY_AFTER_MAX_SECS`].
+//!
+//! Every wait is logged. Requests run through [`RetryPolicy::run`], which calls the
+//! request closure once per attempt; the closure reports failures as a [`Failure`].
+
+use anyhow::Result;
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+/// Default maximum number of attempts (including the first) for transient failures
+pub const MAX_RETRIES: u32 = 3_u32;
+
+/// Default base delay for exponential backoff (milliseconds)
+pub const BACKOFF_BASE_MS: u64 = 200_u64;
+
+/// Default maximum backoff delay (milliseconds)
+pub const BACKOFF_MAX_MS: u64 = 1000_u64;
+
+/// Default upper bound for a server-requested `Retry-After` wait (seconds)
+pub const RETRY_AFTER_MAX_SECS: u64 = 10_u64;
+
+/// How often and how patiently a failed request is retried
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts per request, including the first.
+    max_attempts: u32,
+    /// Delay before the first retry; doubled for each further retry.
+    backoff_base: Duration,
+    /// Upper bound for the retry delay.
+    backoff_max: Duration,
+    /// Upper bound for a wait requested with `Retry-After`.
+    retry_after_max: Duration,
+}
+
+/// Why one attempt failed, as reported to [`RetryPolicy::run`]
+#[derive(Debug)]
+pub enum Failure {
+    /// Worth another attempt, optionally after a server-requested wait.
+    Transient {
+        /// The error returned if no attempts are left.
+        error: anyhow::Error,
+        /// Wait requested by the server's `Retry-After` header.
+        retry_after: Option<Duration>,
+    },
+    /// Not worth retrying; returned immediately.
+    Permanent(anyhow::Error),
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: MAX_RETRIES,
+            backoff_base: Duration::from_millis(BACKOFF_BASE_MS),
+            backoff_max: Duration::from_millis(BACKOFF_MAX_MS),
+            retry_after_max: Duration::from_secs(RETRY_AFTER_MAX_SECS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that makes a single attempt.
+    #[must_use]
+    pub fn none() -> Self {
+        Self::default().max_attempts(1_u32)
+    }
+
+    /// Sets the maximum number of attempts per request, including the first.
+    ///
+    /// Values below 1 are treated as 1 (no retries).
+    #[must_use]
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1_u32);
+        self
+    }
+
+    /// Sets the initial retry delay and the cap it doubles up to.
+    #[must_use]
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.backoff_base = base;
+        self.backoff_max = max.max(base);
+        self
+    }
+
+    /// Sets the longest `Retry-After` wait honored before retrying anyway.
+    #[must_use]
+    pub const fn retry_after_max(mut self, max: Duration) -> Self {
+        self.retry_after_max = max;
+        self
+    }
+
+    /// Returns the maximum number of attempts, including the first.
+    pub const fn attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Runs `attempt` until it succeeds, fails permanently or no attempts are left.
+    ///
+    /// # Arguments
+    /// * `what` - Short description of the request for log messages
+    /// * `attempt` - Makes one attempt; called with the attempt number, starting at 1
+    ///
+    /// # Errors
+    /// Returns the permanent error, or the last transient error once all attempts failed
+    pub async fn run<T, F, Fut>(&self, what: &str, mut attempt: F) -> Result<T>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T, Failure>>,
+    {
+        let mut number = 1_u32;
+        loop {
+            let (error, retry_after) = match attempt(number).await {
+                Ok(value) => return Ok(value),
+                Err(Failure::Permanent(error)) => return Err(error),
+                Err(Failure::Transient { error, retry_after }) => (error, retry_after),
+            };
+            if number >= self.max_attempts {
+                return Err(error);
+            }
+            let delay = self.delay(number, retry_after);
+            warn!(
+                "{} failed: {:#}. Retrying in {} ms{} (attempt {} of {})",
+                what,
+                error,
+                delay.as_millis(),
+                if retry_after.is_some() {
+                    " as requested by Retry-After"
+                } else {
+                    ""
+                },
+                number + 1,
+                self.max_attempts
+            );
+            tokio::time::sleep(delay).await;
+            number += 1;
+        }
+    }
+
+    /// Calculates the wait before the next attempt.
+    ///
+    /// A server-requested `retry_after` wins (capped at the configured maximum);
+    /// otherwise the exponential backoff for `attempt` is used with full jitter.
+    ///
+    /// # Arguments
+    /// * `attempt` - The attempt that just failed (must be >= 1).
+    /// * `retry_after` - The wait requested by the server's `Retry-After` header, if any.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after.map_or_else(
+            || {
+                let backoff = self.backoff_delay(attempt);
+                // Without a random source, fall back to the plain backoff
+                getrandom::u64().map_or(backoff, |random| full_jitter(backoff, random))
+            },
+            |wait| wait.min(self.retry_after_max),
+        )
+    }
+
+    /// Calculates an exponential backoff delay for retry attempts.
+    ///
+    /// The delay doubles with each `attempt` (by default 200ms, 400ms, 800ms) up to the
+    /// configured maximum (1000ms by default). It is the upper bound for the jittered
+    /// wait chosen by [`Self::delay`].
+    ///
+    /// # Arguments
+    /// * `attempt` - The current retry attempt number (must be >= 1).
+    ///
+    /// # Returns
+    /// The calculated delay.
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        debug_assert!(attempt > 0_u32, "attempt must be >= 1");
+        let factor = 1_u32
+            .checked_shl(attempt.saturating_sub(1_u32))
+            .unwrap_or(u32::MAX);
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(self.backoff_max)
+    }
+
+    /// Determines if an HTTP status code indicates a transient error that is worth retrying.
+    ///
+    /// Transient errors typically include server errors (5xx) and rate limiting (429).
+    ///
+    /// # Arguments
+    /// * `status` - The `StatusCode` to check.
+    ///
+    /// # Returns
+    /// `true` if the status code is transient and suggests a retry, `false` otherwise.
+    pub const fn is_transient_status(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+}
+
+impl Failure {
+    /// Classifies a failed HTTP response by its status.
+    ///
+    /// # Arguments
+    /// * `status` - The response status
+    /// * `headers` - The response headers, checked for `Retry-After`
+    /// * `error` - The error describing the response
+    pub fn from_status(status: StatusCode, headers: &HeaderMap, error: anyhow::Error) -> Self {
+        if RetryPolicy::is_transient_status(status) {
+            Self::Transient {
+                error,
+                retry_after: retry_after(headers, SystemTime::now()),
+            }
+        } else {
+            Self::Permanent(error)
+        }
+    }
+
+    /// Classifies an error from sending a request.
+    ///
+    /// Connection failures, timeouts and request errors are transient.
+    ///
+    /// # Arguments
+    /// * `source` - The `reqwest` error
+    /// * `context` - What was being sent, prefixed to the error message
+    pub fn from_send_error(source: &reqwest::Error, context: &str) -> Self {
+        let error = anyhow::anyhow!("{context}: {source}");
+        if source.is_connect() || source.is_timeout() || source.is_request() {
+            Self::Transient {
+                error,
+                retry_after: None,
+            }
+        } else {
+            Self::Permanent(error)
+        }
+    }
+}
+
+/// Reads the wait requested by a `Retry-After` header.
+///
+/// Accepts both forms allowed by RFC 9110: delta-seconds (`120`) and an HTTP-date
+/// (`Wed, 21 Oct 2015 07:28:00 GMT`). A date in the past means no wait.
+///
+/// # Arguments
+/// * `headers` - The response headers.
+/// * `now` - The current time, used to turn an HTTP-date into a wait.
+///
+/// # Returns
+/// The requested wait, or `None` if the header is missing or invalid.
+fn retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+/// Picks a delay between zero and `cap` (inclusive, millisecond resolution) from `random`.
+fn full_jitter(cap: Duration, random: u64) -> Duration {
+    let cap_ms = u64::try_from(cap.as_millis()).unwrap_or(u64::MAX);
+    let random_ms = cap_ms
+        .checked_add(1_u64)
+        .map_or(random, |range| random % range);
+    Duration::from_millis(random_ms)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn backoff_delay_milliseconds() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff_delay(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff_delay(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff_delay(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff_delay(4), Duration::from_secs(1)); // capped
+        assert_eq!(policy.backoff_delay(64), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn builder_clamps_values() {
+        let policy = RetryPolicy::default()
+            .max_attempts(0)
+            .backoff(Duration::from_millis(50), Duration::from_millis(10));
+        // At least one attempt is always made
+        assert_eq!(policy.attempts(), 1);
+        // The cap is never below the base delay
+        assert_eq!(policy.backoff_delay(3), Duration::from_millis(50));
+        assert_eq!(RetryPolicy::none().attempts(), 1);
+    }
+
+    #[test]
+    fn full_jitter_stays_within_cap() {
+        let cap = Duration::from_millis(400);
+        assert_eq!(full_jitter(cap, 0), Duration::ZERO);
+        assert_eq!(full_jitter(cap, 400), cap);
+        assert_eq!(full_jitter(cap, 401), Duration::ZERO);
+        assert_eq!(full_jitter(cap, 1234), Duration::from_millis(31));
+        assert!(full_jitter(cap, u64::MAX) <= cap);
+        assert_eq!(full_jitter(Duration::ZERO, u64::MAX), Duration::ZERO);
+
+        let policy = RetryPolicy::default();
+        for _ in 0..32 {
+            assert!(policy.delay(2, None) <= Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn delay_prefers_capped_retry_after() {
+        let policy = RetryPolicy::default().retry_after_max(Duration::from_secs(3));
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(2))),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(90))),
+            Duration::from_secs(3)
+        );
+    }
+
+    #[test]
+    fn retry_after_header_forms() {
+        use reqwest::header::{HeaderValue, RETRY_AFTER};
+
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers, now), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("90"));
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(90)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:30 GMT"),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(30)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:00:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers, now), None);
+    }
+
+    #[test]
+    fn is_transient_status() {
+        assert!(RetryPolicy::is_transient_status(
+            StatusCode::TOO_MANY_REQUESTS
+        ));
+        assert!(RetryPolicy::is_transient_status(
+            StatusCode::INTERNAL_SERVER_ERROR
+        ));
+        assert!(RetryPolicy::is_transient_status(StatusCode::BAD_GATEWAY));
+        assert!(RetryPolicy::is_transient_status(
+            StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert!(RetryPolicy::is_transient_status(
+            StatusCode::GATEWAY_TIMEOUT
+        ));
+        assert!(!RetryPolicy::is_transient_status(StatusCode::NOT_FOUND));
+        assert!(!RetryPolicy::is_transient_status(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn failure_from_status_classifies() {
+        let headers = HeaderMap::new();
+        let transient = Failure::from_status(
+            StatusCode::SERVICE_UNAVAILABLE,
+            &headers,
+            anyhow::anyhow!("503"),
+        );
+        assert!(matches!(transient, Failure::Transient { .. }));
+        let permanent =
+            Failure::from_status(StatusCode::NOT_FOUND, &headers, anyhow::anyhow!("404"));
+        assert!(matches!(permanent, Failure::Permanent(_)));
+    }
+
+    #[tokio::test]
+    async fn run_retries_transient_failures_until_success() {
+        let policy =
+            RetryPolicy::default().backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let calls = AtomicU32::new(0);
+        let result = policy
+            .run("test request", |attempt| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt < 3 {
+                        Err(Failure::Transient {
+                            error: anyhow::anyhow!("flaky"),
+                            retry_after: None,
+                        })
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn run_stops_after_max_attempts() {
+        let policy = RetryPolicy::default()
+            .max_attempts(2)
+            .backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = policy
+            .run("test request", |attempt| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    Err(Failure::Transient {
+                        error: anyhow::anyhow!("attempt {attempt} failed"),
+                        retry_after: None,
+                    })
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap_err().to_string(), "attempt 2 failed");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn run_returns_permanent_failures_immediately() {
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = RetryPolicy::default()
+            .run("test request", |_| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async { Err(Failure::Permanent(anyhow::anyhow!("not found"))) }
+            })
+            .await;
+        assert_eq!(result.unwrap_err().to_string(), "not found");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,427 @@
+//! Single-flight deduplication of identical in-flight requests.
+//!
+//! When an agent fires the same search several times at once (parallel tool calls),
+//! [`SingleFlight`] lets the first caller (the leader) make the upstream call while
+//! the others wait for and share its result. Requests are identified by
+//! [`request_key`], which ignores the JSON-RPC `id` and `params._meta`, so each
+//! caller re-stamps the shared response with its own ID.
+//!
+//! Progress and log notifications streamed during the call reach every caller that
+//! joined it: the call is made with a [`NotificationRelay`] each joining caller's
+//! relay is added to, and progress is re-stamped with each caller's own token.
+//!
+//! If the leader is cancelled before it finishes, one of the waiting callers takes
+//! over and makes the call itself.
+//!
+//! A failed call that others joined is returned to every caller as a [`SharedError`],
+//! which reads like the original error and still lets callers downcast to its type.
+
+use super::sse::NotificationRelay;
+use anyhow::Result;
+use core::fmt;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::broadcast;
+use tracing::debug;
+
+/// Shared outcome of a call.
+type Outcome<V> = Result<V, Arc<anyhow::Error>>;
+
+/// Error of a call several callers shared, returned to each of them
+///
+/// It displays like the original error and has the same sources; use
+/// [`SharedError::downcast_ref`] to reach the original error type.
+#[derive(Debug, Clone)]
+pub struct SharedError(Arc<anyhow::Error>);
+
+impl SharedError {
+    /// Returns the original error, or one of its context layers, as `E`.
+    #[allow(
+        dead_code,
+        reason = "lets callers inspect a shared error like an unshared one"
+    )]
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        self.0.downcast_ref::<E>()
+    }
+}
+
+impl fmt::Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self.0, f)
+    }
+}
+
+impl core::error::Error for SharedError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// A call in flight, as seen by callers joining it
+#[derive(Debug)]
+struct Call<V> {
+    /// Channel announcing the outcome of the call.
+    outcome: broadcast::Sender<Outcome<V>>,
+    /// Relay the call forwards notifications to; joining callers add theirs.
+    relay: NotificationRelay,
+}
+
+/// Deduplicates concurrent calls with the same key
+#[derive(Debug)]
+pub struct SingleFlight<V> {
+    /// Calls in flight, by key.
+    calls: Mutex<HashMap<String, Call<V>>>,
+}
+
+impl<V> Default for SingleFlight<V> {
+    fn default() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<V: Clone> SingleFlight<V> {
+    /// Runs `call`, unless a call with the same `key` is already in flight, in which
+    /// case its result is awaited and shared instead.
+    ///
+    /// # Arguments
+    /// * `key` - Identifies equivalent calls, see [`request_key`]
+    /// * `relay` - Receives the notifications of the call this caller makes or joins
+    /// * `call` - Makes the call, forwarding notifications to the relay it is given;
+    ///   only invoked if this caller leads
+    ///
+    /// # Errors
+    /// Returns the error of the call; once other callers have joined, every caller
+    /// gets it as a [`SharedError`]
+    pub async fn run<F, Fut>(
+        &self,
+        key: &str,
+        relay: Option<&NotificationRelay>,
+        call: F,
+    ) -> Result<V>
+    where
+        F: FnOnce(NotificationRelay) -> Fut,
+        Fut: Future<Output = Result<V>>,
+    {
+        let shared_relay = loop {
+            let mut receiver = {
+                let mut calls = self.calls.lock().unwrap_or_else(PoisonError::into_inner);
+                let Some(in_flight) = calls.get(key) else {
+                    let shared_relay = NotificationRelay::default();
+                    if let Some(relay) = relay {
+                        shared_relay.join(relay);
+                    }
+                    calls.insert(
+                        key.to_owned(),
+                        Call {
+                            outcome: broadcast::channel(1_usize).0,
+                            relay: shared_relay.clone(),
+                        },
+                    );
+                    break shared_relay;
+                };
+                if let Some(relay) = relay {
+                    in_flight.relay.join(relay);
+                }
+                let receiver = in_flight.outcome.subscribe();
+                drop(calls);
+                receiver
+            };
+
+            debug!("Joining in-flight request: {}", key);
+            if let Ok(outcome) = receiver.recv().await {
+                return outcome.map_err(|error| anyhow::Error::new(SharedError(error)));
+            }
+            // The leader was cancelled; lead or join the next call instead
+            debug!("In-flight request was cancelled: {}", key);
+        };
+
+        let flight = Flight {
+            owner: self,
+            key,
+            landed: false,
+        };
+        let result = call(shared_relay).await;
+        let Some(sender) = flight
+            .land()
+            .map(|landed| landed.outcome)
+            .filter(|sender| sender.receiver_count() > 0_usize)
+        else {
+            return result;
+        };
+        let outcome = result.map_err(Arc::new);
+        // Joined callers may have been cancelled meanwhile, which is fine
+        let _ = sender.send(outcome.clone());
+        outcome.map_err(|error| anyhow::Error::new(SharedError(error)))
+    }
+
+    /// Returns the number of calls in flight.
+    #[cfg(test)]
+    fn in_flight(&self) -> usize {
+        self.calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+/// A call in flight; removes its entry when finished or cancelled.
+struct Flight<'flights, V> {
+    /// Single-flight group the call belongs to.
+    owner: &'flights SingleFlight<V>,
+    /// Key of the call.
+    key: &'flights str,
+    /// Set once the entry has been removed.
+    landed: bool,
+}
+
+impl<V> Flight<'_, V> {
+    /// Removes the call's entry, returning it to announce the outcome on.
+    fn land(mut self) -> Option<Call<V>> {
+        self.landed = true;
+        self.remove()
+    }
+
+    /// Removes the call's entry from the group.
+    fn remove(&self) -> Option<Call<V>> {
+        self.owner
+            .calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(self.key)
+    }
+}
+
+impl<V> Drop for Flight<'_, V> {
+    fn drop(&mut self) {
+        if !self.landed {
+            // Dropping the sender tells joined callers that nothing is coming
+            drop(self.remove());
+        }
+    }
+}
+
+/// Builds the single-flight key for a JSON-RPC request.
+///
+/// The key is the request serialized with sorted object keys, without its `id` and
+/// `params._meta` (which carries per-call data such as progress tokens). Whether a
+/// progress token was sent is kept, since the API only reports progress for requests
+/// that ask for it.
+pub fn request_key(request: &Value) -> String {
+    let mut normalized = request.clone();
+    if let Some(object) = normalized.as_object_mut() {
+        object.remove("id");
+    }
+    if let Some(params) = normalized.get_mut("params").and_then(Value::as_object_mut)
+        && let Some(meta) = params.remove("_meta")
+        && meta.get("progressToken").is_some()
+    {
+        params.insert(
+            "_meta".to_owned(),
+            serde_json::json!({"progressToken": true}),
+        );
+    }
+    // `serde_json::Map` keeps its keys sorted, so equal requests serialize equally
+    normalized.to_string()
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+    use crate::http::body::ResponseTooLarge;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::oneshot;
+
+    #[test]
+    fn request_key_ignores_id_and_meta() {
+        let first = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {"name": "SearchBun", "arguments": {"query": "serve"}, "_meta": {"progressToken": 1}}
+        });
+        let second = json!({
+            "params": {"arguments": {"query": "serve"}, "name": "SearchBun", "_meta": {"progressToken": "t"}},
+            "method": "tools/call",
+            "id": "other",
+            "jsonrpc": "2.0"
+        });
+        assert_eq!(request_key(&first), request_key(&second));
+
+        // Without a progress token the API reports no progress, so it is not shared
+        let no_progress = json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "tools/call",
+            "params": {"name": "SearchBun", "arguments": {"query": "serve"}, "_meta": {}}
+        });
+        let mut plain = no_progress.clone();
+        plain["params"].as_object_mut().unwrap().remove("_meta");
+        assert_eq!(request_key(&no_progress), request_key(&plain));
+        assert_ne!(request_key(&first), request_key(&no_progress));
+
+        let different = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {"name": "SearchBun", "arguments": {"query": "file"}}
+        });
+        assert_ne!(request_key(&first), request_key(&different));
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_calls_share_one_call() {
+        let flights = SingleFlight::default();
+        let calls = AtomicUsize::new(0_usize);
+        let (release, released) = oneshot::channel::<()>();
+
+        let leader = flights.run("key", None, |_| async {
+            calls.fetch_add(1_usize, Ordering::SeqCst);
+            released.await.unwrap();
+            Ok(42_i32)
+        });
+        let follower = flights.run("key", None, |_| async {
+            calls.fetch_add(1_usize, Ordering::SeqCst);
+            Ok(0_i32)
+        });
+        let (first, second, ()) = tokio::join!(leader, follower, async {
+            tokio::task::yield_now().await;
+            release.send(()).unwrap();
+        });
+
+        assert_eq!(first.unwrap(), 42_i32);
+        assert_eq!(second.unwrap(), 42_i32);
+        assert_eq!(calls.load(Ordering::SeqCst), 1_usize);
+        assert_eq!(flights.in_flight(), 0_usize);
+    }
+
+    #[tokio::test]
+    async fn notifications_reach_every_joined_caller() {
+        let flights = SingleFlight::default();
+        let (release, released) = oneshot::channel::<()>();
+        let (leader_sender, mut leader_received) = tokio::sync::mpsc::unbounded_channel();
+        let (follower_sender, mut follower_received) = tokio::sync::mpsc::unbounded_channel();
+        let leader_relay =
+            NotificationRelay::new(leader_sender, Some(&json!({"_meta": {"progressToken": 1}})));
+        let follower_relay = NotificationRelay::new(
+            follower_sender,
+            Some(&json!({"_meta": {"progressToken": "f"}})),
+        );
+
+        let leader = flights.run("key", Some(&leader_relay), |relay| async move {
+            released.await.unwrap();
+            relay.relay(&json!({"method": "notifications/progress", "params": {"progress": 1}}));
+            Ok(42_i32)
+        });
+        let follower = flights.run("key", Some(&follower_relay), |_| async { Ok(0_i32) });
+        let (first, second, ()) = tokio::join!(leader, follower, async {
+            tokio::task::yield_now().await;
+            release.send(()).unwrap();
+        });
+
+        assert_eq!((first.unwrap(), second.unwrap()), (42_i32, 42_i32));
+        let leader_progress = leader_received.try_recv().unwrap();
+        assert_eq!(leader_progress["params"]["progressToken"], 1);
+        let follower_progress = follower_received.try_recv().unwrap();
+        assert_eq!(follower_progress["params"]["progressToken"], "f");
+        assert_eq!(follower_progress["params"]["progress"], 1);
+    }
+
+    #[tokio::test]
+    async fn different_keys_and_sequential_calls_are_not_shared() {
+        let flights = SingleFlight::default();
+        let calls = AtomicUsize::new(0_usize);
+        let call = |_| async { Ok::<_, anyhow::Error>(calls.fetch_add(1_usize, Ordering::SeqCst)) };
+
+        let (first, second) =
+            tokio::join!(flights.run("a", None, call), flights.run("b", None, call));
+        assert_ne!(first.unwrap(), second.unwrap());
+        flights.run("a", None, call).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3_usize);
+    }
+
+    #[tokio::test]
+    async fn errors_are_shared() {
+        let flights = SingleFlight::<i32>::default();
+        let (release, released) = oneshot::channel::<()>();
+
+        let leader = flights.run("key", None, |_| async {
+            released.await.unwrap();
+            Err(anyhow::anyhow!("upstream failed"))
+        });
+        let follower = flights.run("key", None, |_| async { Ok(0_i32) });
+        let (first, second, ()) = tokio::join!(leader, follower, async {
+            tokio::task::yield_now().await;
+            release.send(()).unwrap();
+        });
+
+        assert_eq!(first.unwrap_err().to_string(), "upstream failed");
+        assert_eq!(second.unwrap_err().to_string(), "upstream failed");
+    }
+
+    #[tokio::test]
+    async fn followers_can_downcast_the_shared_error() {
+        let flights = SingleFlight::<i32>::default();
+        let (release, released) = oneshot::channel::<()>();
+
+        let leader = flights.run("key", None, |_| async {
+            released.await.unwrap();
+            Err(anyhow::Error::new(ResponseTooLarge { limit: 8_usize }).context("API response"))
+        });
+        let follower = flights.run("key", None, |_| async { Ok(0_i32) });
+        let (first, second, ()) = tokio::join!(leader, follower, async {
+            tokio::task::yield_now().await;
+            release.send(()).unwrap();
+        });
+
+        let first = first.unwrap_err();
+        let second = second.unwrap_err();
+        assert_eq!(
+            format!("{first:#}"),
+            "API response: response too large: body exceeds the limit of 8 bytes"
+        );
+        assert_eq!(format!("{second:#}"), format!("{first:#}"));
+        assert!(first.downcast_ref::<SharedError>().is_some());
+        let shared = second
+            .downcast_ref::<SharedError>()
+            .expect("follower error is shared");
+        assert_eq!(
+            shared.downcast_ref::<ResponseTooLarge>(),
+            Some(&ResponseTooLarge { limit: 8_usize })
+        );
+        assert_eq!(shared.downcast_ref::<&str>(), Some(&"API response"));
+        assert!(
+            second
+                .chain()
+                .any(|cause| cause.downcast_ref::<ResponseTooLarge>().is_some()),
+            "{second:#}"
+        );
+    }
+
+    #[tokio::test]
+    async fn follower_takes_over_when_leader_is_cancelled() {
+        let flights = SingleFlight::default();
+        let leader = flights.run("key", None, |_| futures::future::pending::<Result<i32>>());
+        let follower = flights.run("key", None, |_| async { Ok(7_i32) });
+
+        // The leader times out, and is dropped, after the follower has joined
+        let (timed_out, result) = tokio::join!(
+            tokio::time::timeout(core::time::Duration::from_millis(20), leader),
+            async {
+                tokio::task::yield_now().await;
+                follower.await
+            }
+        );
+        timed_out.unwrap_err();
+        assert_eq!(result.unwrap(), 7_i32);
+        assert_eq!(flights.in_flight(), 0_usize);
+    }
+}
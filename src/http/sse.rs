@@ -0,0 +1,705 @@
+//! Assembles the JSON-RPC response from an upstream SSE stream.
+//!
+//! A Streamable HTTP server may answer a `POST` with a `text/event-stream` that
+//! carries more than the response itself. [`SseAssembler`] consumes the events of
+//! such a stream (already split into fields by `eventsource-stream`, which joins
+//! multi-line `data:` fields with `\n`) and:
+//!
+//! - **Accumulates JSON** - the data of consecutive events is concatenated until it
+//!   forms complete JSON, so a message split across events is reassembled. Several
+//!   messages in one event (or a JSON-RPC batch array) are all picked up.
+//! - **Relays progress and logs** - `notifications/progress` and
+//!   `notifications/message` sent before the response are handed to a
+//!   [`NotificationRelay`], which forwards them to the MCP client while the request
+//!   is in flight.
+//! - **Skips other messages** - other notifications, server requests, and responses
+//!   to other request IDs are logged and passed over.
+//! - **Tracks `id`/`retry`** - the last event ID and reconnection delay announced by
+//!   the server are kept, so a broken stream can be resumed with `Last-Event-ID` and
+//!   the resumed events fed to the same assembler.
+//!
+//! Only `message` (or unnamed) and `completion` events carry messages; other event
+//! types such as heartbeats are ignored. Data that is not JSON is dropped with a
+//! warning, and a stream that ends without the response produces an error that
+//! describes what was received instead.
+
+use anyhow::Result;
+use eventsource_stream::{Event, Eventsource as _};
+use futures::{Stream, StreamExt as _};
+use serde_json::Value;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, warn};
+
+/// Maximum size of JSON data buffered while waiting for it to complete (16 MB)
+pub const MAX_PENDING_DATA_SIZE: usize = 16_000_000_usize;
+
+/// Notification methods relayed to the MCP client while a request is in flight
+pub const RELAYED_METHODS: [&str; 2] = ["notifications/progress", "notifications/message"];
+
+/// Forwards upstream notifications for one request to the MCP client.
+///
+/// Progress notifications are rewritten to carry the `_meta.progressToken` of the
+/// client's original request, and dropped if the client did not ask for progress.
+/// When identical requests share one upstream call, the relays of the other requests
+/// [join](Self::join) the one passed to the call, which then forwards every
+/// notification to each of them with their own progress token. Clones share their
+/// recipients.
+#[derive(Clone, Debug, Default)]
+pub struct NotificationRelay {
+    /// Client requests receiving the notifications.
+    recipients: Arc<Mutex<Vec<Recipient>>>,
+}
+
+/// Client request a [`NotificationRelay`] forwards notifications to
+#[derive(Clone, Debug)]
+struct Recipient {
+    /// Channel to the client connection.
+    sender: UnboundedSender<Value>,
+    /// Progress token from the client's request, if any.
+    progress_token: Option<Value>,
+}
+
+impl NotificationRelay {
+    /// Creates a relay for a client request.
+    ///
+    /// # Arguments
+    /// * `sender` - Channel to the client connection
+    /// * `params` - Parameters of the client's request, read for `_meta.progressToken`
+    pub fn new(sender: UnboundedSender<Value>, params: Option<&Value>) -> Self {
+        let progress_token = params
+            .and_then(|params| params.pointer("/_meta/progressToken"))
+            .filter(|token| token.is_string() || token.is_number())
+            .cloned();
+        Self {
+            recipients: Arc::new(Mutex::new(vec![Recipient {
+                sender,
+                progress_token,
+            }])),
+        }
+    }
+
+    /// Also forwards later notifications to the recipients of `other`.
+    pub fn join(&self, other: &Self) {
+        let joining = other.recipients().clone();
+        self.recipients().extend(joining);
+    }
+
+    /// Forwards `notification` to every recipient, rewriting its progress token.
+    pub fn relay(&self, notification: &Value) {
+        let recipients = self.recipients().clone();
+        for recipient in recipients {
+            recipient.relay(notification.clone());
+        }
+    }
+
+    /// Locks the list of recipients.
+    fn recipients(&self) -> std::sync::MutexGuard<'_, Vec<Recipient>> {
+        self.recipients
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Recipient {
+    /// Forwards `notification` to the client, rewriting its progress token.
+    fn relay(&self, mut notification: Value) {
+        if notification.get("method").and_then(Value::as_str) == Some("notifications/progress") {
+            let Some(token) = &self.progress_token else {
+                debug!("Dropping upstream progress: the client did not request progress");
+                return;
+            };
+            if let Some(params) = notification
+                .get_mut("params")
+                .and_then(Value::as_object_mut)
+            {
+                params.insert("progressToken".to_owned(), token.clone());
+            }
+        }
+        if self.sender.send(notification).is_err() {
+            debug!("Dropping upstream notification: the client connection is closed");
+        }
+    }
+}
+
+/// How reading one SSE stream stopped
+#[derive(Debug)]
+pub enum Outcome {
+    /// The awaited JSON-RPC response arrived.
+    Response(Value),
+    /// The stream ended before the response.
+    Ended,
+    /// The stream failed before the response, e.g. the connection dropped.
+    Failed(String),
+}
+
+/// Feeds an SSE byte stream to `assembler` until the response arrives or the stream
+/// stops.
+///
+/// The same assembler can be fed a resumed stream afterwards, see
+/// [`SseAssembler::last_event_id`].
+///
+/// # Arguments
+/// * `stream` - Body of an SSE response
+/// * `assembler` - Assembler waiting for the response
+/// * `relay` - Receives progress and log notifications sent before the response
+///
+/// # Errors
+/// Returns an error if a message grows beyond [`MAX_PENDING_DATA_SIZE`]
+pub async fn feed<S, B, E>(
+    stream: S,
+    assembler: &mut SseAssembler,
+    relay: Option<&NotificationRelay>,
+) -> Result<Outcome>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: core::fmt::Display,
+{
+    let mut events = stream.eventsource();
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(event) => {
+                let response = assembler.push(&event)?;
+                for notification in assembler.take_notifications() {
+                    if let Some(relay) = relay {
+                        relay.relay(&notification);
+                    }
+                }
+                if let Some(response) = response {
+                    debug!(
+                        "SSE response complete (last event ID {:?}, retry {:?})",
+                        assembler.last_event_id(),
+                        assembler.retry()
+                    );
+                    return Ok(Outcome::Response(response));
+                }
+            }
+            Err(error) => {
+                warn!("SSE stream error: {}", error);
+                return Ok(Outcome::Failed(format!("stream failed: {error}")));
+            }
+        }
+    }
+    Ok(Outcome::Ended)
+}
+
+/// Incremental assembler for the JSON-RPC response carried by an SSE stream
+#[derive(Debug, Default)]
+pub struct SseAssembler {
+    /// ID of the request being answered; `None` accepts any response.
+    request_id: Option<Value>,
+    /// Event data received so far that does not form complete JSON yet.
+    pending: String,
+    /// ID of the last event that carried one.
+    last_event_id: Option<String>,
+    /// Reconnection delay last announced by the server.
+    retry: Option<Duration>,
+    /// Number of messages skipped because they were not the response.
+    skipped: usize,
+    /// Bytes of data dropped because they were not valid JSON.
+    discarded: usize,
+    /// Relayable notifications received since the last [`Self::take_notifications`].
+    notifications: Vec<Value>,
+}
+
+impl SseAssembler {
+    /// Creates an assembler waiting for the response to `request_id`.
+    pub fn new(request_id: Option<Value>) -> Self {
+        Self {
+            request_id,
+            ..Self::default()
+        }
+    }
+
+    /// Feeds one event to the assembler.
+    ///
+    /// # Returns
+    /// The JSON-RPC response once it is complete
+    ///
+    /// # Errors
+    /// Returns an error if incomplete data grows beyond [`MAX_PENDING_DATA_SIZE`]
+    pub fn push(&mut self, event: &Event) -> Result<Option<Value>> {
+        if !event.id.is_empty() && self.last_event_id.as_deref() != Some(&event.id) {
+            self.last_event_id = Some(event.id.clone());
+        }
+        if let Some(retry) = event.retry {
+            debug!("SSE retry interval set to {} ms", retry.as_millis());
+            self.retry = Some(retry);
+        }
+
+        let event_type = if event.event.is_empty() {
+            "message"
+        } else {
+            event.event.as_str()
+        };
+        if event_type != "message" && event_type != "completion" {
+            debug!("Skipping SSE event type: {}", event_type);
+            return Ok(None);
+        }
+        if event.data.trim().is_empty() {
+            return Ok(None);
+        }
+
+        self.pending.push_str(&event.data);
+        if self.pending.len() > MAX_PENDING_DATA_SIZE {
+            return Err(anyhow::anyhow!(
+                "SSE message exceeds {MAX_PENDING_DATA_SIZE} bytes without forming complete JSON"
+            ));
+        }
+
+        for message in self.drain_messages(&event.data) {
+            if let Some(response) = self.classify(message) {
+                return Ok(Some(response));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes and returns the progress and log notifications received so far.
+    pub fn take_notifications(&mut self) -> Vec<Value> {
+        core::mem::take(&mut self.notifications)
+    }
+
+    /// Returns the ID of the last event that carried one.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// Returns the reconnection delay last announced by the server.
+    pub const fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Builds the error for a stream that stopped before the response arrived.
+    ///
+    /// # Arguments
+    /// * `cause` - How the stream stopped, e.g. "stream ended"
+    pub fn incomplete(&self, cause: &str) -> anyhow::Error {
+        let mut details = Vec::new();
+        if self.skipped > 0_usize {
+            details.push(format!("{} other message(s) skipped", self.skipped));
+        }
+        if !self.pending.trim().is_empty() {
+            details.push(format!(
+                "{} bytes of incomplete JSON pending",
+                self.pending.len()
+            ));
+        }
+        if self.discarded > 0_usize {
+            details.push(format!("{} bytes of invalid data dropped", self.discarded));
+        }
+        if let Some(id) = &self.last_event_id {
+            details.push(format!("last event ID {id:?}"));
+        }
+        let details = if details.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", details.join(", "))
+        };
+        anyhow::anyhow!("No valid JSON-RPC response in SSE stream: {cause}{details}")
+    }
+
+    /// Parses every complete JSON value from the pending data, keeping an incomplete
+    /// tail for the next event.
+    ///
+    /// # Arguments
+    /// * `latest` - Data of the event just appended, to resynchronize after invalid
+    ///   data
+    fn drain_messages(&mut self, latest: &str) -> Vec<Value> {
+        let mut messages = Vec::new();
+        let mut values = serde_json::Deserializer::from_str(&self.pending).into_iter::<Value>();
+        let error = loop {
+            match values.next() {
+                Some(Ok(value)) => messages.push(value),
+                Some(Err(error)) if error.is_eof() => break None,
+                Some(Err(error)) => break Some(error),
+                None => break None,
+            }
+        };
+        let consumed = values.byte_offset();
+
+        if let Some(error) = error {
+            // Drop the invalid part; the latest event may start a new message
+            let invalid = &self.pending[consumed..];
+            let keep = if invalid.len() > latest.len() && invalid.ends_with(latest) {
+                latest.to_owned()
+            } else {
+                String::new()
+            };
+            warn!(
+                "Dropping invalid SSE data ({}): {}",
+                error,
+                &invalid[..invalid.floor_char_boundary(200_usize)]
+            );
+            self.discarded += invalid.len() - keep.len();
+            self.pending = keep;
+            if !self.pending.is_empty() {
+                messages.extend(self.drain_messages(latest));
+            }
+            return messages;
+        }
+
+        self.pending.drain(..consumed);
+        if self.pending.trim().is_empty() {
+            self.pending.clear();
+        }
+        messages
+    }
+
+    /// Returns `message` if it is the awaited response; keeps relayable notifications
+    /// and logs and skips anything else.
+    fn classify(&mut self, message: Value) -> Option<Value> {
+        if let Value::Array(batch) = message {
+            let mut response = None;
+            for item in batch {
+                if let Some(found) = self.classify(item) {
+                    response.get_or_insert(found);
+                }
+            }
+            return response;
+        }
+
+        let is_response = message.get("result").is_some() || message.get("error").is_some();
+        let answers_request = self
+            .request_id
+            .as_ref()
+            .is_none_or(|id| message.get("id").is_none_or(|message_id| message_id == id));
+        if is_response && answers_request {
+            return Some(message);
+        }
+        if message.get("id").is_none()
+            && let Some(method) = message.get("method").and_then(Value::as_str)
+            && RELAYED_METHODS.contains(&method)
+        {
+            debug!("Received upstream notification {}", method);
+            self.notifications.push(message);
+            return None;
+        }
+
+        self.skipped += 1_usize;
+        let id = message.get("id").map_or_else(String::new, Value::to_string);
+        match message.get("method").and_then(Value::as_str) {
+            Some(method) if message.get("id").is_some() => {
+                warn!(
+                    "Ignoring upstream request {} sent before the response",
+                    method
+                );
+            }
+            Some(method) => debug!("Skipping upstream notification {}", method),
+            None if is_response => warn!("Skipping response to another request: {}", id),
+            None => warn!("Skipping SSE message that is not JSON-RPC"),
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use serde_json::json;
+
+    /// Feeds raw SSE chunks to an assembler, returning the response or the error
+    /// reported when the stream stops early.
+    async fn assemble(chunks: &[&'static str], request_id: Option<&Value>) -> Result<Value> {
+        let stream = futures::stream::iter(chunks.iter().map(|chunk| Ok::<_, Infallible>(*chunk)));
+        let mut assembler = SseAssembler::new(request_id.cloned());
+        match feed(stream, &mut assembler, None).await? {
+            Outcome::Response(response) => Ok(response),
+            Outcome::Ended => Err(assembler.incomplete("stream ended")),
+            Outcome::Failed(cause) => Err(assembler.incomplete(&cause)),
+        }
+    }
+
+    fn event(data: &str) -> Event {
+        Event {
+            data: data.to_owned(),
+            ..Event::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn single_event_response() {
+        let response = assemble(
+            &["event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}\n\n"],
+            Some(&json!(1)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn multi_line_data_field() {
+        let response = assemble(
+            &["data: {\"jsonrpc\": \"2.0\",\ndata:  \"id\": 1,\ndata: \"result\": {\"ok\": true}}\n\n"],
+            Some(&json!(1)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["result"]["ok"], true);
+    }
+
+    #[tokio::test]
+    async fn json_split_across_events() {
+        let response = assemble(
+            &[
+                "data: {\"jsonrpc\":\"2.0\",\"id\":7,\"res\n\n",
+                "data: ult\":{\"content\":[{\"type\":\"text\",\n\n",
+                "data: \"text\":\"split\"}]}}\n\n",
+            ],
+            Some(&json!(7)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["result"]["content"][0]["text"], "split");
+    }
+
+    #[tokio::test]
+    async fn event_split_across_chunks() {
+        let response = assemble(
+            &["da", "ta: {\"id\":1,", "\"result\":", "{}}\n", "\n"],
+            Some(&json!(1)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn notifications_and_requests_before_response_are_skipped() {
+        let response = assemble(
+            &[
+                "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\",\"params\":{\"progress\":1}}\n\n",
+                "data: {\"jsonrpc\":\"2.0\",\"id\":\"s1\",\"method\":\"sampling/createMessage\"}\n\n",
+                "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/message\"}\n\n",
+                "data: {\"jsonrpc\":\"2.0\",\"id\":3,\"result\":{\"done\":true}}\n\n",
+            ],
+            Some(&json!(3)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["result"]["done"], true);
+    }
+
+    #[tokio::test]
+    async fn responses_to_other_requests_are_skipped() {
+        let response = assemble(
+            &[
+                "data: {\"id\":2,\"result\":\"other\"}\n\n",
+                "data: {\"id\":1,\"result\":\"mine\"}\n\n",
+            ],
+            Some(&json!(1)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["result"], "mine");
+    }
+
+    #[tokio::test]
+    async fn several_messages_in_one_event_and_batches() {
+        let response = assemble(
+            &["data: {\"method\":\"notifications/progress\"} [{\"method\":\"a\"},{\"id\":4,\"error\":{\"code\":-1,\"message\":\"x\"}}]\n\n"],
+            Some(&json!(4)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["error"]["code"], -1);
+    }
+
+    #[tokio::test]
+    async fn heartbeats_and_other_event_types_are_ignored() {
+        let response = assemble(
+            &[
+                ": keep-alive\n\n",
+                "event: ping\ndata: {\"id\":1,\"result\":\"not this\"}\n\n",
+                "data:\n\n",
+                "event: completion\ndata: {\"id\":1,\"result\":\"this\"}\n\n",
+            ],
+            Some(&json!(1)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["result"], "this");
+    }
+
+    #[tokio::test]
+    async fn invalid_data_is_dropped_and_parsing_resumes() {
+        let response = assemble(
+            &[
+                "data: not json\n\n",
+                "data: {\"id\":1,\"result\":\"recovered\"}\n\n",
+            ],
+            Some(&json!(1)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["result"], "recovered");
+    }
+
+    #[tokio::test]
+    async fn response_without_request_id_is_accepted() {
+        let response = assemble(&["data: {\"result\":{}}\n\n"], Some(&json!(9)))
+            .await
+            .unwrap();
+        assert!(response.get("result").is_some());
+        let any = assemble(&["data: {\"id\":5,\"result\":{}}\n\n"], None)
+            .await
+            .unwrap();
+        assert_eq!(any["id"], 5);
+    }
+
+    #[tokio::test]
+    async fn stream_ending_early_reports_what_was_seen() {
+        let error = assemble(
+            &[
+                "id: 42\ndata: {\"method\":\"notifications/resources/updated\"}\n\n",
+                "data: {\"id\":1,\"resu\n\n",
+            ],
+            Some(&json!(1)),
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("No valid JSON-RPC response"), "{error}");
+        assert!(error.contains("stream ended"), "{error}");
+        assert!(error.contains("1 other message(s) skipped"), "{error}");
+        assert!(error.contains("incomplete JSON pending"), "{error}");
+        assert!(error.contains("\"42\""), "{error}");
+    }
+
+    #[tokio::test]
+    async fn stream_error_is_reported() {
+        let stream =
+            futures::stream::iter([Ok("data: {\"method\":\"x\"}\n\n"), Err("connection reset")]);
+        let mut assembler = SseAssembler::new(None);
+        let outcome = feed(stream, &mut assembler, None).await.unwrap();
+        assert!(
+            matches!(&outcome, Outcome::Failed(cause) if cause.contains("connection reset")),
+            "{outcome:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn resumed_stream_continues_the_same_response() {
+        let mut assembler = SseAssembler::new(Some(json!(1)));
+        let broken = futures::stream::iter([
+            Ok("id: 1\nretry: 250\ndata: {\"method\":\"notifications/message\"}\n\n"),
+            Ok("id: 2\ndata: {\"id\":1,\"result\":{\"parts\":\n\n"),
+            Ok("id: 3\ndata: [1,"),
+            Err("connection reset"),
+        ]);
+        let outcome = feed(broken, &mut assembler, None).await.unwrap();
+        assert!(matches!(outcome, Outcome::Failed(_)), "{outcome:?}");
+        // The unterminated event was never dispatched, so resumption starts after 2
+        assert_eq!(assembler.last_event_id(), Some("2"));
+        assert_eq!(assembler.retry(), Some(Duration::from_millis(250)));
+
+        let resumed = futures::stream::iter(
+            ["id: 3\ndata: [1,\n\n", "id: 4\ndata: 2]}}\n\n"].map(Ok::<_, Infallible>),
+        );
+        let resumed_outcome = feed(resumed, &mut assembler, None).await.unwrap();
+        let Outcome::Response(response) = resumed_outcome else {
+            panic!("expected a response, got {resumed_outcome:?}");
+        };
+        assert_eq!(response["result"]["parts"], json!([1, 2]));
+    }
+
+    #[tokio::test]
+    async fn progress_and_log_notifications_are_relayed() {
+        let (sender, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let params = json!({"_meta": {"progressToken": 7}});
+        let relay = NotificationRelay::new(sender, Some(&params));
+        let stream = futures::stream::iter(
+            [
+                "data: {\"method\":\"notifications/progress\",\"params\":{\"progressToken\":\"up\",\"progress\":1}}\n\n",
+                "data: {\"method\":\"notifications/resources/updated\"}\n\n",
+                "data: {\"method\":\"notifications/message\",\"params\":{\"level\":\"info\"}}\n\n",
+                "data: {\"id\":1,\"result\":{}}\n\n",
+            ]
+            .map(Ok::<_, Infallible>),
+        );
+
+        let mut assembler = SseAssembler::new(Some(json!(1)));
+        let outcome = feed(stream, &mut assembler, Some(&relay)).await.unwrap();
+        assert!(matches!(outcome, Outcome::Response(_)), "{outcome:?}");
+        let progress = received.try_recv().unwrap();
+        assert_eq!(progress["params"]["progressToken"], 7);
+        assert_eq!(progress["params"]["progress"], 1);
+        let log = received.try_recv().unwrap();
+        assert_eq!(log["method"], "notifications/message");
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn progress_is_dropped_without_client_token() {
+        let (sender, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let relay = NotificationRelay::new(sender, Some(&json!({"name": "SearchBun"})));
+        relay
+            .relay(&json!({"method": "notifications/progress", "params": {"progressToken": "up"}}));
+        relay.relay(&json!({"method": "notifications/message", "params": {"level": "debug"}}));
+        assert_eq!(
+            received.try_recv().unwrap()["method"],
+            "notifications/message"
+        );
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn joined_relays_get_notifications_with_their_own_token() {
+        let (first_sender, mut first) = tokio::sync::mpsc::unbounded_channel();
+        let (second_sender, mut second) = tokio::sync::mpsc::unbounded_channel();
+        let (third_sender, mut third) = tokio::sync::mpsc::unbounded_channel();
+        let shared = NotificationRelay::new(
+            first_sender,
+            Some(&json!({"_meta": {"progressToken": "a"}})),
+        );
+        shared.join(&NotificationRelay::new(
+            second_sender,
+            Some(&json!({"_meta": {"progressToken": 2}})),
+        ));
+        shared.join(&NotificationRelay::new(third_sender, None));
+
+        shared
+            .relay(&json!({"method": "notifications/progress", "params": {"progressToken": "up"}}));
+        assert_eq!(first.try_recv().unwrap()["params"]["progressToken"], "a");
+        assert_eq!(second.try_recv().unwrap()["params"]["progressToken"], 2);
+        assert!(third.try_recv().is_err(), "no progress without a token");
+
+        shared.relay(&json!({"method": "notifications/message", "params": {"level": "info"}}));
+        for received in [&mut first, &mut second, &mut third] {
+            assert_eq!(
+                received.try_recv().unwrap()["method"],
+                "notifications/message"
+            );
+        }
+    }
+
+    #[test]
+    fn id_and_retry_fields_are_tracked() {
+        let mut assembler = SseAssembler::new(None);
+        let mut first = event("{\"method\":\"a\"}");
+        first.id = "e1".to_owned();
+        first.retry = Some(Duration::from_millis(1500));
+        assert!(assembler.push(&first).unwrap().is_none());
+        assert_eq!(assembler.last_event_id(), Some("e1"));
+        assert_eq!(assembler.retry(), Some(Duration::from_millis(1500)));
+
+        let mut second = event("{\"method\":\"b\"}");
+        second.id = "e2".to_owned();
+        assembler.push(&second).unwrap();
+        assert_eq!(assembler.last_event_id(), Some("e2"));
+        assert_eq!(assembler.retry(), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn oversized_pending_data_is_an_error() {
+        let mut assembler = SseAssembler::new(None);
+        let chunk = format!("{{\"data\":\"{}", "x".repeat(MAX_PENDING_DATA_SIZE));
+        assert!(assembler.push(&event(&chunk)).is_err());
+    }
+}
@@ -19,25 +19,43 @@
 //!
 //! ## Architecture
 //!
-//! The proxy consists of three main modules:
+//! The proxy consists of seven main modules:
 //! - [`http`] - HTTP client with SSE parsing and retry logic
 //! - [`protocol`] - JSON-RPC 2.0 types and serialization
-//! - [`transport`] - Stdio transport layer for reading/writing messages
+//! - [`server`] - Streamable HTTP server for sharing one proxy between clients (`--listen`)
+//! - [`socket`] - Unix socket daemon (`--socket`) and stdio shim (`--connect`)
+//! - [`shutdown`] - Graceful shutdown on EOF, SIGINT and SIGTERM
+//! - [`watchdog`] - Parent-process watchdog and idle timeout
+//! - [`transport`] - `Transport` trait with stdio, Unix socket and in-memory implementations
 
 mod http;
 mod protocol;
+mod server;
+mod shutdown;
+mod socket;
 mod transport;
+mod watchdog;
 
 use anyhow::Result;
 use clap::{Parser, ValueEnum};
 use core::fmt::Write as _;
+use futures::StreamExt as _;
 use protocol::{JsonRpcRequest, JsonRpcResponse};
+use std::collections::HashMap;
 use std::fs;
-use tracing::{error, info, warn};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
 use tracing_subscriber::EnvFilter;
+use transport::Transport;
 
 /// Standard JSON-RPC 2.0 error code for parse errors (invalid JSON).
 const JSONRPC_PARSE_ERROR: i32 = -32700;
+/// Standard JSON-RPC 2.0 error code for invalid requests (e.g. oversized messages).
+const JSONRPC_INVALID_REQUEST: i32 = -32600;
 /// Standard JSON-RPC 2.0 error code for invalid parameters.
 const JSONRPC_INVALID_PARAMS: i32 = -32602;
 /// Standard JSON-RPC 2.0 error code for internal errors.
@@ -45,6 +63,14 @@ const JSONRPC_INTERNAL_ERROR: i32 = -32603;
 /// Standard JSON-RPC 2.0 error code for method not found errors.
 const JSONRPC_METHOD_NOT_FOUND: i32 = -32601;
 
+/// Number of consecutive transport read failures after which a connection is closed
+const MAX_CONSECUTIVE_READ_ERRORS: u32 = 5_u32;
+
+/// Default number of documentation pages fetched at once for Markdown output
+const DEFAULT_FETCH_CONCURRENCY: usize = 4_usize;
+/// Default time in seconds allowed for fetching all pages for Markdown output
+const DEFAULT_FETCH_DEADLINE_SECS: u64 = 15_u64;
+
 /// Output format for CLI search results
 #[derive(Debug, Clone, ValueEnum)]
 enum OutputFormat {
@@ -76,13 +102,86 @@ enum OutputFormat {
     # Run as MCP server (default mode, reads from stdin)
     bun-docs-mcp-proxy
 
+    # Run behind an LSP-style host that frames messages with Content-Length headers
+    bun-docs-mcp-proxy --framing content-length
+
+    # Share one MCP server between several clients over Streamable HTTP
+    bun-docs-mcp-proxy --listen 8080
+
+    # Require a bearer token and allow one extra browser origin
+    bun-docs-mcp-proxy --listen 8080 --auth-token-file ~/.mcp-token \
+        --allow-origin https://tools.example.com
+
+    # Allow up to 10 seconds for in-flight requests when stopping
+    bun-docs-mcp-proxy --grace-period 10
+
+    # Exit when the launching editor dies or after 10 idle minutes
+    bun-docs-mcp-proxy --watch-parent --idle-timeout 600
+
+    # Use a local mirror and allow more time on a slow VPN
+    bun-docs-mcp-proxy --upstream-url http://localhost:3000/mcp --timeout 30 --max-retries 5
+
+    # Search on a plane: answer from the cache only
+    bun-docs-mcp-proxy --offline -s "Bun.serve"
+
+    # Keep the persistent cache somewhere else, or turn it off
+    bun-docs-mcp-proxy --cache-dir /var/cache/bun-docs
+    bun-docs-mcp-proxy --no-disk-cache
+
+    # Run one warm daemon on a Unix socket and point each editor at it
+    bun-docs-mcp-proxy --socket /tmp/bun-docs.sock
+    bun-docs-mcp-proxy --connect /tmp/bun-docs.sock
+
 ENVIRONMENT:
     RUST_LOG    Set logging level (debug, info, warn, error)
                 Example: RUST_LOG=debug bun-docs-mcp-proxy -s "test"
+    BUN_DOCS_MCP_AUTH_TOKEN
+                Bearer token required in --listen mode (--auth-token-file wins)
+    BUN_DOCS_MCP_URL, BUN_DOCS_MCP_TIMEOUT, BUN_DOCS_MCP_MAX_RETRIES,
+    BUN_DOCS_MCP_BACKOFF_BASE_MS, BUN_DOCS_MCP_BACKOFF_MAX_MS,
+    BUN_DOCS_MCP_MAX_RETRY_AFTER, BUN_DOCS_MCP_CIRCUIT_THRESHOLD,
+    BUN_DOCS_MCP_CIRCUIT_COOLDOWN, BUN_DOCS_MCP_MAX_RESPONSE_SIZE,
+    BUN_DOCS_MCP_MAX_PAGE_SIZE, BUN_DOCS_MCP_CACHE_ENTRIES,
+    BUN_DOCS_MCP_CACHE_MAX_BYTES, BUN_DOCS_MCP_CACHE_TTL,
+    BUN_DOCS_MCP_CACHE_DIR, BUN_DOCS_MCP_DISK_CACHE_MAX_BYTES,
+    BUN_DOCS_MCP_DISK_CACHE_TTL
+                Defaults for --upstream-url, --timeout, --max-retries,
+                --backoff-base-ms, --backoff-max-ms, --max-retry-after,
+                --circuit-threshold, --circuit-cooldown, --max-response-size,
+                --max-page-size, --cache-entries,
+                --cache-max-bytes, --cache-ttl, --cache-dir,
+                --disk-cache-max-bytes and --disk-cache-ttl (flags win)
+    BUN_DOCS_MCP_FETCH_CONCURRENCY, BUN_DOCS_MCP_FETCH_DEADLINE
+                Defaults for --fetch-concurrency and --fetch-deadline (flags win)
+    BUN_DOCS_MCP_ALLOW_PAGE_HOSTS
+                Comma-separated default for --allow-page-host (flags win)
+    XDG_CACHE_HOME
+                Base of the default persistent cache directory (~/.cache if unset)
 
 MCP SERVER MODE:
     When run without --search, operates as an MCP (Model Context Protocol) server
-    reading JSON-RPC requests from stdin and writing responses to stdout."#
+    reading JSON-RPC requests from stdin and writing responses to stdout.
+    Messages are newline-delimited JSON or Content-Length framed (--framing);
+    by default the framing is detected from the first bytes of input.
+    Messages larger than --max-message-size are answered with a -32600 error.
+    On EOF, SIGINT or SIGTERM the proxy stops reading, lets in-flight requests
+    finish for up to --grace-period seconds and ends the upstream session. It
+    exits with 0 after EOF, 130/143 after SIGINT/SIGTERM, or 1 if in-flight
+    requests had to be cancelled; a second signal exits immediately.
+    --watch-parent and --idle-timeout shut down the same way (exit status 0).
+    With --listen, it serves the MCP Streamable HTTP transport at /mcp instead,
+    plus the legacy HTTP+SSE transport at /sse for older clients.
+    With --socket, it accepts any number of stdio-style connections on a Unix
+    socket; --connect relays stdin/stdout to such a daemon.
+    Requests with a non-loopback Origin outside --allow-origin get 403; when a
+    token is configured, requests without it get 401.
+    If the Bun Docs API fails (or with --offline), cached results are served
+    even when expired, with a note that they are stale, and refreshed in the
+    background once the API answers again."#
+)]
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "each bool is an independent command-line switch"
 )]
 struct Cli {
     /// Search query for Bun documentation (enables CLI mode)
@@ -96,6 +195,200 @@ struct Cli {
     /// Output format
     #[arg(short, long, value_enum, default_value_t = OutputFormat::Json)]
     format: OutputFormat,
+
+    /// Documentation pages fetched at once for `--format markdown`
+    #[arg(long, value_name = "N", env = "BUN_DOCS_MCP_FETCH_CONCURRENCY", default_value_t = DEFAULT_FETCH_CONCURRENCY, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    fetch_concurrency: usize,
+
+    /// Seconds allowed for fetching all pages for `--format markdown`; slower pages fall back to their snippet
+    #[arg(long, value_name = "SECS", env = "BUN_DOCS_MCP_FETCH_DEADLINE", default_value_t = DEFAULT_FETCH_DEADLINE_SECS, value_parser = clap::value_parser!(u64).range(1..))]
+    fetch_deadline: u64,
+
+    /// Upstream MCP endpoint, e.g. a local mock or an internal mirror
+    #[arg(long, value_name = "URL", env = "BUN_DOCS_MCP_URL", default_value = http::BUN_DOCS_API)]
+    upstream_url: String,
+
+    /// Timeout for each upstream HTTP request in seconds
+    #[arg(long, value_name = "SECS", env = "BUN_DOCS_MCP_TIMEOUT", default_value_t = http::REQUEST_TIMEOUT_SECS, value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+
+    /// Maximum attempts per upstream request, including the first
+    #[arg(long, value_name = "N", env = "BUN_DOCS_MCP_MAX_RETRIES", default_value_t = http::MAX_RETRIES, value_parser = clap::value_parser!(u32).range(1..))]
+    max_retries: u32,
+
+    /// Delay before the first retry in milliseconds; doubles for each further retry
+    #[arg(long, value_name = "MS", env = "BUN_DOCS_MCP_BACKOFF_BASE_MS", default_value_t = http::BACKOFF_BASE_MS)]
+    backoff_base_ms: u64,
+
+    /// Upper bound for the retry delay in milliseconds
+    #[arg(long, value_name = "MS", env = "BUN_DOCS_MCP_BACKOFF_MAX_MS", default_value_t = http::BACKOFF_MAX_MS)]
+    backoff_max_ms: u64,
+
+    /// Longest wait in seconds honored from an upstream `Retry-After` header
+    #[arg(long, value_name = "SECS", env = "BUN_DOCS_MCP_MAX_RETRY_AFTER", default_value_t = http::RETRY_AFTER_MAX_SECS)]
+    max_retry_after: u64,
+
+    /// Consecutive failed upstream calls after which calls fail fast (0 disables the circuit breaker)
+    #[arg(long, value_name = "N", env = "BUN_DOCS_MCP_CIRCUIT_THRESHOLD", default_value_t = http::DEFAULT_CIRCUIT_THRESHOLD)]
+    circuit_threshold: u32,
+
+    /// Seconds calls fail fast before the upstream is probed again
+    #[arg(long, value_name = "SECS", env = "BUN_DOCS_MCP_CIRCUIT_COOLDOWN", default_value_t = http::DEFAULT_CIRCUIT_COOLDOWN_SECS)]
+    circuit_cooldown: u64,
+
+    /// Largest accepted upstream response body or SSE stream in bytes
+    #[arg(long, value_name = "BYTES", env = "BUN_DOCS_MCP_MAX_RESPONSE_SIZE", default_value_t = http::DEFAULT_MAX_RESPONSE_SIZE)]
+    max_response_size: usize,
+
+    /// Largest accepted documentation page in bytes
+    #[arg(long, value_name = "BYTES", env = "BUN_DOCS_MCP_MAX_PAGE_SIZE", default_value_t = http::DEFAULT_MAX_PAGE_SIZE)]
+    max_page_size: usize,
+
+    /// Host documentation pages may be fetched from, with its subdomains (repeatable; `*` allows any)
+    #[arg(long = "allow-page-host", value_name = "HOST", env = "BUN_DOCS_MCP_ALLOW_PAGE_HOSTS", value_delimiter = ',', default_value = http::DEFAULT_PAGE_HOST)]
+    allow_page_hosts: Vec<String>,
+
+    /// Allow fetching documentation pages over plain HTTP
+    #[arg(long)]
+    allow_http_pages: bool,
+
+    /// Allow fetching documentation pages from loopback and private addresses
+    #[arg(long)]
+    allow_private_pages: bool,
+
+    /// Maximum number of cached upstream responses (0 disables the cache)
+    #[arg(long, value_name = "N", env = "BUN_DOCS_MCP_CACHE_ENTRIES", default_value_t = http::DEFAULT_CACHE_ENTRIES)]
+    cache_entries: usize,
+
+    /// Maximum total size of cached upstream responses in bytes
+    #[arg(long, value_name = "BYTES", env = "BUN_DOCS_MCP_CACHE_MAX_BYTES", default_value_t = http::DEFAULT_CACHE_MAX_BYTES)]
+    cache_max_bytes: usize,
+
+    /// Seconds a cached upstream response stays fresh
+    #[arg(long, value_name = "SECS", env = "BUN_DOCS_MCP_CACHE_TTL", default_value_t = http::DEFAULT_CACHE_TTL_SECS)]
+    cache_ttl: u64,
+
+    /// Directory of the persistent cache [default: $XDG_CACHE_HOME/bun-docs-mcp-proxy]
+    #[arg(long, value_name = "PATH", env = "BUN_DOCS_MCP_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Keep cached responses in memory only
+    #[arg(long)]
+    no_disk_cache: bool,
+
+    /// Never contact the API; answer from the cache only, marking expired results stale
+    #[arg(long)]
+    offline: bool,
+
+    /// Maximum total size of the persistent cache in bytes
+    #[arg(long, value_name = "BYTES", env = "BUN_DOCS_MCP_DISK_CACHE_MAX_BYTES", default_value_t = http::DEFAULT_DISK_CACHE_MAX_BYTES)]
+    disk_cache_max_bytes: u64,
+
+    /// Seconds a persisted response stays fresh
+    #[arg(long, value_name = "SECS", env = "BUN_DOCS_MCP_DISK_CACHE_TTL", default_value_t = http::DEFAULT_DISK_CACHE_TTL_SECS)]
+    disk_cache_ttl: u64,
+
+    /// Message framing on stdio (auto-detected from the first bytes by default)
+    #[arg(long, value_enum, default_value_t = transport::Framing::Auto)]
+    framing: transport::Framing,
+
+    /// Largest accepted stdio or socket message in bytes; larger ones get a -32600 error
+    #[arg(long, value_name = "BYTES", default_value_t = transport::DEFAULT_MAX_MESSAGE_SIZE)]
+    max_message_size: usize,
+
+    /// Seconds in-flight requests may take to finish after EOF, SIGINT or SIGTERM
+    #[arg(long, value_name = "SECS", default_value_t = shutdown::DEFAULT_GRACE_PERIOD_SECS)]
+    grace_period: u64,
+
+    /// Shut down when the parent process exits, even if stdin stays open (Unix only)
+    #[arg(long, conflicts_with_all = ["search", "connect"])]
+    watch_parent: bool,
+
+    /// Shut down after this many seconds without requests
+    #[arg(long, value_name = "SECS", value_parser = parse_idle_timeout, conflicts_with_all = ["search", "connect"])]
+    idle_timeout: Option<Duration>,
+
+    /// Serve MCP over Streamable HTTP instead of stdio: PORT (binds 127.0.0.1) or ADDR:PORT
+    #[arg(long, value_name = "ADDR", value_parser = parse_listen_addr, conflicts_with = "search")]
+    listen: Option<SocketAddr>,
+
+    /// Extra Origin allowed to call the HTTP server (repeatable; loopback is always allowed)
+    #[arg(long = "allow-origin", value_name = "ORIGIN", requires = "listen")]
+    allow_origins: Vec<String>,
+
+    /// Require `Authorization: Bearer <token>` with the token read from this file
+    #[arg(long, value_name = "PATH", requires = "listen")]
+    auth_token_file: Option<PathBuf>,
+
+    /// Serve MCP on a Unix socket, one stdio-style session per connection
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["search", "listen"])]
+    socket: Option<PathBuf>,
+
+    /// Relay stdin/stdout to a proxy already serving on this Unix socket
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["search", "listen", "socket"])]
+    connect: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Builds the page fetch limits for Markdown output from the command line and environment.
+    const fn markdown_options(&self) -> MarkdownOptions {
+        MarkdownOptions {
+            concurrency: self.fetch_concurrency,
+            deadline: Duration::from_secs(self.fetch_deadline),
+        }
+    }
+
+    /// Builds the upstream client settings from the command line and environment.
+    fn client_config(&self) -> http::ClientConfig {
+        let config = http::ClientConfig::default()
+            .base_url(&self.upstream_url)
+            .timeout(Duration::from_secs(self.timeout))
+            .retry(
+                http::RetryPolicy::default()
+                    .max_attempts(self.max_retries)
+                    .backoff(
+                        Duration::from_millis(self.backoff_base_ms),
+                        Duration::from_millis(self.backoff_max_ms),
+                    )
+                    .retry_after_max(Duration::from_secs(self.max_retry_after)),
+            )
+            .circuit(
+                http::CircuitConfig::default()
+                    .failure_threshold(self.circuit_threshold)
+                    .cooldown(Duration::from_secs(self.circuit_cooldown)),
+            )
+            .max_response_size(self.max_response_size)
+            .max_page_size(self.max_page_size)
+            .page_policy(
+                http::PagePolicy::default()
+                    .allowed_hosts(&self.allow_page_hosts)
+                    .allow_http(self.allow_http_pages)
+                    .allow_private(self.allow_private_pages),
+            )
+            .cache(
+                http::CacheConfig::default()
+                    .max_entries(self.cache_entries)
+                    .max_bytes(self.cache_max_bytes)
+                    .ttl(Duration::from_secs(self.cache_ttl)),
+            )
+            .offline(self.offline);
+        if self.no_disk_cache {
+            return config;
+        }
+        let Some(dir) = self
+            .cache_dir
+            .clone()
+            .or_else(http::disk_cache::default_dir)
+        else {
+            debug!("No cache directory found, keeping responses in memory only");
+            return config;
+        };
+        config.disk_cache(
+            http::DiskCacheConfig::new(dir)
+                .max_bytes(self.disk_cache_max_bytes)
+                .ttl(Duration::from_secs(self.disk_cache_ttl)),
+        )
+    }
 }
 
 /// Extracts a required string parameter from a `serde_json::Value` representing JSON-RPC parameters.
@@ -256,22 +549,45 @@ fn format_text(result: &serde_json::Value) -> Result<String> {
     }
 }
 
+/// Limits for fetching documentation pages when formatting Markdown output
+#[derive(Debug, Clone, Copy)]
+struct MarkdownOptions {
+    /// Maximum number of pages fetched at once.
+    concurrency: usize,
+    /// Time allowed for fetching all pages; unfetched entries fall back to their text.
+    deadline: Duration,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_FETCH_CONCURRENCY,
+            deadline: Duration::from_secs(DEFAULT_FETCH_DEADLINE_SECS),
+        }
+    }
+}
+
 /// Formats a search result as a Markdown string by fetching the raw MDX content from URLs.
 ///
-/// This function extracts `DocEntry` items from the search result. For each entry with a URL,
-/// it attempts to fetch the full MDX content. If successful, the content is included with a source
-/// comment. If the fetch fails or no URL is present, it falls back to the entry's text.
-/// The final output joins all parts with Markdown horizontal rules.
+/// This function extracts `DocEntry` items from the search result and fetches the full MDX
+/// content of each distinct URL, up to `options.concurrency` pages at once. A fetched page is
+/// included once, with a source comment, in place of the first entry linking to it. Entries
+/// whose fetch fails, that are not fetched before `options.deadline`, or that have no URL fall
+/// back to their text, after a comment saying why; URLs refused by the client's page policy
+/// are reported as blocked. The final output keeps the order of the search result and joins
+/// all parts with Markdown horizontal rules.
 ///
 /// # Arguments
 /// * `result` - A reference to the `serde_json::Value` representing the search result.
 /// * `client` - A reference to the `BunDocsClient` for fetching MDX content.
+/// * `options` - Concurrency limit and deadline for the page fetches.
 ///
 /// # Returns
 /// A `Result` containing the aggregated and formatted Markdown string.
 async fn format_markdown(
     result: &serde_json::Value,
     client: &http::BunDocsClient,
+    options: &MarkdownOptions,
 ) -> Result<String> {
     let doc_entries = extract_doc_entries(result);
 
@@ -284,39 +600,98 @@ async fn format_markdown(
         return Ok(output);
     }
 
+    let mut pages = fetch_pages(&doc_entries, client, options).await;
     let mut mdx_parts = Vec::new();
 
-    for entry in doc_entries {
-        if let Some(url) = entry.url {
-            // Try to fetch MDX from the URL
-            let fetch_result = client.fetch_doc_markdown(&url).await;
-            match fetch_result {
-                Ok(mdx) => {
-                    // Success: include URL comment and MDX content
-                    let mut part = String::new();
-                    write!(part, "<!-- Source: {url} -->\n\n")?;
-                    part.push_str(&mdx);
-                    mdx_parts.push(part);
-                }
-                Err(e) => {
-                    // Error: include error comment and fallback to original text
-                    warn!("Failed to fetch MDX from {url}: {e}");
-                    let mut part = String::new();
-                    write!(part, "<!-- Error: {e} -->\n\n")?;
-                    part.push_str(entry.text);
-                    mdx_parts.push(part);
-                }
-            }
-        } else {
+    for entry in &doc_entries {
+        let Some(url) = entry.url.as_deref() else {
             // No URL found, use original text content
             mdx_parts.push(entry.text.to_owned());
+            continue;
+        };
+        let mut part = String::new();
+        match pages.get_mut(url).map(Option::take) {
+            // Success: include URL comment and MDX content
+            Some(Some(Ok(mdx))) => {
+                write!(part, "<!-- Source: {url} -->\n\n")?;
+                part.push_str(&mdx);
+            }
+            // The page is already included for an earlier entry
+            Some(None) => continue,
+            // Refused by the page policy: report it and fall back to original text
+            Some(Some(Err(e))) if e.downcast_ref::<http::PageRejected>().is_some() => {
+                warn!("{e}");
+                write!(part, "<!-- Blocked: {e} -->\n\n")?;
+                part.push_str(entry.text);
+            }
+            // Error: include error comment and fallback to original text
+            Some(Some(Err(e))) => {
+                warn!("Failed to fetch MDX from {url}: {e}");
+                write!(part, "<!-- Error: {e} -->\n\n")?;
+                part.push_str(entry.text);
+            }
+            // Deadline passed before the page was fetched
+            None => {
+                write!(
+                    part,
+                    "<!-- Error: not fetched within {:?} -->\n\n",
+                    options.deadline
+                )?;
+                part.push_str(entry.text);
+            }
         }
+        mdx_parts.push(part);
     }
 
     // Join with horizontal rules and two newlines
     Ok(mdx_parts.join("\n\n---\n\n"))
 }
 
+/// Fetches the MDX content of each distinct URL among `entries`.
+///
+/// Pages are fetched up to `options.concurrency` at once. Fetches still running when
+/// `options.deadline` passes are cancelled, and their URLs are missing from the result.
+///
+/// # Returns
+/// The outcome of each finished fetch, by URL.
+async fn fetch_pages<'entries>(
+    entries: &'entries [DocEntry<'_>],
+    client: &http::BunDocsClient,
+    options: &MarkdownOptions,
+) -> HashMap<&'entries str, Option<Result<String>>> {
+    let mut urls: Vec<&str> = Vec::new();
+    for url in entries.iter().filter_map(|entry| entry.url.as_deref()) {
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+
+    let mut fetches = futures::stream::iter(urls.iter().copied())
+        .map(|url| async move { (url, client.fetch_doc_markdown(url).await) })
+        .buffer_unordered(options.concurrency.max(1_usize));
+    let deadline = tokio::time::Instant::now() + options.deadline;
+    let mut pages = HashMap::new();
+
+    loop {
+        match tokio::time::timeout_at(deadline, fetches.next()).await {
+            Ok(Some((url, page))) => {
+                pages.insert(url, Some(page));
+            }
+            Ok(None) => break,
+            Err(_) => {
+                warn!(
+                    "Fetching MDX took longer than {:?}; {} of {} page(s) fall back to snippets",
+                    options.deadline,
+                    urls.len() - pages.len(),
+                    urls.len()
+                );
+                break;
+            }
+        }
+    }
+    pages
+}
+
 /// Validates a file path to ensure it does not contain directory traversal components (e.g., `..`).
 ///
 /// This is a security measure to prevent writing files outside of the intended directory.
@@ -344,6 +719,38 @@ fn validate_output_path(path: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Parses the `--idle-timeout` value as a positive number of seconds.
+///
+/// # Errors
+/// Returns an error message if the value is not a positive integer
+fn parse_idle_timeout(value: &str) -> Result<Duration, String> {
+    match value.parse::<u64>() {
+        Ok(secs) if secs > 0_u64 => Ok(Duration::from_secs(secs)),
+        _ => Err(format!(
+            "invalid idle timeout '{value}' (expected a positive number of seconds)"
+        )),
+    }
+}
+
+/// Parses the `--listen` value as either a bare port or a full socket address.
+///
+/// A bare port binds to `127.0.0.1`, so the server is only reachable from the local
+/// machine unless an address is given explicitly.
+///
+/// # Arguments
+/// * `value` - The raw argument, e.g. `8080` or `0.0.0.0:8080`.
+///
+/// # Returns
+/// The `SocketAddr` to bind, or an error message if the value is neither form.
+fn parse_listen_addr(value: &str) -> Result<SocketAddr, String> {
+    if let Ok(port) = value.parse::<u16>() {
+        return Ok(SocketAddr::from((Ipv4Addr::LOCALHOST, port)));
+    }
+    value
+        .parse()
+        .map_err(|_| format!("invalid listen address '{value}' (expected PORT or ADDR:PORT)"))
+}
+
 /// Executes a search query in CLI mode, formats the result, and writes it to the specified output.
 ///
 /// This function orchestrates the CLI search functionality. It builds and sends a `tools/call`
@@ -351,15 +758,19 @@ fn validate_output_path(path: &str) -> Result<(), String> {
 /// (JSON, text, or Markdown), and writes the output to a file or `stdout`.
 ///
 /// # Arguments
+/// * `client` - The `BunDocsClient` used for the search and MDX fetches.
 /// * `query` - The search query string.
 /// * `format` - The desired `OutputFormat` for the results.
+/// * `markdown` - Page fetch limits for Markdown output.
 /// * `output_path` - An optional file path to write the output to. If `None`, output is written to `stdout`.
 ///
 /// # Returns
 /// An `anyhow::Result<()>` indicating success or failure.
 async fn direct_search(
+    client: &http::BunDocsClient,
     query: &str,
     format: &OutputFormat,
+    markdown: &MarkdownOptions,
     output_path: Option<&str>,
 ) -> Result<()> {
     // Validate query is not empty
@@ -367,8 +778,6 @@ async fn direct_search(
         return Err(anyhow::anyhow!("Search query cannot be empty"));
     }
 
-    let client = http::BunDocsClient::new();
-
     // Validate output path if provided
     if let Some(path) = output_path {
         validate_output_path(path).map_err(|e| anyhow::anyhow!("Invalid output path: {e}"))?;
@@ -406,7 +815,7 @@ async fn direct_search(
     let formatted = match format {
         OutputFormat::Json => format_json(search_result)?,
         OutputFormat::Text => format_text(search_result)?,
-        OutputFormat::Markdown => format_markdown(search_result, &client).await?,
+        OutputFormat::Markdown => format_markdown(search_result, client, markdown).await?,
     };
 
     // Write output
@@ -422,84 +831,215 @@ async fn direct_search(
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     // Parse CLI arguments
     let cli = Cli::parse();
 
     // Initialize logging early for both CLI and server modes
     init_logging();
 
+    // Stdio-to-socket shim mode
+    if let Some(path) = &cli.connect {
+        socket::connect(path).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let client = http::BunDocsClient::with_config(cli.client_config())?;
+    debug!("Upstream endpoint: {}", client.base_url());
+
     // CLI search mode
     if let Some(query) = &cli.search {
-        return direct_search(query, &cli.format, cli.output.as_deref()).await;
+        direct_search(
+            &client,
+            query,
+            &cli.format,
+            &cli.markdown_options(),
+            cli.output.as_deref(),
+        )
+        .await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // All server modes stop gracefully on signals
+    let (trigger, shutdown) = shutdown::channel(Duration::from_secs(cli.grace_period));
+    shutdown::listen_for_signals(trigger.clone())?;
+    if cli.watch_parent {
+        watchdog::spawn_parent_watchdog(trigger.clone(), shutdown.clone());
+    }
+    if let Some(timeout) = cli.idle_timeout {
+        watchdog::spawn_idle_watchdog(timeout, trigger.clone(), shutdown.clone());
+    }
+
+    // Streamable HTTP server mode
+    if let Some(addr) = cli.listen {
+        let token = server::auth::load_token(cli.auth_token_file.as_deref())?;
+        let policy = server::auth::AccessPolicy::new(&cli.allow_origins, token)?;
+        let drained = server::serve(addr, client, policy, shutdown.clone()).await?;
+        return Ok(ExitCode::from(shutdown.exit_code(drained)));
+    }
+
+    // Unix socket daemon mode
+    if let Some(path) = &cli.socket {
+        let drained = socket::serve(
+            path,
+            client,
+            cli.framing,
+            cli.max_message_size,
+            shutdown.clone(),
+        )
+        .await?;
+        return Ok(ExitCode::from(shutdown.exit_code(drained)));
     }
 
     // MCP server mode
     info!("Bun Docs MCP Proxy starting");
 
-    let mut transport = transport::StdioTransport::new();
-    let http_client = http::BunDocsClient::new();
+    let drained = run_server(
+        &client,
+        transport::StdioTransport::with_framing(cli.framing)
+            .with_max_message_size(cli.max_message_size),
+        shutdown.clone(),
+    )
+    .await;
+    trigger.trigger(shutdown::ShutdownReason::InputClosed);
+    end_upstream_session(&client, &shutdown).await;
+
+    info!("Bun Docs MCP Proxy shutting down");
+    let code = shutdown.exit_code(drained);
+    if shutdown.reason() != Some(shutdown::ShutdownReason::InputClosed) {
+        // A blocking stdin read cannot be cancelled and would stall runtime shutdown
+        std::process::exit(i32::from(code));
+    }
+    Ok(ExitCode::from(code))
+}
+
+/// Ends the upstream session within the remaining grace period, logging failures,
+/// and logs the response cache statistics and circuit breaker state.
+///
+/// # Arguments
+/// * `client` - The client whose upstream session should end
+/// * `shutdown` - Shutdown handle bounding how long ending the session may take
+async fn end_upstream_session(client: &http::BunDocsClient, shutdown: &shutdown::Shutdown) {
+    match shutdown.drain(client.end_session()).await {
+        Some(Ok(())) => {}
+        Some(Err(e)) => warn!("{:#}", e),
+        None => warn!("Timed out ending upstream session"),
+    }
+    let stats = client.cache_stats();
+    info!(
+        "Response cache: {} hits, {} misses, {} entries ({} bytes)",
+        stats.hits, stats.misses, stats.entries, stats.bytes
+    );
+    let circuit = client.circuit_status();
+    info!(
+        "Upstream circuit: {} ({} consecutive failures)",
+        circuit.state, circuit.consecutive_failures
+    );
+}
+
+/// Serves JSON-RPC requests from a transport until the peer disconnects.
+///
+/// Each message is read, dispatched via [`handle_message`] and answered on the same
+/// transport; progress and log notifications the API streams for a `tools/call`
+/// are written before its response. Notifications the API pushes on its message
+/// stream are written while a request is handled or before the next message is
+/// read, since a pending read cannot be interrupted without losing input. Malformed
+/// or oversized messages are answered with a JSON-RPC error and skipped. Other read
+/// errors are logged and retried, but after [`MAX_CONSECUTIVE_READ_ERRORS`] failures
+/// in a row the input is considered broken and the loop ends. A failed write ends the
+/// loop since the peer can no longer receive responses.
+///
+/// Once `shutdown` fires no further messages are read; a request already being
+/// handled may finish and be answered within the grace period, otherwise it is
+/// cancelled.
+///
+/// # Arguments
+/// * `client` - A reference to the `BunDocsClient` used by forwarding handlers.
+/// * `transport` - The transport to serve, e.g. stdio or an in-memory duplex pipe.
+/// * `shutdown` - Shutdown handle that stops the loop.
+///
+/// # Returns
+/// `false` if in-flight work was cancelled because the grace period ran out
+async fn run_server(
+    client: &http::BunDocsClient,
+    mut transport: impl Transport,
+    mut shutdown: shutdown::Shutdown,
+) -> bool {
+    let mut consecutive_errors = 0_u32;
+    let mut drained = true;
+    let mut upstream = client.subscribe();
 
     loop {
-        // Read JSON-RPC request from stdin
-        let read_result = transport.read_message().await;
-        let message = match read_result {
-            Ok(Some(msg)) => msg,
+        write_upstream_notifications(&mut upstream, &mut transport).await;
+
+        // Read next JSON-RPC message, unless shutdown has started
+        let read_result = tokio::select! {
+            biased;
+            reason = shutdown.triggered() => {
+                info!("Stopped reading input ({:?})", reason);
+                break;
+            }
+            result = transport.read_message() => result,
+        };
+        let response = match read_result {
+            Ok(Some(msg)) => {
+                consecutive_errors = 0_u32;
+                let busy = shutdown.activity().begin();
+                let handled = shutdown
+                    .drain(handle_relaying(client, &msg, &mut transport, &mut upstream))
+                    .await;
+                drop(busy);
+                let Some(handled) = handled else {
+                    warn!("Cancelled in-flight request: grace period expired");
+                    drained = false;
+                    break;
+                };
+                // Notifications produce no response
+                let Some(response) = handled else {
+                    continue;
+                };
+                response
+            }
             Ok(None) => {
                 info!("Connection closed");
                 break;
             }
             Err(e) => {
-                error!("Failed to read message: {}", e);
-                continue;
-            }
-        };
-
-        // Parse JSON-RPC request
-        let request: JsonRpcRequest = match serde_json::from_str(&message) {
-            Ok(req) => req,
-            Err(e) => {
-                error!("Failed to parse JSON-RPC request: {}", e);
-                let error_response = JsonRpcResponse::error(
-                    serde_json::Value::Null,
-                    JSONRPC_PARSE_ERROR,
-                    format!("Parse error: {e}"),
-                );
-                if let Ok(response_str) = serde_json::to_string(&error_response) {
-                    let write_result = transport.write_message(&response_str).await;
-                    let _ = write_result;
+                if let Some(frame_error) = e.downcast_ref::<transport::FrameError>() {
+                    consecutive_errors = 0_u32;
+                    warn!("Rejected message: {}", frame_error);
+                    frame_error_response(frame_error)
+                } else {
+                    consecutive_errors += 1_u32;
+                    error!("Failed to read message: {:#}", e);
+                    if consecutive_errors >= MAX_CONSECUTIVE_READ_ERRORS {
+                        error!(
+                            "Closing connection after {} consecutive read errors",
+                            consecutive_errors
+                        );
+                        break;
+                    }
+                    continue;
                 }
-                continue;
-            }
-        };
-
-        info!("Received method: {}", request.method);
-
-        // Handle request based on method
-        let response = match request.method.as_str() {
-            "tools/call" => handle_tools_call(&http_client, &request).await,
-            "tools/list" => handle_tools_list(&request),
-            "resources/list" => handle_resources_list(&request),
-            "resources/read" => handle_resources_read(&http_client, &request).await,
-            "initialize" => handle_initialize(&request),
-            method => {
-                error!("Unsupported method: {}", method);
-                JsonRpcResponse::error(
-                    request.id,
-                    JSONRPC_METHOD_NOT_FOUND,
-                    format!("Method not found: {method}"),
-                )
             }
         };
 
-        // Send response back to stdout
+        // Send response back to the client
         let serialize_result = serde_json::to_string(&response);
         match serialize_result {
             Ok(response_str) => {
-                let write_result = transport.write_message(&response_str).await;
-                if let Err(e) = write_result {
-                    error!("Failed to write response: {}", e);
-                    break;
+                let write_result = shutdown.drain(transport.write_message(&response_str)).await;
+                match write_result {
+                    Some(Ok(())) => {}
+                    Some(Err(e)) => {
+                        error!("Failed to write response: {}", e);
+                        break;
+                    }
+                    None => {
+                        warn!("Cancelled writing response: grace period expired");
+                        drained = false;
+                        break;
+                    }
                 }
             }
             Err(e) => {
@@ -508,8 +1048,164 @@ async fn main() -> Result<()> {
         }
     }
 
-    info!("Bun Docs MCP Proxy shutting down");
-    Ok(())
+    match shutdown.drain(transport.close()).await {
+        Some(Ok(())) => {}
+        Some(Err(e)) => debug!("Failed to close transport: {}", e),
+        None => debug!("Timed out closing transport"),
+    }
+    drained
+}
+
+/// Channel carrying upstream notifications to relay to a client connection
+type Notifications = tokio::sync::mpsc::UnboundedSender<serde_json::Value>;
+
+/// Handles one message, writing the upstream notifications relayed for it, and
+/// those the API pushes meanwhile, to `transport` while the request is in flight.
+///
+/// # Arguments
+/// * `client` - A reference to the `BunDocsClient` used by forwarding handlers.
+/// * `message` - The raw JSON text of a single JSON-RPC message.
+/// * `transport` - The transport the message was read from.
+/// * `upstream` - Notifications pushed on the API's message stream.
+///
+/// # Returns
+/// The response from [`handle_message`]
+async fn handle_relaying(
+    client: &http::BunDocsClient,
+    message: &str,
+    transport: &mut impl Transport,
+    upstream: &mut broadcast::Receiver<serde_json::Value>,
+) -> Option<JsonRpcResponse> {
+    let (sender, mut notifications) = tokio::sync::mpsc::unbounded_channel();
+    let mut handling = core::pin::pin!(handle_message(client, message, Some(&sender)));
+    let handled = loop {
+        tokio::select! {
+            biased;
+            Some(notification) = notifications.recv() => {
+                write_notification(transport, &notification).await;
+            }
+            Ok(notification) = upstream.recv() => {
+                write_notification(transport, &notification).await;
+            }
+            handled = &mut handling => break handled,
+        }
+    };
+    // Notifications queued just before the response still go first
+    while let Ok(notification) = notifications.try_recv() {
+        write_notification(transport, &notification).await;
+    }
+    handled
+}
+
+/// Writes the notifications the API pushed since the last call.
+async fn write_upstream_notifications(
+    upstream: &mut broadcast::Receiver<serde_json::Value>,
+    transport: &mut impl Transport,
+) {
+    loop {
+        match upstream.try_recv() {
+            Ok(notification) => write_notification(transport, &notification).await,
+            Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                warn!("Missed {} upstream notifications", skipped);
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+/// Writes a relayed notification; failures are left to the response write to report.
+async fn write_notification(transport: &mut impl Transport, notification: &serde_json::Value) {
+    if let Err(e) = transport.write_message(&notification.to_string()).await {
+        debug!("Failed to write notification: {}", e);
+    }
+}
+
+/// Builds the error response for a message the transport rejected.
+///
+/// The message was never parsed, so the response carries a null id. Invalid UTF-8
+/// is reported as a parse error; oversized and malformed frames as invalid requests.
+fn frame_error_response(error: &transport::FrameError) -> JsonRpcResponse {
+    let code = match error {
+        transport::FrameError::InvalidUtf8 => JSONRPC_PARSE_ERROR,
+        transport::FrameError::TooLarge { .. } | transport::FrameError::MalformedHeader(_) => {
+            JSONRPC_INVALID_REQUEST
+        }
+    };
+    JsonRpcResponse::error(serde_json::Value::Null, code, error.to_string())
+}
+
+/// Parses a raw JSON-RPC message and dispatches it to the matching method handler.
+///
+/// This is the single entry point shared by every server transport (stdio and
+/// Streamable HTTP), so all clients see identical method semantics. Messages that
+/// fail to parse are answered with a parse error carrying a `null` id.
+///
+/// # Arguments
+/// * `client` - A reference to the `BunDocsClient` used by forwarding handlers.
+/// * `message` - The raw JSON text of a single JSON-RPC message.
+/// * `notifications` - Receives upstream notifications to relay to the client, if
+///   the transport can deliver them while a request is in flight.
+///
+/// # Returns
+/// `Some(response)` to send back to the client, or `None` for notifications,
+/// which must not be answered.
+async fn handle_message(
+    client: &http::BunDocsClient,
+    message: &str,
+    notifications: Option<&Notifications>,
+) -> Option<JsonRpcResponse> {
+    // Parse JSON-RPC request
+    let request: JsonRpcRequest = match serde_json::from_str(message) {
+        Ok(req) => req,
+        Err(e) => {
+            error!("Failed to parse JSON-RPC request: {}", e);
+            return Some(JsonRpcResponse::error(
+                serde_json::Value::Null,
+                JSONRPC_PARSE_ERROR,
+                format!("Parse error: {e}"),
+            ));
+        }
+    };
+
+    info!("Received method: {}", request.method);
+
+    if request.method.starts_with("notifications/") {
+        debug!("Ignoring notification: {}", request.method);
+        return None;
+    }
+
+    Some(dispatch_request(client, &request, notifications).await)
+}
+
+/// Routes a parsed JSON-RPC request to its method handler.
+///
+/// # Arguments
+/// * `client` - A reference to the `BunDocsClient` used by forwarding handlers.
+/// * `request` - A reference to the incoming `JsonRpcRequest`.
+/// * `notifications` - Receives upstream notifications to relay to the client.
+///
+/// # Returns
+/// The `JsonRpcResponse` produced by the handler, or a method-not-found error.
+async fn dispatch_request(
+    client: &http::BunDocsClient,
+    request: &JsonRpcRequest,
+    notifications: Option<&Notifications>,
+) -> JsonRpcResponse {
+    match request.method.as_str() {
+        "tools/call" => handle_tools_call(client, request, notifications).await,
+        "tools/list" => handle_tools_list(client, request),
+        "resources/list" => handle_resources_list(request),
+        "resources/read" => handle_resources_read(client, request).await,
+        "initialize" => handle_initialize(request),
+        method => {
+            error!("Unsupported method: {}", method);
+            JsonRpcResponse::error(
+                request.id.clone(),
+                JSONRPC_METHOD_NOT_FOUND,
+                format!("Method not found: {method}"),
+            )
+        }
+    }
 }
 
 /// Handles a `tools/call` JSON-RPC request by forwarding it to the Bun Docs API.
@@ -517,16 +1213,21 @@ async fn main() -> Result<()> {
 /// This function takes an incoming `tools/call` request, constructs a new request
 /// with the same parameters, and sends it to the Bun Docs API via the `BunDocsClient`.
 /// It then processes the response, extracting the `result` field on success.
+/// Progress and log notifications the API streams meanwhile are relayed to
+/// `notifications`, with progress tokens rewritten to the client's
+/// `_meta.progressToken`.
 ///
 /// # Arguments
 /// * `client` - A reference to the `BunDocsClient` for making the API call.
 /// * `request` - A reference to the incoming `JsonRpcRequest`.
+/// * `notifications` - Receives upstream notifications to relay to the client.
 ///
 /// # Returns
 /// A `JsonRpcResponse` to be sent back to the client.
 async fn handle_tools_call(
     client: &http::BunDocsClient,
     request: &JsonRpcRequest,
+    notifications: Option<&Notifications>,
 ) -> JsonRpcResponse {
     // Forward entire request to Bun Docs API
     let original_request = serde_json::json!({
@@ -536,7 +1237,15 @@ async fn handle_tools_call(
         "params": request.params
     });
 
-    match client.forward_request(original_request).await {
+    let forwarded = if let Some(sender) = notifications {
+        let relay = http::NotificationRelay::new(sender.clone(), request.params.as_ref());
+        client
+            .forward_request_with_relay(original_request, &relay)
+            .await
+    } else {
+        client.forward_request(original_request).await
+    };
+    match forwarded {
         Ok(result) => {
             info!("Successfully got response from Bun Docs");
 
@@ -563,16 +1272,22 @@ async fn handle_tools_call(
     }
 }
 
-/// Handles a `tools/list` JSON-RPC request by returning a static list of available tools.
+/// Handles a `tools/list` JSON-RPC request by returning the list of available tools.
 ///
-/// Currently, this returns a single tool: `SearchBun`.
+/// Once the API has announced a tool list change, this returns the upstream list
+/// fetched in response; until then a static list with a single tool, `SearchBun`.
 ///
 /// # Arguments
+/// * `client` - A reference to the `BunDocsClient` holding the upstream tool list.
 /// * `request` - A reference to the incoming `JsonRpcRequest`.
 ///
 /// # Returns
 /// A `JsonRpcResponse` containing the list of tools.
-fn handle_tools_list(request: &JsonRpcRequest) -> JsonRpcResponse {
+fn handle_tools_list(client: &http::BunDocsClient, request: &JsonRpcRequest) -> JsonRpcResponse {
+    if let Some(tools) = client.tool_list() {
+        return JsonRpcResponse::success(request.id.clone(), tools);
+    }
+
     // Return available tools
     let tools = serde_json::json!({
         "tools": [{
@@ -725,7 +1440,7 @@ fn handle_initialize(request: &JsonRpcRequest) -> JsonRpcResponse {
     let init_result = serde_json::json!({
         "protocolVersion": "2024-11-05",
         "capabilities": {
-            "tools": {},
+            "tools": { "listChanged": true },
             "resources": {}
         },
         "serverInfo": {
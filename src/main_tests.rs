@@ -36,7 +36,7 @@ fn test_handle_tools_list() {
         params: None,
     };
 
-    let response = handle_tools_list(&request);
+    let response = handle_tools_list(&http::BunDocsClient::new(), &request);
     let serialized = serde_json::to_value(&response).unwrap();
 
     assert_eq!(serialized["id"], "test-id");
@@ -108,7 +108,7 @@ fn test_handle_tools_list_structure() {
         params: None,
     };
 
-    let response = handle_tools_list(&request);
+    let response = handle_tools_list(&http::BunDocsClient::new(), &request);
     let serialized = serde_json::to_value(&response).unwrap();
 
     // Verify required fields
@@ -211,7 +211,7 @@ async fn test_handle_tools_call_mocked() {
         })),
     };
 
-    let response = handle_tools_call(&client, &request).await;
+    let response = handle_tools_call(&client, &request, None).await;
     let serialized = serde_json::to_value(&response).unwrap();
 
     mock.assert_async().await;
@@ -283,7 +283,7 @@ async fn test_handle_tools_call_real_api() {
         })),
     };
 
-    let response = handle_tools_call(&client, &request).await;
+    let response = handle_tools_call(&client, &request, None).await;
     let serialized = serde_json::to_value(&response).unwrap();
 
     assert!(serialized["result"].is_object());
@@ -309,7 +309,7 @@ async fn test_handle_tools_call_empty_query() {
         })),
     };
 
-    let response = handle_tools_call(&client, &request).await;
+    let response = handle_tools_call(&client, &request, None).await;
     let serialized = serde_json::to_value(&response).unwrap();
 
     // Proxy should forward successfully; Bun API decides what empty query means
@@ -514,7 +514,9 @@ async fn test_format_markdown_no_url() {
     // Test content without URL - should just return the text
     let result = serde_json::json!({"content": [{"text": "test content", "type": "text"}]});
     let client = http::BunDocsClient::new();
-    let formatted = format_markdown(&result, &client).await.unwrap();
+    let formatted = format_markdown(&result, &client, &MarkdownOptions::default())
+        .await
+        .unwrap();
     assert!(formatted.contains("test content"));
     assert!(!formatted.contains("<!--")); // No URL comment
 }
@@ -524,7 +526,9 @@ async fn test_format_markdown_no_content() {
     // Test fallback to JSON when no content array
     let result = serde_json::json!({"other": "data"});
     let client = http::BunDocsClient::new();
-    let formatted = format_markdown(&result, &client).await.unwrap();
+    let formatted = format_markdown(&result, &client, &MarkdownOptions::default())
+        .await
+        .unwrap();
     assert!(formatted.contains("```json"));
     assert!(formatted.contains("\"other\""));
 }
@@ -537,7 +541,9 @@ async fn test_format_markdown_multiple_items_no_url() {
         {"text": "Second Section", "type": "text"}
     ]});
     let client = http::BunDocsClient::new();
-    let formatted = format_markdown(&result, &client).await.unwrap();
+    let formatted = format_markdown(&result, &client, &MarkdownOptions::default())
+        .await
+        .unwrap();
     assert!(formatted.contains("First Section"));
     assert!(formatted.contains("Second Section"));
     assert!(formatted.contains("\n\n---\n\n")); // Horizontal rule separator
@@ -548,7 +554,9 @@ async fn test_format_markdown_empty_content() {
     // Test empty content array falls back to JSON
     let result = serde_json::json!({"content": []});
     let client = http::BunDocsClient::new();
-    let formatted = format_markdown(&result, &client).await.unwrap();
+    let formatted = format_markdown(&result, &client, &MarkdownOptions::default())
+        .await
+        .unwrap();
     assert!(formatted.contains("```json"));
     assert!(formatted.contains("\"content\": []"));
 }
@@ -680,7 +688,9 @@ fn test_format_text_with_null_content() {
 async fn test_format_markdown_with_null_content() {
     let result = serde_json::json!({"content": null});
     let client = http::BunDocsClient::new();
-    let formatted = format_markdown(&result, &client).await.unwrap();
+    let formatted = format_markdown(&result, &client, &MarkdownOptions::default())
+        .await
+        .unwrap();
     assert!(formatted.contains("```json"));
     assert!(formatted.contains("null"));
 }
@@ -717,21 +727,42 @@ fn test_jsonrpc_error_code_constants() {
 #[tokio::test]
 #[cfg(feature = "integration-tests")]
 async fn test_direct_search_json_format() {
-    let result = direct_search("Bun.serve", &OutputFormat::Json, None).await;
+    let result = direct_search(
+        &http::BunDocsClient::new(),
+        "Bun.serve",
+        &OutputFormat::Json,
+        &MarkdownOptions::default(),
+        None,
+    )
+    .await;
     result.unwrap();
 }
 
 #[tokio::test]
 #[cfg(feature = "integration-tests")]
 async fn test_direct_search_text_format() {
-    let result = direct_search("HTTP", &OutputFormat::Text, None).await;
+    let result = direct_search(
+        &http::BunDocsClient::new(),
+        "HTTP",
+        &OutputFormat::Text,
+        &MarkdownOptions::default(),
+        None,
+    )
+    .await;
     result.unwrap();
 }
 
 #[tokio::test]
 #[cfg(feature = "integration-tests")]
 async fn test_direct_search_markdown_format() {
-    let result = direct_search("server", &OutputFormat::Markdown, None).await;
+    let result = direct_search(
+        &http::BunDocsClient::new(),
+        "server",
+        &OutputFormat::Markdown,
+        &MarkdownOptions::default(),
+        None,
+    )
+    .await;
     result.unwrap();
 }
 
@@ -745,7 +776,14 @@ async fn test_direct_search_with_output_file() {
         .unwrap();
     let output_path = temp_file.path().file_name().unwrap().to_str().unwrap();
 
-    let result = direct_search("test", &OutputFormat::Json, Some(output_path)).await;
+    let result = direct_search(
+        &http::BunDocsClient::new(),
+        "test",
+        &OutputFormat::Json,
+        &MarkdownOptions::default(),
+        Some(output_path),
+    )
+    .await;
     result.unwrap();
 
     // Verify file was created
@@ -760,14 +798,28 @@ async fn test_direct_search_with_output_file() {
 
 #[tokio::test]
 async fn test_direct_search_empty_query() {
-    let result = direct_search("", &OutputFormat::Json, None).await;
+    let result = direct_search(
+        &http::BunDocsClient::new(),
+        "",
+        &OutputFormat::Json,
+        &MarkdownOptions::default(),
+        None,
+    )
+    .await;
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("cannot be empty"));
 }
 
 #[tokio::test]
 async fn test_direct_search_whitespace_only_query() {
-    let result = direct_search("   ", &OutputFormat::Json, None).await;
+    let result = direct_search(
+        &http::BunDocsClient::new(),
+        "   ",
+        &OutputFormat::Json,
+        &MarkdownOptions::default(),
+        None,
+    )
+    .await;
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("cannot be empty"));
 }
@@ -782,7 +834,14 @@ async fn test_direct_search_markdown_with_file() {
         .unwrap();
     let output_path = temp_file.path().file_name().unwrap().to_str().unwrap();
 
-    let result = direct_search("Bun", &OutputFormat::Markdown, Some(output_path)).await;
+    let result = direct_search(
+        &http::BunDocsClient::new(),
+        "Bun",
+        &OutputFormat::Markdown,
+        &MarkdownOptions::default(),
+        Some(output_path),
+    )
+    .await;
     result.unwrap();
 
     // Verify file was created
@@ -820,7 +879,14 @@ fn test_validate_output_path_absolute_paths() {
 
 #[tokio::test]
 async fn test_direct_search_invalid_output_path() {
-    let result = direct_search("test", &OutputFormat::Json, Some("../output.json")).await;
+    let result = direct_search(
+        &http::BunDocsClient::new(),
+        "test",
+        &OutputFormat::Json,
+        &MarkdownOptions::default(),
+        Some("../output.json"),
+    )
+    .await;
     assert!(result.is_err());
     assert!(
         result
@@ -845,7 +911,14 @@ async fn test_direct_search_file_overwrite() {
     assert!(std::path::Path::new(output_path).exists());
 
     // Should overwrite
-    let result = direct_search("test", &OutputFormat::Json, Some(output_path)).await;
+    let result = direct_search(
+        &http::BunDocsClient::new(),
+        "test",
+        &OutputFormat::Json,
+        &MarkdownOptions::default(),
+        Some(output_path),
+    )
+    .await;
     result.unwrap();
 
     // Verify new content
@@ -860,12 +933,12 @@ async fn test_format_markdown_fetch_mdx_error_with_fallback() {
     // Test that when MDX fetch fails, we get an error comment + fallback text
     let mut server = mockito::Server::new_async().await;
 
-    // Mock the MDX fetch to fail with 500
+    // Mock the MDX fetch to fail with 500 on every retry
     let mock_error = server
         .mock("GET", mockito::Matcher::Any)
         .with_status(500_usize)
         .with_body("Internal Server Error")
-        .expect(1_usize)
+        .expect(3_usize)
         .create_async()
         .await;
 
@@ -875,7 +948,7 @@ async fn test_format_markdown_fetch_mdx_error_with_fallback() {
     }]});
 
     let client = http::BunDocsClient::with_base_url(&server.url()).expect("valid URL");
-    let formatted = format_markdown(&result, &client)
+    let formatted = format_markdown(&result, &client, &MarkdownOptions::default())
         .await
         .expect("format should succeed");
 
@@ -921,7 +994,7 @@ async fn test_handle_tools_call_with_network_error() {
         })),
     };
 
-    let response = handle_tools_call(&client, &request).await;
+    let response = handle_tools_call(&client, &request, None).await;
     let serialized = serde_json::to_value(&response).unwrap();
 
     drop(server);
@@ -969,7 +1042,7 @@ async fn test_format_markdown_with_url_and_fetch_success() {
     }]});
 
     let client = http::BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
-    let formatted = format_markdown(&result, &client)
+    let formatted = format_markdown(&result, &client, &MarkdownOptions::default())
         .await
         .expect("format should succeed");
 
@@ -991,3 +1064,529 @@ async fn test_format_markdown_with_url_and_fetch_success() {
     );
     // Verifies src/main.rs lines 292-298: successful fetch with source comment
 }
+
+#[tokio::test]
+async fn test_format_markdown_keeps_order_and_fetches_duplicates_once() {
+    let mut server = mockito::Server::new_async().await;
+    let first = server
+        .mock("GET", "/docs/first")
+        .with_status(200_usize)
+        .with_body("# First page")
+        .expect(1_usize)
+        .create_async()
+        .await;
+    let second = server
+        .mock("GET", "/docs/second")
+        .with_status(200_usize)
+        .with_body("# Second page")
+        .expect(1_usize)
+        .create_async()
+        .await;
+
+    let url = server.url();
+    let result = json!({"content": [
+        {"type": "text", "text": format!("First\nLink: {url}/docs/first")},
+        {"type": "text", "text": "No link here"},
+        {"type": "text", "text": format!("Second\nLink: {url}/docs/second")},
+        {"type": "text", "text": format!("First again\nLink: {url}/docs/first")},
+    ]});
+
+    let client = http::BunDocsClient::with_base_url(&url).expect("valid mock server URL");
+    let options = MarkdownOptions {
+        concurrency: 2_usize,
+        ..MarkdownOptions::default()
+    };
+    let formatted = format_markdown(&result, &client, &options)
+        .await
+        .expect("format should succeed");
+
+    first.assert_async().await;
+    second.assert_async().await;
+    drop(server);
+
+    let parts: Vec<&str> = formatted.split("\n\n---\n\n").collect();
+    assert_eq!(parts.len(), 3_usize, "duplicate URL is included once");
+    assert!(parts[0].ends_with("# First page"));
+    assert_eq!(parts[1], "No link here");
+    assert!(parts[2].ends_with("# Second page"));
+}
+
+#[tokio::test]
+async fn test_format_markdown_falls_back_to_text_after_deadline() {
+    let mut server = mockito::Server::new_async().await;
+    let fast = server
+        .mock("GET", "/docs/fast")
+        .with_status(200_usize)
+        .with_body("# Fast page")
+        .create_async()
+        .await;
+    let slow = start_slow_upstream(std::time::Duration::from_secs(30), "# Slow page").await;
+
+    let url = server.url();
+    let result = json!({"content": [
+        {"type": "text", "text": format!("Slow snippet\nLink: {slow}/docs/slow")},
+        {"type": "text", "text": format!("Fast snippet\nLink: {url}/docs/fast")},
+    ]});
+
+    // The slow page is on another local origin, which the default policy refuses
+    let config = http::ClientConfig::default().base_url(&url).page_policy(
+        http::PagePolicy::default()
+            .allowed_hosts(["127.0.0.1"])
+            .allow_http(true)
+            .allow_private(true),
+    );
+    let client = http::BunDocsClient::with_config(config).expect("valid mock server URL");
+    let options = MarkdownOptions {
+        concurrency: 2_usize,
+        deadline: std::time::Duration::from_millis(500),
+    };
+    let started = std::time::Instant::now();
+    let formatted = format_markdown(&result, &client, &options)
+        .await
+        .expect("format should succeed");
+
+    fast.assert_async().await;
+    drop(server);
+
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    let parts: Vec<&str> = formatted.split("\n\n---\n\n").collect();
+    assert_eq!(
+        parts[0],
+        format!("<!-- Error: not fetched within 500ms -->\n\nSlow snippet\nLink: {slow}/docs/slow")
+    );
+    assert!(parts[1].ends_with("# Fast page"));
+}
+
+#[tokio::test]
+async fn test_format_markdown_reports_blocked_links() {
+    let server = mockito::Server::new_async().await;
+    let result = json!({"content": [
+        {"type": "text", "text": "Metadata\nLink: http://169.254.169.254/latest/meta-data"},
+        {"type": "text", "text": "Elsewhere\nLink: https://example.com/docs"},
+    ]});
+
+    let client = http::BunDocsClient::with_base_url(&server.url()).expect("valid mock server URL");
+    let formatted = format_markdown(&result, &client, &MarkdownOptions::default())
+        .await
+        .expect("format should succeed");
+    drop(server);
+
+    let parts: Vec<&str> = formatted.split("\n\n---\n\n").collect();
+    assert!(
+        parts[0].starts_with("<!-- Blocked: refused to fetch http://169.254.169.254/"),
+        "{formatted}"
+    );
+    assert!(parts[0].ends_with("Metadata\nLink: http://169.254.169.254/latest/meta-data"));
+    assert!(parts[1].contains("not in the allowlist"), "{formatted}");
+    assert!(parts[1].ends_with("Elsewhere\nLink: https://example.com/docs"));
+}
+
+#[tokio::test]
+async fn test_handle_message_dispatches_request() {
+    let client = http::BunDocsClient::new();
+    let response = handle_message(
+        &client,
+        r#"{"jsonrpc":"2.0","id":7,"method":"tools/list"}"#,
+        None,
+    )
+    .await
+    .expect("requests produce a response");
+    let serialized = serde_json::to_value(&response).unwrap();
+
+    assert_eq!(serialized["id"], 7);
+    assert_eq!(serialized["result"]["tools"][0]["name"], "SearchBun");
+}
+
+#[tokio::test]
+async fn test_handle_message_parse_error() {
+    let client = http::BunDocsClient::new();
+    let response = handle_message(&client, "not json", None)
+        .await
+        .expect("parse errors produce a response");
+    let serialized = serde_json::to_value(&response).unwrap();
+
+    assert!(serialized["id"].is_null());
+    assert_eq!(serialized["error"]["code"], JSONRPC_PARSE_ERROR);
+}
+
+#[tokio::test]
+async fn test_handle_message_notification_has_no_response() {
+    let client = http::BunDocsClient::new();
+    let response = handle_message(
+        &client,
+        r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        None,
+    )
+    .await;
+
+    assert!(response.is_none());
+}
+
+#[tokio::test]
+async fn test_handle_message_unknown_method() {
+    let client = http::BunDocsClient::new();
+    let response = handle_message(
+        &client,
+        r#"{"jsonrpc":"2.0","id":1,"method":"bogus"}"#,
+        None,
+    )
+    .await
+    .expect("unknown methods produce an error response");
+    let serialized = serde_json::to_value(&response).unwrap();
+
+    assert_eq!(serialized["error"]["code"], JSONRPC_METHOD_NOT_FOUND);
+}
+
+#[test]
+fn test_parse_listen_addr() {
+    assert_eq!(
+        parse_listen_addr("8080").unwrap(),
+        "127.0.0.1:8080".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(
+        parse_listen_addr("0.0.0.0:9000").unwrap(),
+        "0.0.0.0:9000".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(
+        parse_listen_addr("[::1]:8080").unwrap(),
+        "[::1]:8080".parse::<SocketAddr>().unwrap()
+    );
+    parse_listen_addr("localhost").unwrap_err();
+    parse_listen_addr("70000").unwrap_err();
+}
+
+#[tokio::test]
+async fn test_run_server_over_duplex() {
+    let (server_end, client_end) = tokio::io::duplex(1024);
+    let client = http::BunDocsClient::new();
+    let server = tokio::spawn(async move {
+        run_server(
+            &client,
+            transport::DuplexTransport::duplex(server_end, transport::Framing::Auto),
+            shutdown::Shutdown::default(),
+        )
+        .await;
+    });
+
+    let mut peer = transport::DuplexTransport::duplex(client_end, transport::Framing::Ndjson);
+    peer.write_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+        .await
+        .unwrap();
+    peer.write_message(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#)
+        .await
+        .unwrap();
+
+    let reply = peer.read_message().await.unwrap().unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&reply).unwrap();
+    assert_eq!(parsed["id"], 1);
+    assert_eq!(parsed["result"]["serverInfo"]["name"], "bun-docs-mcp-proxy");
+
+    // Closing the client side ends the server loop
+    peer.close().await.unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_run_server_relays_upstream_notifications() {
+    let mut upstream = mockito::Server::new_async().await;
+    let mock = upstream
+        .mock("POST", "/")
+        .with_status(200_usize)
+        .with_header("content-type", "text/event-stream")
+        .with_body(concat!(
+            "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\",",
+            "\"params\":{\"progressToken\":\"upstream\",\"progress\":1,\"total\":2}}\n\n",
+            "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/message\",",
+            "\"params\":{\"level\":\"info\",\"data\":\"searching\"}}\n\n",
+            "data: {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"content\":[]}}\n\n",
+        ))
+        .create_async()
+        .await;
+    let client = http::BunDocsClient::with_base_url(&upstream.url()).unwrap();
+    let (server_end, client_end) = tokio::io::duplex(4096);
+    let server = tokio::spawn(async move {
+        run_server(
+            &client,
+            transport::DuplexTransport::duplex(server_end, transport::Framing::Ndjson),
+            shutdown::Shutdown::default(),
+        )
+        .await;
+    });
+
+    let mut peer = transport::DuplexTransport::duplex(client_end, transport::Framing::Ndjson);
+    peer.write_message(
+        r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"SearchBun","arguments":{"query":"serve"},"_meta":{"progressToken":"client-7"}}}"#,
+    )
+    .await
+    .unwrap();
+
+    let mut replies = Vec::new();
+    for _ in 0_i32..3_i32 {
+        let reply = peer.read_message().await.unwrap().unwrap();
+        replies.push(serde_json::from_str::<serde_json::Value>(&reply).unwrap());
+    }
+    assert_eq!(replies[0]["method"], "notifications/progress");
+    assert_eq!(replies[0]["params"]["progressToken"], "client-7");
+    assert_eq!(replies[0]["params"]["progress"], 1);
+    assert_eq!(replies[1]["method"], "notifications/message");
+    assert_eq!(replies[1]["params"]["data"], "searching");
+    assert_eq!(replies[2]["id"], 1);
+    assert!(replies[2]["result"].is_object());
+
+    peer.close().await.unwrap();
+    server.await.unwrap();
+    mock.assert_async().await;
+    drop(upstream);
+}
+
+#[tokio::test]
+async fn test_run_server_relays_upstream_tool_list_change() {
+    let mut upstream = mockito::Server::new_async().await;
+    let call = upstream
+        .mock("POST", "/")
+        .match_body(mockito::Matcher::PartialJson(
+            json!({"method": "tools/call"}),
+        ))
+        .with_status(200_usize)
+        .with_header("content-type", "application/json")
+        .with_header("mcp-session-id", "s1")
+        .with_body(r#"{"jsonrpc":"2.0","id":1,"result":{"content":[]}}"#)
+        .create_async()
+        .await;
+    let stream = upstream
+        .mock("GET", "/")
+        .with_status(200_usize)
+        .with_header("content-type", "text/event-stream")
+        .with_body(
+            "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/tools/list_changed\"}\n\n",
+        )
+        .create_async()
+        .await;
+    let list = upstream
+        .mock("POST", "/")
+        .match_body(mockito::Matcher::PartialJson(
+            json!({"method": "tools/list"}),
+        ))
+        .with_status(200_usize)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"jsonrpc":"2.0","id":"x","result":{"tools":[{"name":"NewTool"}]}}"#)
+        .create_async()
+        .await;
+    let client = http::BunDocsClient::with_base_url(&upstream.url()).unwrap();
+    let mut events = client.subscribe();
+    let (server_end, client_end) = tokio::io::duplex(4096);
+    let server = tokio::spawn(async move {
+        run_server(
+            &client,
+            transport::DuplexTransport::duplex(server_end, transport::Framing::Ndjson),
+            shutdown::Shutdown::default(),
+        )
+        .await;
+    });
+
+    let mut peer = transport::DuplexTransport::duplex(client_end, transport::Framing::Ndjson);
+    peer.write_message(
+        r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"SearchBun","arguments":{"query":"serve"}}}"#,
+    )
+    .await
+    .unwrap();
+    let mut replies = Vec::new();
+    let first = peer.read_message().await.unwrap().unwrap();
+    replies.push(serde_json::from_str::<serde_json::Value>(&first).unwrap());
+    tokio::time::timeout(std::time::Duration::from_secs(5), events.recv())
+        .await
+        .expect("upstream announces the change")
+        .unwrap();
+
+    peer.write_message(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#)
+        .await
+        .unwrap();
+    while replies.len() < 3_usize {
+        let reply = peer.read_message().await.unwrap().unwrap();
+        replies.push(serde_json::from_str::<serde_json::Value>(&reply).unwrap());
+    }
+    // The change may be written before or after the tools/call response
+    let notification = replies
+        .iter()
+        .find(|reply| reply.get("method").is_some())
+        .expect("change is relayed");
+    assert_eq!(notification["method"], "notifications/tools/list_changed");
+    let tools = replies.iter().find(|reply| reply["id"] == 2).unwrap();
+    assert_eq!(tools["result"]["tools"][0]["name"], "NewTool");
+
+    peer.close().await.unwrap();
+    server.await.unwrap();
+    call.assert_async().await;
+    stream.assert_async().await;
+    list.assert_async().await;
+    drop(upstream);
+}
+
+#[tokio::test]
+async fn test_run_server_rejects_oversized_message_and_continues() {
+    let (server_end, client_end) = tokio::io::duplex(1024);
+    let client = http::BunDocsClient::new();
+    let server = tokio::spawn(async move {
+        let transport = transport::DuplexTransport::duplex(server_end, transport::Framing::Ndjson)
+            .with_max_message_size(64);
+        run_server(&client, transport, shutdown::Shutdown::default()).await;
+    });
+
+    let mut peer = transport::DuplexTransport::duplex(client_end, transport::Framing::Ndjson);
+    peer.write_message(&format!(r#"{{"padding":"{}"}}"#, "x".repeat(200)))
+        .await
+        .unwrap();
+    peer.write_message(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#)
+        .await
+        .unwrap();
+
+    let rejected: serde_json::Value =
+        serde_json::from_str(&peer.read_message().await.unwrap().unwrap()).unwrap();
+    assert_eq!(rejected["id"], serde_json::Value::Null);
+    assert_eq!(rejected["error"]["code"], JSONRPC_INVALID_REQUEST);
+
+    let next_reply: serde_json::Value =
+        serde_json::from_str(&peer.read_message().await.unwrap().unwrap()).unwrap();
+    assert_eq!(next_reply["id"], 2);
+
+    peer.close().await.unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_run_server_invalid_utf8_is_parse_error() {
+    let input: &[u8] = b"{\"id\":\"\xff\"}\n";
+    let mut output = Vec::new();
+    run_server(
+        &http::BunDocsClient::new(),
+        transport::StreamTransport::from_parts(input, &mut output, transport::Framing::Ndjson),
+        shutdown::Shutdown::default(),
+    )
+    .await;
+
+    let reply: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(reply["error"]["code"], JSONRPC_PARSE_ERROR);
+}
+
+/// Reader whose every read fails, like a broken stdin.
+struct BrokenReader;
+
+impl tokio::io::AsyncRead for BrokenReader {
+    fn poll_read(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+        _buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        core::task::Poll::Ready(Err(std::io::Error::other("broken pipe")))
+    }
+}
+
+#[tokio::test]
+async fn test_run_server_stops_after_repeated_read_errors() {
+    let transport = transport::StreamTransport::from_parts(
+        tokio::io::BufReader::new(BrokenReader),
+        Vec::new(),
+        transport::Framing::Ndjson,
+    );
+    tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        run_server(
+            &http::BunDocsClient::new(),
+            transport,
+            shutdown::Shutdown::default(),
+        ),
+    )
+    .await
+    .expect("server loop ends on a broken input");
+}
+
+/// Starts an upstream that answers every request with `body` after `delay`.
+async fn start_slow_upstream(delay: std::time::Duration, body: &'static str) -> String {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut request = [0_u8; 4096];
+                let _ = stream.read(&mut request).await;
+                tokio::time::sleep(delay).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn test_run_server_drains_in_flight_request_on_shutdown() {
+    let upstream = start_slow_upstream(
+        std::time::Duration::from_millis(200),
+        r#"{"jsonrpc":"2.0","id":1,"result":{"content":[]}}"#,
+    )
+    .await;
+    let client = http::BunDocsClient::with_base_url(&upstream).unwrap();
+    let (trigger, shutdown) = shutdown::channel(std::time::Duration::from_secs(5));
+    let (server_end, client_end) = tokio::io::duplex(4096);
+    let server = tokio::spawn(async move {
+        run_server(
+            &client,
+            transport::DuplexTransport::duplex(server_end, transport::Framing::Ndjson),
+            shutdown,
+        )
+        .await
+    });
+
+    let mut peer = transport::DuplexTransport::duplex(client_end, transport::Framing::Ndjson);
+    peer.write_message(
+        r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"SearchBun","arguments":{"query":"serve"}}}"#,
+    )
+    .await
+    .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    trigger.trigger(shutdown::ShutdownReason::Terminate);
+
+    let reply: serde_json::Value =
+        serde_json::from_str(&peer.read_message().await.unwrap().unwrap()).unwrap();
+    assert_eq!(reply["id"], 1);
+    assert!(server.await.unwrap(), "in-flight request drained");
+    // No further input is read once shutdown has started
+    assert_eq!(peer.read_message().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_run_server_cancels_in_flight_request_after_grace() {
+    let upstream = start_slow_upstream(std::time::Duration::from_secs(30), "{}").await;
+    let client = http::BunDocsClient::with_base_url(&upstream).unwrap();
+    let (trigger, shutdown) = shutdown::channel(std::time::Duration::from_millis(50));
+    let (server_end, client_end) = tokio::io::duplex(4096);
+    let server = tokio::spawn(async move {
+        run_server(
+            &client,
+            transport::DuplexTransport::duplex(server_end, transport::Framing::Ndjson),
+            shutdown,
+        )
+        .await
+    });
+
+    let mut peer = transport::DuplexTransport::duplex(client_end, transport::Framing::Ndjson);
+    peer.write_message(
+        r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"SearchBun","arguments":{"query":"serve"}}}"#,
+    )
+    .await
+    .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    trigger.trigger(shutdown::ShutdownReason::Interrupt);
+
+    let drained = tokio::time::timeout(std::time::Duration::from_secs(5), server)
+        .await
+        .expect("server loop ends after the grace period")
+        .unwrap();
+    assert!(!drained);
+    assert_eq!(peer.read_message().await.unwrap(), None);
+}
@@ -43,7 +43,8 @@ pub struct JsonRpcRequest {
     /// Protocol version (must be "2.0")
     #[allow(dead_code, reason = "field required for protocol compliance")]
     pub jsonrpc: String,
-    /// Request identifier (can be string, number, or null)
+    /// Request identifier (can be string, number, or null; absent for notifications)
+    #[serde(default)]
     pub id: Value,
     /// Method name to invoke
     pub method: String,
@@ -217,6 +218,16 @@ mod tests {
         assert!(request.params.is_none());
     }
 
+    #[test]
+    fn deserialize_notification_without_id() {
+        let json_str = r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#;
+
+        let request: JsonRpcRequest =
+            serde_json::from_str(json_str).expect("notification should parse");
+        assert_eq!(request.method, "notifications/initialized");
+        assert!(request.id.is_null());
+    }
+
     #[test]
     fn serialize_success_response() {
         let response = JsonRpcResponse::success(json!(1_i32), json!({"status": "ok"}));
@@ -0,0 +1,977 @@
+//! Streamable HTTP server transport for sharing one proxy between several clients.
+//!
+//! In `--listen` mode the proxy serves the MCP Streamable HTTP transport on a single
+//! endpoint ([`MCP_ENDPOINT`]) instead of reading from stdin, so every editor window
+//! can talk to one long-running process.
+//!
+//! ## Endpoint Behavior
+//!
+//! - `POST` carries one JSON-RPC message. Requests are answered with an SSE stream
+//!   containing the response (or plain JSON if the client does not accept
+//!   `text/event-stream`); notifications are acknowledged with `202 Accepted`.
+//! - `GET` opens a long-lived SSE stream for server-initiated notifications, such as
+//!   those the Bun Docs API pushes on its own message stream.
+//! - `DELETE` terminates the session.
+//!
+//! The same listener also serves the legacy HTTP+SSE transport for older clients
+//! (`GET /sse` plus `POST /messages`, see [`legacy_sse`]).
+//!
+//! ## Sessions
+//!
+//! An `initialize` request creates a session whose ID is returned in the
+//! `Mcp-Session-Id` response header. Every later request must echo it back; unknown
+//! or terminated sessions get `404 Not Found`, which tells clients to re-initialize.
+//!
+//! Most clients never send `DELETE`, so a session also ends after
+//! [`SESSION_IDLE_TIMEOUT`] without requests (an open GET stream keeps it alive), and
+//! once [`MAX_SESSIONS`] sessions exist the least recently used one is dropped to make
+//! room for a new one.
+//!
+//! All sessions share one [`BunDocsClient`] and go through the same dispatcher as
+//! stdio mode.
+//!
+//! ## Shutdown
+//!
+//! On shutdown the listener stops accepting connections, open event streams end,
+//! and in-flight requests get the grace period to finish before their connections
+//! are dropped.
+//!
+//! ## Security
+//!
+//! Every request is first checked against an [`AccessPolicy`] (Origin allowlist and
+//! optional bearer token, see [`auth`]). Binding a non-loopback address without a
+//! token logs a warning.
+
+pub mod auth;
+mod legacy_sse;
+
+use crate::http::BunDocsClient;
+use crate::shutdown::Shutdown;
+use anyhow::{Context as _, Result};
+use auth::{AccessPolicy, Rejection};
+use bytes::Bytes;
+use core::convert::Infallible;
+use core::fmt::Write as _;
+use http_body_util::{BodyExt as _, Empty, Full, Limited, StreamBody, combinators::BoxBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+use tracing::{debug, error, info, warn};
+
+/// Path of the single MCP endpoint
+const MCP_ENDPOINT: &str = "/mcp";
+
+/// Header carrying the session identifier assigned at initialization
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Maximum accepted size of a POST body (1 MB)
+const MAX_REQUEST_BODY_SIZE: usize = 1_000_000_usize;
+
+/// Interval between keep-alive comments on idle GET streams (seconds)
+const SSE_KEEP_ALIVE_SECS: u64 = 15_u64;
+
+/// Time without requests after which a session expires
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_mins(30_u64);
+
+/// Maximum number of live sessions; creating one more drops the least recently used
+const MAX_SESSIONS: usize = 1024_usize;
+
+/// Capacity of the server notification channel
+const NOTIFICATION_CHANNEL_CAPACITY: usize = 64_usize;
+
+/// Boxed body type shared by all responses
+type ResponseBody = BoxBody<Bytes, Infallible>;
+
+/// State shared by every connection of the HTTP server
+struct ServerState {
+    /// Upstream client shared by all sessions.
+    client: BunDocsClient,
+    /// Origin allowlist and token applied to every request.
+    policy: AccessPolicy,
+    /// Sessions created by `initialize` and not yet terminated, with their last use.
+    sessions: Mutex<HashMap<String, Instant>>,
+    /// Time without requests after which a session expires.
+    session_idle_timeout: Duration,
+    /// Server-initiated notifications fanned out to every open GET stream.
+    notifications: broadcast::Sender<String>,
+    /// Open streams of legacy HTTP+SSE clients.
+    legacy: legacy_sse::LegacySessions,
+    /// Shutdown handle that ends open event streams.
+    shutdown: Shutdown,
+}
+
+impl ServerState {
+    /// Creates empty server state around a shared upstream client.
+    fn new(client: BunDocsClient, policy: AccessPolicy, shutdown: Shutdown) -> Self {
+        let (notifications, _) = broadcast::channel(NOTIFICATION_CHANNEL_CAPACITY);
+        Self {
+            client,
+            policy,
+            sessions: Mutex::new(HashMap::new()),
+            session_idle_timeout: SESSION_IDLE_TIMEOUT,
+            notifications,
+            legacy: legacy_sse::LegacySessions::default(),
+            shutdown,
+        }
+    }
+
+    /// Creates and registers a new session, returning its ID.
+    ///
+    /// Expired sessions are swept first, and at [`MAX_SESSIONS`] the least recently
+    /// used session is dropped.
+    fn create_session(&self) -> Result<String> {
+        let id = new_session_id()?;
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        let before = sessions.len();
+        sessions.retain(|_, last_used| last_used.elapsed() < self.session_idle_timeout);
+        let expired = before - sessions.len();
+        let evicted = if sessions.len() >= MAX_SESSIONS {
+            sessions
+                .iter()
+                .min_by_key(|&(_, last_used)| *last_used)
+                .map(|(oldest, _)| oldest.clone())
+                .and_then(|oldest| sessions.remove_entry(&oldest))
+                .map(|(oldest, _)| oldest)
+        } else {
+            None
+        };
+        sessions.insert(id.clone(), Instant::now());
+        drop(sessions);
+
+        if expired > 0_usize {
+            debug!("Expired {} idle session(s)", expired);
+        }
+        if let Some(oldest) = evicted {
+            info!(
+                "Dropped session {}: more than {} sessions",
+                oldest, MAX_SESSIONS
+            );
+        }
+        info!("Created session {}", id);
+        Ok(id)
+    }
+
+    /// Returns `true` if the session exists, has not been terminated and has not been
+    /// idle for longer than the timeout, marking it as used.
+    fn touch_session(&self, id: &str) -> bool {
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(last_used) = sessions.get_mut(id) else {
+            return false;
+        };
+        if last_used.elapsed() >= self.session_idle_timeout {
+            sessions.remove(id);
+            drop(sessions);
+            info!("Session {} expired after being idle", id);
+            return false;
+        }
+        *last_used = Instant::now();
+        true
+    }
+
+    /// Removes a session, returning `true` if it existed.
+    fn remove_session(&self, id: &str) -> bool {
+        self.sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(id)
+            .is_some()
+    }
+
+    /// Routes an HTTP request to the handler for its method.
+    async fn handle(self: Arc<Self>, request: Request<Incoming>) -> Response<ResponseBody> {
+        if let Err(rejection) = self.policy.check(request.headers()) {
+            warn!(
+                "Rejected {} {}: {}",
+                request.method(),
+                request.uri().path(),
+                rejection.message()
+            );
+            return rejection_response(rejection);
+        }
+
+        match (request.uri().path(), request.method()) {
+            (MCP_ENDPOINT, &Method::POST) => self.handle_post(request).await,
+            (MCP_ENDPOINT, &Method::GET) => self.handle_get(&request),
+            (MCP_ENDPOINT, &Method::DELETE) => self.handle_delete(&request),
+            (MCP_ENDPOINT, _) => method_not_allowed("GET, POST, DELETE"),
+            (legacy_sse::SSE_ENDPOINT, &Method::GET) => legacy_sse::open_stream(self),
+            (legacy_sse::SSE_ENDPOINT, _) => method_not_allowed("GET"),
+            (legacy_sse::MESSAGES_ENDPOINT, &Method::POST) => {
+                legacy_sse::post_message(self, request).await
+            }
+            (legacy_sse::MESSAGES_ENDPOINT, _) => method_not_allowed("POST"),
+            _ => plain_response(StatusCode::NOT_FOUND, "Not found"),
+        }
+    }
+
+    /// Handles a `POST` carrying a single JSON-RPC message.
+    async fn handle_post(&self, request: Request<Incoming>) -> Response<ResponseBody> {
+        let wants_sse = accepts(&request, "text/event-stream");
+        let session_id = header_str(&request, SESSION_HEADER).map(str::to_owned);
+
+        let message = match read_body(request).await {
+            Ok(message) => message,
+            Err((status, text)) => return plain_response(status, text),
+        };
+        let message = message.as_str();
+
+        // Peek at the method to decide whether this message opens a new session
+        let is_initialize = serde_json::from_str::<serde_json::Value>(message)
+            .ok()
+            .and_then(|value| value.get("method")?.as_str().map(|m| m == "initialize"))
+            .unwrap_or(false);
+
+        let session_id = if is_initialize {
+            match self.create_session() {
+                Ok(id) => id,
+                Err(e) => {
+                    error!("Failed to create session: {}", e);
+                    return plain_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to create session",
+                    );
+                }
+            }
+        } else {
+            match self.require_session(session_id.as_deref()) {
+                Ok(id) => id.to_owned(),
+                Err((status, text)) => return plain_response(status, text),
+            }
+        };
+
+        let busy = self.shutdown.activity().begin();
+        let handled = crate::handle_message(&self.client, message, None).await;
+        drop(busy);
+        let Some(response) = handled else {
+            return with_session(plain_response(StatusCode::ACCEPTED, ""), &session_id);
+        };
+
+        let payload = match serde_json::to_string(&response) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Failed to serialize response: {}", e);
+                return plain_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to serialize response",
+                );
+            }
+        };
+
+        let http_response = if wants_sse {
+            sse_response(Full::new(sse_event(&payload)).boxed())
+        } else {
+            let mut json = Response::new(Full::new(Bytes::from(payload)).boxed());
+            json.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+            json
+        };
+        with_session(http_response, &session_id)
+    }
+
+    /// Handles a `GET` opening a stream for server-initiated notifications.
+    ///
+    /// The stream emits keep-alive comments while idle and ends once the session is
+    /// terminated or the server shuts down.
+    fn handle_get(self: Arc<Self>, request: &Request<Incoming>) -> Response<ResponseBody> {
+        if !accepts(request, "text/event-stream") {
+            return plain_response(
+                StatusCode::NOT_ACCEPTABLE,
+                "GET requires Accept: text/event-stream",
+            );
+        }
+        let session_id = match self.require_session(header_str(request, SESSION_HEADER)) {
+            Ok(id) => id.to_owned(),
+            Err((status, text)) => return plain_response(status, text),
+        };
+
+        debug!("Opening notification stream for session {}", session_id);
+        let receiver = self.notifications.subscribe();
+        let mut keep_alive = tokio::time::interval(Duration::from_secs(SSE_KEEP_ALIVE_SECS));
+        keep_alive.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        let shutdown = self.shutdown.clone();
+        let stream = futures::stream::unfold(
+            (self, session_id, receiver, keep_alive, shutdown),
+            |(state, id, mut receiver, mut keep_alive, mut shutdown)| async move {
+                loop {
+                    let chunk = tokio::select! {
+                        _ = shutdown.triggered() => return None,
+                        message = receiver.recv() => match message {
+                            Ok(message) => sse_event(&message),
+                            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                warn!("Session {} missed {} notifications", id, skipped);
+                                continue;
+                            }
+                            Err(broadcast::error::RecvError::Closed) => return None,
+                        },
+                        _ = keep_alive.tick() => Bytes::from_static(b": keep-alive\n\n"),
+                    };
+                    if !state.touch_session(&id) {
+                        debug!("Closing notification stream for session {}", id);
+                        return None;
+                    }
+                    let frame = Ok::<_, Infallible>(Frame::data(chunk));
+                    return Some((frame, (state, id, receiver, keep_alive, shutdown)));
+                }
+            },
+        );
+
+        with_session(
+            sse_response(StreamBody::new(stream).boxed()),
+            header_str(request, SESSION_HEADER).unwrap_or_default(),
+        )
+    }
+
+    /// Handles a `DELETE` terminating the caller's session.
+    fn handle_delete(&self, request: &Request<Incoming>) -> Response<ResponseBody> {
+        let Some(id) = header_str(request, SESSION_HEADER) else {
+            return plain_response(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
+        };
+        if self.remove_session(id) {
+            info!("Terminated session {}", id);
+            plain_response(StatusCode::NO_CONTENT, "")
+        } else {
+            plain_response(StatusCode::NOT_FOUND, "Unknown session")
+        }
+    }
+
+    /// Validates the session header of a non-initialization request.
+    ///
+    /// # Errors
+    /// Returns the status and message to reply with: `400` when the header is
+    /// missing, or `404` when the session is unknown, terminated or expired.
+    fn require_session<'id>(
+        &self,
+        session_id: Option<&'id str>,
+    ) -> Result<&'id str, (StatusCode, &'static str)> {
+        let Some(id) = session_id else {
+            return Err((StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header"));
+        };
+        if self.touch_session(id) {
+            Ok(id)
+        } else {
+            Err((StatusCode::NOT_FOUND, "Unknown session"))
+        }
+    }
+}
+
+/// Generates a cryptographically random session ID as 32 lowercase hex characters.
+///
+/// # Errors
+/// Returns an error if the operating system random source is unavailable.
+fn new_session_id() -> Result<String> {
+    let mut bytes = [0_u8; 16_usize];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to generate session ID: {e}"))?;
+    let mut id = String::with_capacity(32_usize);
+    for byte in bytes {
+        write!(id, "{byte:02x}")?;
+    }
+    Ok(id)
+}
+
+/// Reads a request body of at most [`MAX_REQUEST_BODY_SIZE`] bytes as UTF-8 text.
+///
+/// # Errors
+/// Returns the status and message to reply with: `413` when the body is too large,
+/// or `400` when it is not valid UTF-8.
+async fn read_body(request: Request<Incoming>) -> Result<String, (StatusCode, &'static str)> {
+    let body = match Limited::new(request.into_body(), MAX_REQUEST_BODY_SIZE)
+        .collect()
+        .await
+    {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => {
+            warn!("Rejected request body: {}", e);
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, "Request body too large"));
+        }
+    };
+    String::from_utf8(body.to_vec())
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Request body is not valid UTF-8"))
+}
+
+/// Returns a header value as a string slice, if present and valid ASCII.
+fn header_str<'req>(request: &'req Request<Incoming>, name: &str) -> Option<&'req str> {
+    request.headers().get(name)?.to_str().ok()
+}
+
+/// Returns `true` if the request's `Accept` header lists the given media type.
+fn accepts(request: &Request<Incoming>, media_type: &str) -> bool {
+    header_str(request, header::ACCEPT.as_str()).is_some_and(|accept| {
+        accept
+            .split(',')
+            .any(|item| item.split(';').next().unwrap_or("").trim() == media_type)
+    })
+}
+
+/// Formats a payload as a single SSE `message` event.
+fn sse_event(data: &str) -> Bytes {
+    Bytes::from(format!("event: message\ndata: {data}\n\n"))
+}
+
+/// Builds a plain-text response with the given status.
+fn plain_response(status: StatusCode, text: &'static str) -> Response<ResponseBody> {
+    let body = if text.is_empty() {
+        Empty::new().boxed()
+    } else {
+        Full::new(Bytes::from_static(text.as_bytes())).boxed()
+    };
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    response
+}
+
+/// Builds a `405` response advertising the allowed methods.
+fn method_not_allowed(allow: &'static str) -> Response<ResponseBody> {
+    let mut response = plain_response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed");
+    response
+        .headers_mut()
+        .insert(header::ALLOW, HeaderValue::from_static(allow));
+    response
+}
+
+/// Builds the `401`/`403` response for a failed access check.
+fn rejection_response(rejection: Rejection) -> Response<ResponseBody> {
+    let mut response = plain_response(rejection.status(), rejection.message());
+    if rejection == Rejection::Unauthorized {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    response
+}
+
+/// Wraps a body in a `text/event-stream` response.
+fn sse_response(body: ResponseBody) -> Response<ResponseBody> {
+    let mut response = Response::new(body);
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/event-stream"),
+    );
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+/// Attaches the `Mcp-Session-Id` header to a response.
+fn with_session(mut response: Response<ResponseBody>, session_id: &str) -> Response<ResponseBody> {
+    if let Ok(value) = HeaderValue::from_str(session_id) {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+/// Accepts connections and serves each on its own task until shutdown, then waits
+/// for in-flight requests to finish.
+///
+/// # Returns
+/// `false` if any connection was dropped with a request still in flight
+async fn serve_listener(listener: TcpListener, state: Arc<ServerState>) -> bool {
+    let mut shutdown = state.shutdown.clone();
+    let mut connections = JoinSet::new();
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            biased;
+            _ = shutdown.triggered() => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            },
+        };
+        debug!("Accepted connection from {}", peer);
+
+        // Reap connections that have already closed
+        while connections.try_join_next().is_some() {}
+
+        let state = Arc::clone(&state);
+        let mut shutdown = shutdown.clone();
+        connections.spawn(async move {
+            let service = service_fn(move |request| {
+                let state = Arc::clone(&state);
+                async move { Ok::<_, Infallible>(state.handle(request).await) }
+            });
+            let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+            tokio::pin!(connection);
+
+            let result = tokio::select! {
+                result = connection.as_mut() => result,
+                _ = shutdown.triggered() => {
+                    // Finish the in-flight request, then close instead of keeping alive
+                    connection.as_mut().graceful_shutdown();
+                    let Some(result) = shutdown.drain(connection).await else {
+                        warn!("Dropped connection from {}: grace period expired", peer);
+                        return false;
+                    };
+                    result
+                }
+            };
+            if let Err(e) = result {
+                debug!("Connection from {} ended with error: {}", peer, e);
+            }
+            true
+        });
+    }
+
+    info!(
+        "Waiting for {} open connection(s) to finish",
+        connections.len()
+    );
+    let mut drained = true;
+    while let Some(result) = connections.join_next().await {
+        drained &= result.unwrap_or(false);
+    }
+    drained
+}
+
+/// Runs the Streamable HTTP server on `addr`.
+///
+/// # Arguments
+/// * `addr` - Socket address to bind (e.g. `127.0.0.1:8080`)
+/// * `client` - Upstream client shared by all sessions
+/// * `policy` - Origin allowlist and optional bearer token
+/// * `shutdown` - Shutdown handle that stops the server
+///
+/// # Returns
+/// `false` if in-flight requests were cancelled during shutdown
+///
+/// # Errors
+/// Returns an error if the address cannot be bound
+pub async fn serve(
+    addr: SocketAddr,
+    client: BunDocsClient,
+    policy: AccessPolicy,
+    shutdown: Shutdown,
+) -> Result<bool> {
+    if !addr.ip().is_loopback() && !policy.requires_token() {
+        warn!(
+            "Listening on non-loopback address {} without a bearer token; set {} or --auth-token-file",
+            addr,
+            auth::AUTH_TOKEN_ENV
+        );
+    }
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind {addr}"))?;
+    info!(
+        "Bun Docs MCP Proxy listening on http://{}{}",
+        listener.local_addr().unwrap_or(addr),
+        MCP_ENDPOINT
+    );
+
+    let state = Arc::new(ServerState::new(client, policy, shutdown.clone()));
+    let forwarder = tokio::spawn(forward_upstream_notifications(
+        state.client.subscribe(),
+        state.notifications.clone(),
+    ));
+    let drained = serve_listener(listener, Arc::clone(&state)).await;
+    forwarder.abort();
+    crate::end_upstream_session(&state.client, &shutdown).await;
+    Ok(drained)
+}
+
+/// Publishes the notifications the API pushes to every open GET and legacy stream.
+async fn forward_upstream_notifications(
+    mut upstream: broadcast::Receiver<serde_json::Value>,
+    notifications: broadcast::Sender<String>,
+) {
+    loop {
+        match upstream.recv().await {
+            Ok(notification) => {
+                // Without open streams there is nobody to tell
+                let _ = notifications.send(notification.to_string());
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Missed {} upstream notifications", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+#[allow(clippy::indexing_slicing, reason = "tests use array indexing")]
+#[allow(clippy::default_numeric_fallback, reason = "test literals")]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    /// Starts a server on an ephemeral port, returning its endpoint URL.
+    async fn start_server(upstream: &str) -> String {
+        start_server_with_policy(upstream, AccessPolicy::default()).await
+    }
+
+    /// Starts a server with a custom access policy, returning its endpoint URL.
+    async fn start_server_with_policy(upstream: &str, policy: AccessPolicy) -> String {
+        let client = BunDocsClient::with_base_url(upstream).expect("valid mock server URL");
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+        tokio::spawn(serve_listener(
+            listener,
+            Arc::new(ServerState::new(client, policy, Shutdown::default())),
+        ));
+        format!("http://{addr}{MCP_ENDPOINT}")
+    }
+
+    /// Starts a server whose sessions expire after `timeout`, returning its endpoint URL.
+    async fn start_server_with_idle_timeout(upstream: &str, timeout: Duration) -> String {
+        let client = BunDocsClient::with_base_url(upstream).expect("valid mock server URL");
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+        let mut state = ServerState::new(client, AccessPolicy::default(), Shutdown::default());
+        state.session_idle_timeout = timeout;
+        tokio::spawn(serve_listener(listener, Arc::new(state)));
+        format!("http://{addr}{MCP_ENDPOINT}")
+    }
+
+    /// Sends `initialize` and returns the assigned session ID.
+    async fn initialize(http: &reqwest::Client, url: &str) -> String {
+        let response = http
+            .post(url)
+            .header("accept", "application/json, text/event-stream")
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"}))
+            .send()
+            .await
+            .expect("initialize request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        response
+            .headers()
+            .get(SESSION_HEADER)
+            .expect("session header")
+            .to_str()
+            .expect("ascii session id")
+            .to_owned()
+    }
+
+    #[test]
+    fn session_id_format() {
+        let id = new_session_id().expect("random source available");
+        assert_eq!(id.len(), 32);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(id, new_session_id().expect("random source available"));
+    }
+
+    #[test]
+    fn sse_event_format() {
+        assert_eq!(
+            sse_event(r#"{"id":1}"#),
+            Bytes::from_static(b"event: message\ndata: {\"id\":1}\n\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn initialize_returns_session_and_sse() {
+        let url = start_server("http://127.0.0.1:9").await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .post(&url)
+            .header("accept", "application/json, text/event-stream")
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"}))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(response.headers().get(SESSION_HEADER).is_some());
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        let body = response.text().await.unwrap();
+        let data = body
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .expect("data line");
+        let parsed: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(parsed["result"]["serverInfo"]["name"], "bun-docs-mcp-proxy");
+    }
+
+    #[tokio::test]
+    async fn json_response_without_sse_accept() {
+        let url = start_server("http://127.0.0.1:9").await;
+        let http = reqwest::Client::new();
+        let session = initialize(&http, &url).await;
+
+        let response = http
+            .post(&url)
+            .header("accept", "application/json")
+            .header(SESSION_HEADER, &session)
+            .json(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            "application/json"
+        );
+        let parsed: Value = response.json().await.unwrap();
+        assert_eq!(parsed["id"], 2);
+        assert_eq!(parsed["result"]["tools"][0]["name"], "SearchBun");
+    }
+
+    #[tokio::test]
+    async fn tools_call_shares_upstream_client() {
+        let mut upstream = mockito::Server::new_async().await;
+        let mock = upstream
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {"content": [{"type": "text", "text": "shared"}]}}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let url = start_server(&upstream.url()).await;
+        let http = reqwest::Client::new();
+
+        // Distinct queries, so the second call is not answered from the response cache
+        for query in ["Bun.serve", "Bun.file"] {
+            let session = initialize(&http, &url).await;
+            let response = http
+                .post(&url)
+                .header("accept", "application/json")
+                .header(SESSION_HEADER, &session)
+                .json(&json!({
+                    "jsonrpc": "2.0",
+                    "id": "call",
+                    "method": "tools/call",
+                    "params": {"name": "SearchBun", "arguments": {"query": query}}
+                }))
+                .send()
+                .await
+                .unwrap();
+            let parsed: Value = response.json().await.unwrap();
+            assert_eq!(parsed["result"]["content"][0]["text"], "shared");
+        }
+
+        mock.assert_async().await;
+        drop(upstream);
+    }
+
+    #[tokio::test]
+    async fn notification_is_accepted() {
+        let url = start_server("http://127.0.0.1:9").await;
+        let http = reqwest::Client::new();
+        let session = initialize(&http, &url).await;
+
+        let response = http
+            .post(&url)
+            .header(SESSION_HEADER, &session)
+            .json(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+        assert!(response.text().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_and_unknown_sessions_are_rejected() {
+        let url = start_server("http://127.0.0.1:9").await;
+        let http = reqwest::Client::new();
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"});
+
+        let missing = http.post(&url).json(&request).send().await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let unknown = http
+            .post(&url)
+            .header(SESSION_HEADER, "does-not-exist")
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_terminates_session() {
+        let url = start_server("http://127.0.0.1:9").await;
+        let http = reqwest::Client::new();
+        let session = initialize(&http, &url).await;
+
+        let deleted = http
+            .delete(&url)
+            .header(SESSION_HEADER, &session)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(deleted.status(), reqwest::StatusCode::NO_CONTENT);
+
+        let after = http
+            .post(&url)
+            .header(SESSION_HEADER, &session)
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(after.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn idle_session_expires() {
+        let url =
+            start_server_with_idle_timeout("http://127.0.0.1:9", Duration::from_millis(200)).await;
+        let http = reqwest::Client::new();
+        let session = initialize(&http, &url).await;
+        let list = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"});
+
+        let active = http
+            .post(&url)
+            .header(SESSION_HEADER, &session)
+            .json(&list)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(active.status(), reqwest::StatusCode::OK);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let expired = http
+            .post(&url)
+            .header(SESSION_HEADER, &session)
+            .json(&list)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(expired.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn least_recently_used_session_is_dropped_at_the_cap() {
+        let client = BunDocsClient::with_base_url("http://127.0.0.1:9").unwrap();
+        let state = ServerState::new(client, AccessPolicy::default(), Shutdown::default());
+        let first = state.create_session().unwrap();
+        let second = state.create_session().unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        for _ in 2..MAX_SESSIONS {
+            state.create_session().unwrap();
+        }
+        // Using the first session makes the second the least recently used
+        assert!(state.touch_session(&first));
+
+        state.create_session().unwrap();
+        assert_eq!(state.sessions.lock().unwrap().len(), MAX_SESSIONS);
+        assert!(state.touch_session(&first));
+        assert!(!state.touch_session(&second));
+    }
+
+    #[tokio::test]
+    async fn get_opens_event_stream() {
+        let url = start_server("http://127.0.0.1:9").await;
+        let http = reqwest::Client::new();
+        let session = initialize(&http, &url).await;
+
+        let not_acceptable = http
+            .get(&url)
+            .header(SESSION_HEADER, &session)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(not_acceptable.status(), reqwest::StatusCode::NOT_ACCEPTABLE);
+
+        let stream = http
+            .get(&url)
+            .header("accept", "text/event-stream")
+            .header(SESSION_HEADER, &session)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(stream.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            stream.headers()[reqwest::header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_path_and_method() {
+        let url = start_server("http://127.0.0.1:9").await;
+        let http = reqwest::Client::new();
+
+        let not_found = http
+            .post(url.replace(MCP_ENDPOINT, "/other"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(not_found.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let not_allowed = http.put(&url).send().await.unwrap();
+        assert_eq!(
+            not_allowed.status(),
+            reqwest::StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(
+            not_allowed.headers()[reqwest::header::ALLOW],
+            "GET, POST, DELETE"
+        );
+    }
+
+    #[tokio::test]
+    async fn foreign_origin_gets_403() {
+        let url = start_server("http://127.0.0.1:9").await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .post(&url)
+            .header("origin", "https://evil.example")
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let local = http
+            .post(&url)
+            .header("origin", "http://localhost:5173")
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(local.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn bearer_token_gets_401_without_header() {
+        let policy = AccessPolicy::new(&[], Some("s3cret".to_owned())).expect("valid policy");
+        let url = start_server_with_policy("http://127.0.0.1:9", policy).await;
+        let http = reqwest::Client::new();
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"});
+
+        let denied = http.post(&url).json(&request).send().await.unwrap();
+        assert_eq!(denied.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            denied.headers()[reqwest::header::WWW_AUTHENTICATE],
+            "Bearer"
+        );
+
+        let allowed = http
+            .post(&url)
+            .bearer_auth("s3cret")
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(allowed.status(), reqwest::StatusCode::OK);
+    }
+}
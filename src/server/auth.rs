@@ -0,0 +1,295 @@
+//! Access control for the HTTP server mode.
+//!
+//! Implements the protections the MCP Streamable HTTP transport calls for when a
+//! server is reachable over the network:
+//!
+//! - **Origin validation** - browsers attach an `Origin` header to cross-site
+//!   requests, so rejecting unknown origins defeats DNS-rebinding attacks against a
+//!   proxy bound to localhost. Loopback origins are always allowed; requests without
+//!   an `Origin` header come from non-browser clients and are allowed as well.
+//! - **Bearer tokens** - when a token is configured, every request must carry
+//!   `Authorization: Bearer <token>`.
+//!
+//! Failed checks map to `403 Forbidden` (origin) and `401 Unauthorized` (token).
+
+use anyhow::{Context as _, Result};
+use hyper::StatusCode;
+use hyper::header::{self, HeaderMap};
+use reqwest::Url;
+use std::path::Path;
+
+/// Environment variable that may hold the bearer token
+pub const AUTH_TOKEN_ENV: &str = "BUN_DOCS_MCP_AUTH_TOKEN";
+
+/// Hosts whose origins are always allowed
+const LOOPBACK_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+/// Reason a request was refused by the [`AccessPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// The `Origin` header is not in the allowlist (`403`).
+    ForbiddenOrigin,
+    /// The bearer token is missing or wrong (`401`).
+    Unauthorized,
+}
+
+impl Rejection {
+    /// HTTP status code to answer with.
+    pub const fn status(self) -> StatusCode {
+        match self {
+            Self::ForbiddenOrigin => StatusCode::FORBIDDEN,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    /// Human-readable response body.
+    pub const fn message(self) -> &'static str {
+        match self {
+            Self::ForbiddenOrigin => "Origin not allowed",
+            Self::Unauthorized => "Missing or invalid bearer token",
+        }
+    }
+}
+
+/// Origin allowlist and optional bearer token applied to every HTTP request
+#[derive(Debug, Default)]
+pub struct AccessPolicy {
+    /// Serialized origins (`scheme://host[:port]`) allowed in addition to loopback.
+    allowed_origins: Vec<String>,
+    /// Token required in the `Authorization` header, if any.
+    token: Option<String>,
+}
+
+impl AccessPolicy {
+    /// Creates a policy from configured origins and an optional token.
+    ///
+    /// # Arguments
+    /// * `origins` - Extra allowed origins, e.g. `https://example.com`
+    /// * `token` - Bearer token every request must present
+    ///
+    /// # Errors
+    /// Returns an error if an origin cannot be parsed as a URL or the token is empty
+    pub fn new(origins: &[String], token: Option<String>) -> Result<Self> {
+        let allowed_origins = origins
+            .iter()
+            .map(|origin| {
+                Url::parse(origin)
+                    .map(|url| url.origin().ascii_serialization())
+                    .with_context(|| format!("Invalid allowed origin: {origin}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if token.as_deref().is_some_and(str::is_empty) {
+            anyhow::bail!("Bearer token must not be empty");
+        }
+
+        Ok(Self {
+            allowed_origins,
+            token,
+        })
+    }
+
+    /// Returns `true` if a bearer token is required.
+    pub const fn requires_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Checks a request's headers against the policy.
+    ///
+    /// # Errors
+    /// Returns the [`Rejection`] describing the first failed check
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), Rejection> {
+        if let Some(origin) = headers.get(header::ORIGIN) {
+            let allowed = origin
+                .to_str()
+                .is_ok_and(|origin| self.is_origin_allowed(origin));
+            if !allowed {
+                return Err(Rejection::ForbiddenOrigin);
+            }
+        }
+
+        if let Some(expected) = &self.token {
+            let presented = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(str::trim);
+            if !presented.is_some_and(|token| constant_time_eq(token, expected)) {
+                return Err(Rejection::Unauthorized);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the origin is loopback or explicitly allowed.
+    fn is_origin_allowed(&self, origin: &str) -> bool {
+        let Ok(url) = Url::parse(origin) else {
+            return false;
+        };
+        if url
+            .host_str()
+            .is_some_and(|host| LOOPBACK_HOSTS.contains(&host))
+        {
+            return true;
+        }
+        let serialized = url.origin().ascii_serialization();
+        self.allowed_origins.contains(&serialized)
+    }
+}
+
+/// Loads the bearer token from a file or, failing that, from [`AUTH_TOKEN_ENV`].
+///
+/// Surrounding whitespace (such as a trailing newline) is stripped.
+///
+/// # Arguments
+/// * `path` - Optional path to a file containing the token
+///
+/// # Returns
+/// The token, or `None` if neither source is configured
+///
+/// # Errors
+/// Returns an error if the file cannot be read
+pub fn load_token(path: Option<&Path>) -> Result<Option<String>> {
+    if let Some(path) = path {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read token file {}", path.display()))?;
+        return Ok(Some(contents.trim().to_owned()));
+    }
+    Ok(std::env::var(AUTH_TOKEN_ENV)
+        .ok()
+        .map(|token| token.trim().to_owned()))
+}
+
+/// Compares two strings without short-circuiting on the first differing byte.
+fn constant_time_eq(left: &str, right: &str) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+    left.bytes()
+        .zip(right.bytes())
+        .fold(0_u8, |acc, (a, b)| acc | (a ^ b))
+        == 0_u8
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+    use std::io::Write as _;
+
+    fn headers(pairs: &[(header::HeaderName, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(name.clone(), HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn no_origin_is_allowed() {
+        let policy = AccessPolicy::default();
+        assert_eq!(policy.check(&HeaderMap::new()), Ok(()));
+    }
+
+    #[test]
+    fn loopback_origins_are_allowed() {
+        let policy = AccessPolicy::default();
+        for origin in [
+            "http://localhost:3000",
+            "http://127.0.0.1",
+            "https://[::1]:8443",
+        ] {
+            assert!(policy.is_origin_allowed(origin), "{origin}");
+        }
+    }
+
+    #[test]
+    fn foreign_origin_is_forbidden() {
+        let policy = AccessPolicy::default();
+        let result = policy.check(&headers(&[(header::ORIGIN, "https://evil.example")]));
+        assert_eq!(result, Err(Rejection::ForbiddenOrigin));
+        assert_eq!(Rejection::ForbiddenOrigin.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn null_origin_is_forbidden() {
+        let policy = AccessPolicy::default();
+        let result = policy.check(&headers(&[(header::ORIGIN, "null")]));
+        assert_eq!(result, Err(Rejection::ForbiddenOrigin));
+    }
+
+    #[test]
+    fn configured_origin_is_allowed() {
+        let policy =
+            AccessPolicy::new(&["https://tools.example.com/".to_owned()], None).expect("valid");
+        assert!(policy.is_origin_allowed("https://tools.example.com"));
+        assert!(!policy.is_origin_allowed("https://tools.example.com:8443"));
+        assert!(!policy.is_origin_allowed("http://tools.example.com"));
+    }
+
+    #[test]
+    fn invalid_configured_origin_errors() {
+        AccessPolicy::new(&["not a url".to_owned()], None).unwrap_err();
+    }
+
+    #[test]
+    fn empty_token_errors() {
+        AccessPolicy::new(&[], Some(String::new())).unwrap_err();
+    }
+
+    #[test]
+    fn bearer_token_is_required() {
+        let policy = AccessPolicy::new(&[], Some("s3cret".to_owned())).expect("valid");
+        assert!(policy.requires_token());
+
+        assert_eq!(
+            policy.check(&HeaderMap::new()),
+            Err(Rejection::Unauthorized)
+        );
+        assert_eq!(
+            policy.check(&headers(&[(header::AUTHORIZATION, "Bearer wrong")])),
+            Err(Rejection::Unauthorized)
+        );
+        assert_eq!(
+            policy.check(&headers(&[(header::AUTHORIZATION, "Basic s3cret")])),
+            Err(Rejection::Unauthorized)
+        );
+        assert_eq!(
+            policy.check(&headers(&[(header::AUTHORIZATION, "Bearer s3cret")])),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn origin_is_checked_before_token() {
+        let policy = AccessPolicy::new(&[], Some("s3cret".to_owned())).expect("valid");
+        let result = policy.check(&headers(&[
+            (header::ORIGIN, "https://evil.example"),
+            (header::AUTHORIZATION, "Bearer s3cret"),
+        ]));
+        assert_eq!(result, Err(Rejection::ForbiddenOrigin));
+    }
+
+    #[test]
+    fn token_loaded_from_file() {
+        let mut file = tempfile::NamedTempFile::new().expect("temp file");
+        writeln!(file, "  file-token  ").expect("write token");
+        let token = load_token(Some(file.path())).expect("readable file");
+        assert_eq!(token.as_deref(), Some("file-token"));
+    }
+
+    #[test]
+    fn token_file_missing_errors() {
+        load_token(Some(Path::new("/nonexistent/token"))).unwrap_err();
+    }
+
+    #[test]
+    fn constant_time_comparison() {
+        assert!(constant_time_eq("abc", "abc"));
+        assert!(!constant_time_eq("abc", "abd"));
+        assert!(!constant_time_eq("abc", "abcd"));
+    }
+}
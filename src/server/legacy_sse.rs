@@ -0,0 +1,360 @@
+//! Legacy HTTP+SSE server transport (MCP protocol revision 2024-11-05).
+//!
+//! Older clients open `GET /sse` and first receive an `endpoint` event naming the URL
+//! to POST their JSON-RPC messages to (`/messages?sessionId=...`). Each POST is
+//! acknowledged with `202 Accepted`, and the JSON-RPC response is delivered later as
+//! a `message` event on the client's SSE stream. The session ends when that stream
+//! is closed.
+//!
+//! The transport is served by the same listener as Streamable HTTP and shares its
+//! access policy, upstream client, notification channel and dispatcher.
+
+use super::{
+    ResponseBody, SSE_KEEP_ALIVE_SECS, ServerState, new_session_id, plain_response, read_body,
+    sse_event, sse_response,
+};
+use bytes::Bytes;
+use core::convert::Infallible;
+use futures::StreamExt as _;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::{Request, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, info, warn};
+
+/// Path clients open to receive the event stream
+pub const SSE_ENDPOINT: &str = "/sse";
+
+/// Path clients POST messages to, announced in the `endpoint` event
+pub const MESSAGES_ENDPOINT: &str = "/messages";
+
+/// Query parameter carrying the session ID on message POSTs
+const SESSION_QUERY_PARAM: &str = "sessionId";
+
+/// Senders for the event streams of connected legacy clients, keyed by session ID
+#[derive(Default)]
+pub struct LegacySessions {
+    /// Per-session channel feeding that session's SSE stream.
+    streams: Mutex<HashMap<String, mpsc::UnboundedSender<Bytes>>>,
+}
+
+impl LegacySessions {
+    /// Registers the stream sender of a new session.
+    fn insert(&self, id: String, sender: mpsc::UnboundedSender<Bytes>) {
+        self.streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, sender);
+    }
+
+    /// Returns the stream sender of a session, if it is still connected.
+    fn sender(&self, id: &str) -> Option<mpsc::UnboundedSender<Bytes>> {
+        self.streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(id)
+            .cloned()
+    }
+
+    /// Forgets a session.
+    fn remove(&self, id: &str) {
+        self.streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(id);
+    }
+}
+
+/// Removes a legacy session when its event stream is dropped (client disconnected).
+struct SessionGuard {
+    /// Server state holding the session.
+    state: Arc<ServerState>,
+    /// ID of the session to remove.
+    id: String,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.state.legacy.remove(&self.id);
+        info!("Closed legacy SSE session {}", self.id);
+    }
+}
+
+/// Handles `GET /sse`: creates a session and returns its event stream.
+///
+/// The stream starts with the `endpoint` event, then carries responses to the
+/// session's POSTs, server notifications and periodic keep-alive comments until the
+/// client disconnects or the server shuts down.
+pub(super) fn open_stream(state: Arc<ServerState>) -> Response<ResponseBody> {
+    let id = match new_session_id() {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Failed to create legacy session: {}", e);
+            return plain_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to create session",
+            );
+        }
+    };
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    state.legacy.insert(id.clone(), sender);
+    info!("Opened legacy SSE session {}", id);
+
+    let endpoint = Bytes::from(format!(
+        "event: endpoint\ndata: {MESSAGES_ENDPOINT}?{SESSION_QUERY_PARAM}={id}\n\n"
+    ));
+    let notifications = state.notifications.subscribe();
+    let mut keep_alive = tokio::time::interval(Duration::from_secs(SSE_KEEP_ALIVE_SECS));
+    keep_alive.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately; the endpoint event covers it
+    keep_alive.reset();
+    let shutdown = state.shutdown.clone();
+    let guard = SessionGuard { state, id };
+
+    let events = futures::stream::unfold(
+        (guard, receiver, notifications, keep_alive, shutdown),
+        |(guard, mut receiver, mut notifications, mut keep_alive, mut shutdown)| async move {
+            loop {
+                let chunk = tokio::select! {
+                    _ = shutdown.triggered() => return None,
+                    message = receiver.recv() => message?,
+                    notification = notifications.recv() => match notification {
+                        Ok(message) => sse_event(&message),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Legacy session {} missed {} notifications", guard.id, skipped);
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    },
+                    _ = keep_alive.tick() => Bytes::from_static(b": keep-alive\n\n"),
+                };
+                return Some((
+                    chunk,
+                    (guard, receiver, notifications, keep_alive, shutdown),
+                ));
+            }
+        },
+    );
+
+    let stream = futures::stream::once(async { endpoint })
+        .chain(events)
+        .map(|chunk| Ok::<_, Infallible>(Frame::data(chunk)));
+    sse_response(BodyExt::boxed(StreamBody::new(stream)))
+}
+
+/// Handles `POST /messages?sessionId=...` carrying one JSON-RPC message.
+///
+/// The message is dispatched in the background and `202 Accepted` is returned
+/// immediately; any response is written to the session's event stream.
+pub(super) async fn post_message(
+    state: Arc<ServerState>,
+    request: Request<Incoming>,
+) -> Response<ResponseBody> {
+    let Some(id) = request.uri().query().and_then(session_from_query) else {
+        return plain_response(StatusCode::BAD_REQUEST, "Missing sessionId query parameter");
+    };
+    let Some(sender) = state.legacy.sender(&id) else {
+        return plain_response(StatusCode::NOT_FOUND, "Unknown session");
+    };
+
+    let message = match read_body(request).await {
+        Ok(message) => message,
+        Err((status, text)) => return plain_response(status, text),
+    };
+
+    let busy = state.shutdown.activity().begin();
+    tokio::spawn(async move {
+        let handled = crate::handle_message(&state.client, &message, None).await;
+        drop(busy);
+        let Some(response) = handled else {
+            return;
+        };
+        match serde_json::to_string(&response) {
+            Ok(payload) => {
+                if sender.send(sse_event(&payload)).is_err() {
+                    debug!("Legacy session {} closed before its response was sent", id);
+                }
+            }
+            Err(e) => error!("Failed to serialize response: {}", e),
+        }
+    });
+
+    plain_response(StatusCode::ACCEPTED, "Accepted")
+}
+
+/// Extracts the session ID from a `sessionId=...` query string.
+fn session_from_query(query: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == SESSION_QUERY_PARAM)
+        .map(|(_, value)| value.to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+#[allow(clippy::indexing_slicing, reason = "tests use array indexing")]
+#[allow(clippy::default_numeric_fallback, reason = "test literals")]
+mod tests {
+    use super::super::auth::AccessPolicy;
+    use super::super::serve_listener;
+    use super::*;
+    use crate::http::BunDocsClient;
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    /// Starts a server on an ephemeral port, returning its base URL.
+    async fn start_server() -> String {
+        let client = BunDocsClient::with_base_url("http://127.0.0.1:9").expect("valid URL");
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+        tokio::spawn(serve_listener(
+            listener,
+            Arc::new(ServerState::new(
+                client,
+                AccessPolicy::default(),
+                crate::shutdown::Shutdown::default(),
+            )),
+        ));
+        format!("http://{addr}")
+    }
+
+    /// Reads SSE chunks until a complete event with the given type arrives,
+    /// returning its data.
+    async fn next_event(
+        stream: &mut (impl futures::Stream<Item = reqwest::Result<Bytes>> + Unpin),
+        buffer: &mut String,
+        event_type: &str,
+    ) -> String {
+        loop {
+            while let Some(end) = buffer.find("\n\n") {
+                let event: String = buffer.drain(..end + 2).collect();
+                let mut name = "message";
+                let mut data = String::new();
+                for line in event.lines() {
+                    if let Some(value) = line.strip_prefix("event: ") {
+                        name = value;
+                    } else if let Some(value) = line.strip_prefix("data: ") {
+                        data.push_str(value);
+                    }
+                }
+                if name == event_type && !data.is_empty() {
+                    return data;
+                }
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), stream.next())
+                .await
+                .expect("event before timeout")
+                .expect("stream still open")
+                .expect("valid chunk");
+            buffer.push_str(core::str::from_utf8(&chunk).unwrap());
+        }
+    }
+
+    #[test]
+    fn session_query_parsing() {
+        assert_eq!(session_from_query("sessionId=abc"), Some("abc".to_owned()));
+        assert_eq!(
+            session_from_query("x=1&sessionId=abc&y=2"),
+            Some("abc".to_owned())
+        );
+        assert_eq!(session_from_query("sessionId="), None);
+        assert_eq!(session_from_query("other=abc"), None);
+    }
+
+    #[tokio::test]
+    async fn endpoint_event_then_response_on_stream() {
+        let base = start_server().await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .get(format!("{base}{SSE_ENDPOINT}"))
+            .header("accept", "text/event-stream")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        let endpoint = next_event(&mut stream, &mut buffer, "endpoint").await;
+        assert!(endpoint.starts_with("/messages?sessionId="));
+
+        let accepted = http
+            .post(format!("{base}{endpoint}"))
+            .json(&json!({"jsonrpc": "2.0", "id": 5, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(accepted.status(), reqwest::StatusCode::ACCEPTED);
+
+        let data = next_event(&mut stream, &mut buffer, "message").await;
+        let parsed: Value = serde_json::from_str(&data).unwrap();
+        assert_eq!(parsed["id"], 5);
+        assert_eq!(parsed["result"]["tools"][0]["name"], "SearchBun");
+    }
+
+    #[tokio::test]
+    async fn post_to_unknown_session_is_404() {
+        let base = start_server().await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .post(format!("{base}{MESSAGES_ENDPOINT}?sessionId=nope"))
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let missing = http
+            .post(format!("{base}{MESSAGES_ENDPOINT}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn closing_stream_ends_session() {
+        let base = start_server().await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .get(format!("{base}{SSE_ENDPOINT}"))
+            .send()
+            .await
+            .unwrap();
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        let endpoint = next_event(&mut stream, &mut buffer, "endpoint").await;
+        drop(stream);
+
+        // The server notices the disconnect on its next write; poll until it does
+        let mut status = reqwest::StatusCode::ACCEPTED;
+        for _ in 0..50 {
+            status = http
+                .post(format!("{base}{endpoint}"))
+                .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+                .send()
+                .await
+                .unwrap()
+                .status();
+            if status == reqwest::StatusCode::NOT_FOUND {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,292 @@
+//! Graceful shutdown coordination.
+//!
+//! `main` creates one [`ShutdownTrigger`]/[`Shutdown`] pair. The trigger fires on SIGINT,
+//! SIGTERM, when stdin reaches EOF, or from a watchdog (see [`crate::watchdog`]); every
+//! server loop holds a [`Shutdown`] handle and,
+//! once it fires, stops accepting new input and finishes in-flight work through
+//! [`Shutdown::drain`]. Work still running when the grace period (measured from the
+//! trigger) runs out is cancelled. A second signal exits immediately.
+//!
+//! ## Exit Status
+//!
+//! - `0` - input closed (EOF), parent exited or idle timeout, and all work finished
+//! - `130` / `143` - stopped by SIGINT / SIGTERM (128 + signal number), all work finished
+//! - `1` - in-flight work was cancelled when the grace period ran out
+
+use crate::watchdog::Activity;
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Default time in-flight work may take to finish after shutdown starts (seconds)
+pub const DEFAULT_GRACE_PERIOD_SECS: u64 = 5_u64;
+
+/// Exit status when in-flight work had to be cancelled
+const CANCELLED_EXIT_CODE: u8 = 1_u8;
+
+/// Why the proxy is shutting down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// The input stream reached EOF.
+    InputClosed,
+    /// SIGINT (Ctrl-C) was received.
+    Interrupt,
+    /// SIGTERM was received.
+    Terminate,
+    /// The parent process exited (`--watch-parent`).
+    ParentExited,
+    /// No traffic arrived within `--idle-timeout`.
+    IdleTimeout,
+}
+
+impl ShutdownReason {
+    /// Exit status reported when shutdown for this reason completes cleanly.
+    pub const fn exit_code(self) -> u8 {
+        match self {
+            Self::InputClosed | Self::ParentExited | Self::IdleTimeout => 0_u8,
+            Self::Interrupt => 130_u8,
+            Self::Terminate => 143_u8,
+        }
+    }
+}
+
+/// Shared shutdown state: the first reason and when it was triggered.
+type State = Option<(ShutdownReason, Instant)>;
+
+/// Starts shutdown; held by signal handlers and the stdio loop
+#[derive(Clone)]
+pub struct ShutdownTrigger {
+    /// Publishes the shutdown state to every [`Shutdown`] handle.
+    sender: Arc<watch::Sender<State>>,
+}
+
+/// Observes shutdown; cloned into every server loop and connection
+#[derive(Clone)]
+pub struct Shutdown {
+    /// Receives the shutdown state.
+    receiver: watch::Receiver<State>,
+    /// Time in-flight work may take after the trigger.
+    grace: Duration,
+    /// Requests in flight across every server loop, watched by the idle watchdog.
+    activity: Activity,
+}
+
+/// Creates a connected trigger and handle.
+///
+/// # Arguments
+/// * `grace` - Time in-flight work may take to finish once shutdown starts
+pub fn channel(grace: Duration) -> (ShutdownTrigger, Shutdown) {
+    let (sender, receiver) = watch::channel(None);
+    (
+        ShutdownTrigger {
+            sender: Arc::new(sender),
+        },
+        Shutdown {
+            receiver,
+            grace,
+            activity: Activity::default(),
+        },
+    )
+}
+
+impl ShutdownTrigger {
+    /// Starts shutdown for `reason`. Only the first call has an effect.
+    ///
+    /// # Returns
+    /// `true` if this call started shutdown
+    pub fn trigger(&self, reason: ShutdownReason) -> bool {
+        self.sender.send_if_modified(|state| {
+            if state.is_some() {
+                return false;
+            }
+            *state = Some((reason, Instant::now()));
+            true
+        })
+    }
+}
+
+impl Default for Shutdown {
+    /// A handle that is never triggered.
+    fn default() -> Self {
+        channel(Duration::ZERO).1
+    }
+}
+
+impl Shutdown {
+    /// Returns the shutdown reason, or `None` while the proxy is running.
+    pub fn reason(&self) -> Option<ShutdownReason> {
+        self.receiver.borrow().map(|(reason, _)| reason)
+    }
+
+    /// Returns the activity tracker server loops mark their requests with.
+    pub const fn activity(&self) -> &Activity {
+        &self.activity
+    }
+
+    /// Waits until shutdown starts. Never completes if the trigger is dropped unused.
+    pub async fn triggered(&mut self) -> ShutdownReason {
+        let state = self
+            .receiver
+            .wait_for(Option::is_some)
+            .await
+            .map_or(None, |state| *state);
+        match state {
+            Some((reason, _)) => reason,
+            None => core::future::pending().await,
+        }
+    }
+
+    /// Runs `future` to completion unless shutdown starts and the grace period
+    /// runs out first.
+    ///
+    /// # Returns
+    /// The future's output, or `None` if it was cancelled
+    pub async fn drain<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut receiver = self.receiver.clone();
+        tokio::pin!(future);
+
+        let started = tokio::select! {
+            output = &mut future => return Some(output),
+            state = receiver.wait_for(Option::is_some) => {
+                state.ok().and_then(|state| state.map(|(_, at)| at))
+            }
+        };
+        let Some(started) = started else {
+            return Some(future.await);
+        };
+
+        let deadline = tokio::time::Instant::from_std(started + self.grace);
+        tokio::time::timeout_at(deadline, future).await.ok()
+    }
+
+    /// Exit status for the process once all server loops have returned.
+    ///
+    /// # Arguments
+    /// * `drained` - Whether all in-flight work finished within the grace period
+    pub fn exit_code(&self, drained: bool) -> u8 {
+        if !drained {
+            return CANCELLED_EXIT_CODE;
+        }
+        self.reason().map_or(0_u8, ShutdownReason::exit_code)
+    }
+}
+
+/// Fires `trigger` on SIGINT or SIGTERM; a second signal exits immediately.
+///
+/// # Errors
+/// Returns an error if the signal handlers cannot be installed
+pub fn listen_for_signals(trigger: ShutdownTrigger) -> Result<()> {
+    #[cfg(unix)]
+    let mut terminate = {
+        use anyhow::Context as _;
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .context("Failed to install SIGTERM handler")?
+    };
+
+    tokio::spawn(async move {
+        loop {
+            #[cfg(unix)]
+            let received = tokio::select! {
+                result = tokio::signal::ctrl_c() => result.map(|()| ShutdownReason::Interrupt),
+                _ = terminate.recv() => Ok(ShutdownReason::Terminate),
+            };
+            #[cfg(not(unix))]
+            let received = tokio::signal::ctrl_c()
+                .await
+                .map(|()| ShutdownReason::Interrupt);
+
+            let reason = match received {
+                Ok(reason) => reason,
+                Err(e) => {
+                    warn!("Failed to listen for Ctrl-C: {}", e);
+                    return;
+                }
+            };
+            if trigger.trigger(reason) {
+                info!("Received {:?}, shutting down", reason);
+            } else {
+                warn!("Received {:?} again, exiting immediately", reason);
+                std::process::exit(i32::from(reason.exit_code()));
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_reason_wins() {
+        let (trigger, shutdown) = channel(Duration::ZERO);
+        assert_eq!(shutdown.reason(), None);
+        assert!(trigger.trigger(ShutdownReason::Terminate));
+        assert!(!trigger.trigger(ShutdownReason::InputClosed));
+        assert_eq!(shutdown.reason(), Some(ShutdownReason::Terminate));
+    }
+
+    #[test]
+    fn exit_codes() {
+        let (trigger, shutdown) = channel(Duration::ZERO);
+        assert_eq!(shutdown.exit_code(true), 0_u8);
+        assert_eq!(shutdown.exit_code(false), 1_u8);
+
+        trigger.trigger(ShutdownReason::Interrupt);
+        assert_eq!(shutdown.exit_code(true), 130_u8);
+        assert_eq!(shutdown.exit_code(false), 1_u8);
+        assert_eq!(ShutdownReason::Terminate.exit_code(), 143_u8);
+        assert_eq!(ShutdownReason::InputClosed.exit_code(), 0_u8);
+        assert_eq!(ShutdownReason::ParentExited.exit_code(), 0_u8);
+        assert_eq!(ShutdownReason::IdleTimeout.exit_code(), 0_u8);
+    }
+
+    #[tokio::test]
+    async fn triggered_resolves_after_trigger() {
+        let (trigger, mut shutdown) = channel(Duration::ZERO);
+        let waiter = tokio::spawn(async move { shutdown.triggered().await });
+        trigger.trigger(ShutdownReason::Interrupt);
+        assert_eq!(waiter.await.unwrap(), ShutdownReason::Interrupt);
+    }
+
+    #[tokio::test]
+    async fn default_handle_never_triggers() {
+        let mut shutdown = Shutdown::default();
+        let result = tokio::time::timeout(Duration::from_millis(20), shutdown.triggered()).await;
+        assert!(result.is_err());
+        assert_eq!(shutdown.drain(async { 7 }).await, Some(7));
+    }
+
+    #[tokio::test]
+    async fn drain_finishes_work_within_grace() {
+        let (trigger, shutdown) = channel(Duration::from_secs(5));
+        trigger.trigger(ShutdownReason::Terminate);
+        let output = shutdown
+            .drain(async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                "done"
+            })
+            .await;
+        assert_eq!(output, Some("done"));
+    }
+
+    #[tokio::test]
+    async fn drain_cancels_work_after_grace() {
+        let (trigger, shutdown) = channel(Duration::from_millis(20));
+        let work = shutdown.drain(tokio::time::sleep(Duration::from_secs(30)));
+        let stopper = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            trigger.trigger(ShutdownReason::Interrupt);
+        };
+        let (output, ()) = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(work, stopper)
+        })
+        .await
+        .expect("drain returns once the grace period expires");
+        assert_eq!(output, None);
+    }
+}
@@ -0,0 +1,523 @@
+//! Unix domain socket daemon and stdio shim.
+//!
+//! `--socket PATH` runs one long-lived proxy that accepts many connections on a Unix
+//! socket. Each connection speaks the same framed JSON-RPC as stdio mode and is served
+//! by its own task, while all of them share a single [`BunDocsClient`].
+//!
+//! `--connect PATH` is a tiny shim for editors that insist on spawning a binary: it
+//! relays stdin to the socket and the socket to stdout byte-for-byte, so a dozen editor
+//! windows can reach one warm daemon.
+//!
+//! The socket file is bound inside a private `0700` directory, restricted to mode
+//! `0600` and only then moved into place, so other users can never connect to it; it
+//! is removed when the daemon stops. A stale socket left behind by a crashed daemon is
+//! replaced; a live one is an error.
+//! On shutdown the daemon stops accepting connections and waits for open ones to
+//! drain within the grace period.
+
+use crate::http::BunDocsClient;
+use crate::shutdown::Shutdown;
+use crate::transport::Framing;
+use anyhow::Result;
+use std::path::Path;
+
+#[cfg(unix)]
+use {
+    crate::transport::UnixTransport,
+    anyhow::Context as _,
+    std::os::unix::fs::{
+        DirBuilderExt as _, FileTypeExt as _, MetadataExt as _, PermissionsExt as _,
+    },
+    std::path::PathBuf,
+    std::sync::Arc,
+    tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt as _},
+    tokio::net::{UnixListener, UnixStream},
+    tokio::task::JoinSet,
+    tracing::{debug, info, warn},
+};
+
+/// Permissions of the socket file (owner read/write only)
+#[cfg(unix)]
+const SOCKET_MODE: u32 = 0o600;
+
+/// Permissions of the directory the socket is bound in before it is moved into place
+#[cfg(unix)]
+const STAGING_DIR_MODE: u32 = 0o700;
+
+/// Removes the socket file when the daemon stops, unless another process has
+/// replaced it since.
+#[cfg(unix)]
+#[derive(Debug)]
+struct SocketFileGuard {
+    /// Path of the socket file to remove.
+    path: PathBuf,
+    /// Device and inode of the socket file this process bound.
+    inode: (u64, u64),
+}
+
+#[cfg(unix)]
+impl Drop for SocketFileGuard {
+    fn drop(&mut self) {
+        match std::fs::symlink_metadata(&self.path) {
+            Ok(metadata) if (metadata.dev(), metadata.ino()) == self.inode => {
+                if let Err(e) = std::fs::remove_file(&self.path) {
+                    debug!("Failed to remove socket {}: {}", self.path.display(), e);
+                }
+            }
+            Ok(_) => debug!(
+                "Not removing socket {}: replaced by another process",
+                self.path.display()
+            ),
+            Err(e) => debug!("Failed to remove socket {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+/// Binds a Unix listener at `path`, replacing a stale socket file if needed.
+///
+/// # Returns
+/// The listener and a guard removing the socket file when dropped
+///
+/// # Errors
+/// Returns an error if another daemon is already listening at `path`, if `path` exists
+/// and is not a socket, or if binding fails
+#[cfg(unix)]
+async fn bind(path: &Path) -> Result<(UnixListener, SocketFileGuard)> {
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            anyhow::bail!("{} exists and is not a socket", path.display());
+        }
+        if UnixStream::connect(path).await.is_ok() {
+            anyhow::bail!("Another proxy is already listening on {}", path.display());
+        }
+        warn!("Removing stale socket {}", path.display());
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+
+    bind_private(path)
+}
+
+/// Binds a Unix listener at `path` that only the owner can ever connect to.
+///
+/// Binding creates the socket file with the process umask, so the socket is bound
+/// inside a fresh owner-only directory next to `path`, restricted to [`SOCKET_MODE`]
+/// and only then hard-linked to `path`. Unlike a rename, the link fails if `path`
+/// has appeared in the meantime, so a daemon started at the same moment keeps its
+/// socket.
+///
+/// # Errors
+/// Returns an error if `path` already exists, or if the staging directory cannot be
+/// created or binding, restricting or linking the socket fails
+#[cfg(unix)]
+fn bind_private(path: &Path) -> Result<(UnixListener, SocketFileGuard)> {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let staging = parent.join(format!(
+        ".bun-docs-mcp-{}.{:016x}",
+        std::process::id(),
+        getrandom::u64().unwrap_or_default()
+    ));
+    std::fs::DirBuilder::new()
+        .mode(STAGING_DIR_MODE)
+        .create(&staging)
+        .with_context(|| format!("Failed to create {}", staging.display()))?;
+
+    let staged = staging.join("sock");
+    let bound = bind_staged(&staged, path);
+    if let Err(e) = std::fs::remove_file(&staged)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        debug!("Failed to remove {}: {}", staged.display(), e);
+    }
+    if let Err(e) = std::fs::remove_dir(&staging) {
+        debug!("Failed to remove {}: {}", staging.display(), e);
+    }
+    bound
+}
+
+/// Binds a listener at `staged`, restricts it to [`SOCKET_MODE`] and links it to
+/// `path`.
+///
+/// # Errors
+/// Returns an error if `path` already exists, or if binding, restricting or linking
+/// the socket fails
+#[cfg(unix)]
+fn bind_staged(staged: &Path, path: &Path) -> Result<(UnixListener, SocketFileGuard)> {
+    let listener =
+        UnixListener::bind(staged).with_context(|| format!("Failed to bind {}", path.display()))?;
+    std::fs::set_permissions(staged, std::fs::Permissions::from_mode(SOCKET_MODE))
+        .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+    let metadata = std::fs::symlink_metadata(staged)
+        .with_context(|| format!("Failed to inspect {}", staged.display()))?;
+    if let Err(e) = std::fs::hard_link(staged, path) {
+        if e.kind() == std::io::ErrorKind::AlreadyExists {
+            anyhow::bail!("Another proxy is already listening on {}", path.display());
+        }
+        return Err(e).with_context(|| format!("Failed to move socket to {}", path.display()));
+    }
+    let guard = SocketFileGuard {
+        path: path.to_path_buf(),
+        inode: (metadata.dev(), metadata.ino()),
+    };
+    Ok((listener, guard))
+}
+
+/// Accepts connections and serves each on its own task until shutdown, then waits
+/// for the open connections to drain.
+///
+/// # Returns
+/// `false` if any connection had in-flight work cancelled
+#[cfg(unix)]
+async fn serve_listener(
+    listener: UnixListener,
+    client: Arc<BunDocsClient>,
+    framing: Framing,
+    max_message_size: usize,
+    mut shutdown: Shutdown,
+) -> bool {
+    let mut connections = JoinSet::new();
+
+    loop {
+        let stream = tokio::select! {
+            biased;
+            _ = shutdown.triggered() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Failed to accept socket connection: {}", e);
+                    continue;
+                }
+            },
+        };
+        debug!("Accepted socket connection");
+
+        // Reap connections that have already closed
+        while connections.try_join_next().is_some() {}
+
+        let client = Arc::clone(&client);
+        let shutdown = shutdown.clone();
+        connections.spawn(async move {
+            let transport =
+                UnixTransport::unix(stream, framing).with_max_message_size(max_message_size);
+            let drained = crate::run_server(&client, transport, shutdown).await;
+            debug!("Socket connection closed");
+            drained
+        });
+    }
+
+    info!(
+        "Waiting for {} open connection(s) to finish",
+        connections.len()
+    );
+    let mut drained = true;
+    while let Some(result) = connections.join_next().await {
+        drained &= result.unwrap_or(false);
+    }
+    drained
+}
+
+/// Runs the daemon on a Unix socket.
+///
+/// # Arguments
+/// * `path` - Filesystem path of the socket
+/// * `client` - Upstream client shared by all connections
+/// * `framing` - Framing used on every connection (`Auto` detects per connection)
+/// * `max_message_size` - Largest accepted message on each connection (bytes)
+/// * `shutdown` - Shutdown handle that stops the daemon
+///
+/// # Returns
+/// `false` if in-flight work was cancelled during shutdown
+///
+/// # Errors
+/// Returns an error if the socket cannot be bound
+#[cfg(unix)]
+pub async fn serve(
+    path: &Path,
+    client: BunDocsClient,
+    framing: Framing,
+    max_message_size: usize,
+    shutdown: Shutdown,
+) -> Result<bool> {
+    let (listener, _guard) = bind(path).await?;
+    info!("Bun Docs MCP Proxy listening on {}", path.display());
+
+    let client = Arc::new(client);
+    let drained = serve_listener(
+        listener,
+        Arc::clone(&client),
+        framing,
+        max_message_size,
+        shutdown.clone(),
+    )
+    .await;
+    crate::end_upstream_session(&client, &shutdown).await;
+    Ok(drained)
+}
+
+/// Relays stdin/stdout to a daemon listening on a Unix socket.
+///
+/// # Arguments
+/// * `path` - Filesystem path of the daemon's socket
+///
+/// # Errors
+/// Returns an error if the socket cannot be reached or relaying fails
+#[cfg(unix)]
+pub async fn connect(path: &Path) -> Result<()> {
+    let stream = UnixStream::connect(path)
+        .await
+        .with_context(|| format!("Failed to connect to {}", path.display()))?;
+    debug!("Connected to {}", path.display());
+    relay(tokio::io::stdin(), tokio::io::stdout(), stream).await
+}
+
+/// Copies `input` to `stream` and `stream` to `output` until the daemon side closes.
+///
+/// When `input` reaches EOF the write half of `stream` is shut down, and relaying
+/// continues until the daemon has sent its remaining responses and closed. If the
+/// daemon closes first, relaying stops immediately.
+///
+/// # Errors
+/// Returns an error if copying in either direction fails
+#[cfg(unix)]
+async fn relay<I, O, S>(mut input: I, mut output: O, stream: S) -> Result<()>
+where
+    I: AsyncRead + Unpin,
+    O: AsyncWrite + Unpin,
+    S: AsyncRead + AsyncWrite,
+{
+    let (mut socket_read, mut socket_write) = tokio::io::split(stream);
+
+    let upstream = async {
+        tokio::io::copy(&mut input, &mut socket_write)
+            .await
+            .context("Failed to forward input to socket")?;
+        socket_write
+            .shutdown()
+            .await
+            .context("Failed to shut down socket")
+    };
+    let downstream = async {
+        tokio::io::copy(&mut socket_read, &mut output)
+            .await
+            .context("Failed to forward socket to output")?;
+        output.flush().await.context("Failed to flush output")
+    };
+    tokio::pin!(upstream, downstream);
+
+    tokio::select! {
+        result = &mut downstream => result,
+        result = &mut upstream => {
+            result?;
+            downstream.await
+        }
+    }
+}
+
+/// Runs the daemon on a Unix socket (unsupported on this platform).
+///
+/// # Errors
+/// Always returns an error
+#[cfg(not(unix))]
+pub async fn serve(
+    _path: &Path,
+    _client: BunDocsClient,
+    _framing: Framing,
+    _max_message_size: usize,
+    _shutdown: Shutdown,
+) -> Result<bool> {
+    anyhow::bail!("Unix domain sockets are not supported on this platform")
+}
+
+/// Relays stdin/stdout to a Unix socket (unsupported on this platform).
+///
+/// # Errors
+/// Always returns an error
+#[cfg(not(unix))]
+pub async fn connect(_path: &Path) -> Result<()> {
+    anyhow::bail!("Unix domain sockets are not supported on this platform")
+}
+
+#[cfg(all(test, unix))]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+#[allow(clippy::indexing_slicing, reason = "tests use array indexing")]
+#[allow(clippy::default_numeric_fallback, reason = "test literals")]
+mod tests {
+    use super::*;
+    use crate::transport::Transport as _;
+    use serde_json::Value;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt as _;
+
+    /// Starts a daemon in `dir`, returning the socket path and the daemon task.
+    async fn start_daemon_with_shutdown(
+        dir: &Path,
+        shutdown: Shutdown,
+    ) -> (PathBuf, tokio::task::JoinHandle<bool>) {
+        let path = dir.join("proxy.sock");
+        let (listener, guard) = bind(&path).await.expect("bind socket");
+        let client = BunDocsClient::with_base_url("http://127.0.0.1:9").expect("valid URL");
+        let daemon = tokio::spawn(async move {
+            let _guard = guard;
+            serve_listener(
+                listener,
+                Arc::new(client),
+                Framing::Auto,
+                crate::transport::DEFAULT_MAX_MESSAGE_SIZE,
+                shutdown,
+            )
+            .await
+        });
+        (path, daemon)
+    }
+
+    /// Starts a daemon in `dir` that never shuts down, returning the socket path.
+    async fn start_daemon(dir: &Path) -> PathBuf {
+        start_daemon_with_shutdown(dir, Shutdown::default()).await.0
+    }
+
+    #[tokio::test]
+    async fn serves_multiple_concurrent_clients() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = start_daemon(dir.path()).await;
+
+        let mut first =
+            UnixTransport::unix(UnixStream::connect(&path).await.unwrap(), Framing::Ndjson);
+        let mut second =
+            UnixTransport::unix(UnixStream::connect(&path).await.unwrap(), Framing::Ndjson);
+
+        first
+            .write_message(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#)
+            .await
+            .unwrap();
+        second
+            .write_message(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#)
+            .await
+            .unwrap();
+
+        let second_reply: Value =
+            serde_json::from_str(&second.read_message().await.unwrap().unwrap()).unwrap();
+        assert_eq!(second_reply["id"], 2);
+        assert_eq!(second_reply["result"]["tools"][0]["name"], "SearchBun");
+
+        let first_reply: Value =
+            serde_json::from_str(&first.read_message().await.unwrap().unwrap()).unwrap();
+        assert_eq!(first_reply["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn socket_file_is_owner_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = start_daemon(dir.path()).await;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, SOCKET_MODE);
+        // The private staging directory is gone once the socket is in place
+        let entries: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, ["proxy.sock"]);
+    }
+
+    #[tokio::test]
+    async fn live_socket_is_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = start_daemon(dir.path()).await;
+        let error = bind(&path).await.unwrap_err();
+        assert!(error.to_string().contains("already listening"));
+    }
+
+    #[tokio::test]
+    async fn stale_socket_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stale.sock");
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        bind(&path).await.expect("stale socket replaced");
+    }
+
+    #[tokio::test]
+    async fn path_created_after_the_check_is_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("proxy.sock");
+        // Another process wins the race between the stale-socket check and linking
+        std::fs::write(&path, "data").unwrap();
+
+        let error = bind_private(&path).unwrap_err();
+        assert!(error.to_string().contains("already listening"), "{error:#}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+        let entries: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, ["proxy.sock"]);
+    }
+
+    #[tokio::test]
+    async fn guard_leaves_a_replaced_socket_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("proxy.sock");
+        // The first listener stays open so its inode cannot be reused
+        let (_listener, guard) = bind(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let (_other, other_guard) = bind(&path).await.unwrap();
+
+        drop(guard);
+        assert!(path.exists(), "the other daemon's socket is kept");
+        drop(other_guard);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn regular_file_is_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("not-a-socket");
+        std::fs::write(&path, "data").unwrap();
+        bind(&path).await.unwrap_err();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+    }
+
+    #[tokio::test]
+    async fn relay_round_trips_through_daemon() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = start_daemon(dir.path()).await;
+        let stream = UnixStream::connect(&path).await.unwrap();
+
+        let input: &[u8] = b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"tools/list\"}\n";
+        let (output, mut output_reader) = tokio::io::duplex(4096);
+        relay(input, output, stream).await.unwrap();
+
+        let mut relayed = String::new();
+        output_reader.read_to_string(&mut relayed).await.unwrap();
+        let reply: Value = serde_json::from_str(relayed.trim()).unwrap();
+        assert_eq!(reply["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_daemon_and_closes_connections() {
+        let dir = tempfile::tempdir().unwrap();
+        let (trigger, shutdown) = crate::shutdown::channel(Duration::from_secs(5));
+        let (path, daemon) = start_daemon_with_shutdown(dir.path(), shutdown).await;
+
+        let mut client =
+            UnixTransport::unix(UnixStream::connect(&path).await.unwrap(), Framing::Ndjson);
+        client
+            .write_message(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)
+            .await
+            .unwrap();
+        client.read_message().await.unwrap().unwrap();
+
+        trigger.trigger(crate::shutdown::ShutdownReason::Terminate);
+        let drained = tokio::time::timeout(Duration::from_secs(5), daemon)
+            .await
+            .expect("daemon stops")
+            .unwrap();
+        assert!(drained);
+        // The idle connection was closed by the daemon
+        assert_eq!(client.read_message().await.unwrap(), None);
+    }
+}
@@ -1,42 +1,163 @@
-//! Stdio transport layer for JSON-RPC communication
+//! Transport layer for JSON-RPC communication
 //!
-//! This module provides an async stdio transport for reading JSON-RPC requests from stdin
-//! and writing JSON-RPC responses to stdout. It's designed for use with MCP (Model Context
-//! Protocol) clients that communicate over stdio, such as the Zed editor.
+//! This module defines the [`Transport`] trait - a bidirectional channel of JSON-RPC
+//! messages - and [`StreamTransport`], its implementation over any buffered async
+//! reader and async writer pair. The server loop in `main` is generic over
+//! [`Transport`], so the same dispatcher can run on:
+//!
+//! - [`StdioTransport`] - process stdin/stdout, used by MCP clients such as the Zed editor
+//! - [`UnixTransport`] - a connected Unix domain socket
+//! - [`DuplexTransport`] - an in-memory [`tokio::io::duplex`] pipe, for embedding the
+//!   server in tests or other Rust programs
 //!
 //! ## Message Format
 //!
-//! - Messages are newline-delimited JSON (one JSON-RPC message per line)
-//! - Empty lines are ignored
-//! - EOF on stdin signals connection closure
+//! Two framings are supported (see [`Framing`]):
+//!
+//! - **Newline-delimited JSON** - one JSON-RPC message per line; empty lines are ignored
+//! - **`Content-Length` headers** - LSP-style header block terminated by an empty line,
+//!   followed by exactly that many bytes of message body. Other headers (such as
+//!   `Content-Type`) are accepted and ignored.
+//!
+//! By default the framing is auto-detected from the first bytes of input: input that
+//! starts like a `Content-*` header selects header framing, anything else (including
+//! JSON values) selects newline-delimited JSON. Responses are written in the same
+//! framing. EOF on the reader signals connection closure.
+//!
+//! ## Malformed Input
+//!
+//! Messages are bounded by a configurable maximum size (default
+//! [`DEFAULT_MAX_MESSAGE_SIZE`]), so a huge or never-terminated line cannot grow memory
+//! without limit. An oversized message is discarded up to its end (the next newline,
+//! or the end of the `Content-Length` body) and reported as a [`FrameError`], as are
+//! messages that are not valid UTF-8 and malformed header blocks. A malformed header
+//! block is still read up to its terminating empty line, and its body is discarded
+//! when the block carries a valid `Content-Length`. After a [`FrameError`] the
+//! transport is therefore positioned at the next message (unless a header block had
+//! no usable `Content-Length`, leaving the end of its body unknown), so callers can
+//! answer with a JSON-RPC error and keep reading; any other error is an I/O failure.
 //!
 //! ## Logging
 //!
 //! All logging goes to stderr (not stdout) to avoid interfering with JSON-RPC messages.
 //! Long messages are truncated to [`DEBUG_MESSAGE_MAX_LEN`] characters in debug logs.
-//!
-//! ## Test Coverage Note
-//!
-//! Coverage for this module is lower (~56%) because `read_message` and `write_message`
-//! are tightly coupled to real stdin/stdout types, making them difficult to unit test.
-//! They are tested through integration tests and manual testing with the actual binary.
 
 use anyhow::{Context as _, Result};
-use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
-use tracing::debug;
+use clap::ValueEnum;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt as _, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _,
+    BufReader, DuplexStream, ReadHalf, WriteHalf,
+};
+use tracing::{debug, warn};
 
 /// The maximum length of messages (in bytes) to display in debug logs.
 /// Messages longer than this will be truncated for readability.
 const DEBUG_MESSAGE_MAX_LEN: usize = 80_usize;
 
-/// Stdio-based transport for JSON-RPC communication
-pub struct StdioTransport {
-    /// A buffered reader for asynchronous input from `stdin`.
-    stdin: BufReader<tokio::io::Stdin>,
-    /// An asynchronous writer for output to `stdout`.
-    stdout: tokio::io::Stdout,
+/// Default maximum size of one incoming message (16 MB)
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16_000_000_usize;
+
+/// Maximum length of a single `Content-Length` header line (bytes)
+const MAX_HEADER_LINE_LEN: usize = 1024_usize;
+
+/// Name of the header carrying the frame body length (compared case-insensitively)
+const CONTENT_LENGTH_HEADER: &str = "content-length";
+
+/// Message framing used on a transport
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Framing {
+    /// Newline-delimited JSON (one message per line)
+    Ndjson,
+    /// LSP-style `Content-Length:` headers followed by the message body
+    ContentLength,
+    /// Detect from the first bytes of input
+    #[default]
+    Auto,
+}
+
+/// A problem with one incoming message that the transport has already skipped past
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// The message exceeded the maximum message size.
+    TooLarge {
+        /// Configured maximum message size (bytes).
+        limit: usize,
+    },
+    /// The message was not valid UTF-8.
+    InvalidUtf8,
+    /// A `Content-Length` header block was malformed.
+    MalformedHeader(String),
+}
+
+impl core::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooLarge { limit } => {
+                write!(f, "Message exceeds the {limit} byte limit")
+            }
+            Self::InvalidUtf8 => f.write_str("Message is not valid UTF-8"),
+            Self::MalformedHeader(detail) => write!(f, "Malformed frame header: {detail}"),
+        }
+    }
 }
 
+impl core::error::Error for FrameError {}
+
+/// A bidirectional channel carrying one JSON-RPC message at a time
+///
+/// Futures are `Send` so transports can be driven from spawned tasks.
+pub trait Transport {
+    /// Read the next message
+    ///
+    /// # Returns
+    /// - `Ok(Some(message))` - Successfully read a non-empty message
+    /// - `Ok(None)` - The peer closed the connection (EOF)
+    ///
+    /// # Errors
+    /// Returns a [`FrameError`] if one message was malformed or too large and has been
+    /// skipped, or another error if reading fails
+    fn read_message(&mut self) -> impl Future<Output = Result<Option<String>>> + Send;
+
+    /// Write one message and flush it
+    ///
+    /// # Arguments
+    /// * `message` - Message to write (framing will be added)
+    ///
+    /// # Errors
+    /// Returns an error if writing or flushing fails
+    fn write_message(&mut self, message: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Flush pending output and shut down the write side
+    ///
+    /// # Errors
+    /// Returns an error if flushing or shutting down fails
+    fn close(&mut self) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Transport over any buffered async reader and async writer pair
+pub struct StreamTransport<R, W> {
+    /// Buffered source of incoming frames.
+    reader: R,
+    /// Sink for outgoing frames.
+    writer: W,
+    /// Framing in use; `Auto` until the first bytes have been read.
+    framing: Framing,
+    /// Largest accepted message (bytes).
+    max_message_size: usize,
+}
+
+/// Transport over process stdin/stdout
+pub type StdioTransport = StreamTransport<BufReader<tokio::io::Stdin>, tokio::io::Stdout>;
+
+/// Transport over a connected Unix domain socket
+#[cfg(unix)]
+pub type UnixTransport =
+    StreamTransport<BufReader<tokio::net::unix::OwnedReadHalf>, tokio::net::unix::OwnedWriteHalf>;
+
+/// Transport over one end of an in-memory [`tokio::io::duplex`] pipe
+pub type DuplexTransport =
+    StreamTransport<BufReader<ReadHalf<DuplexStream>>, WriteHalf<DuplexStream>>;
+
 impl Default for StdioTransport {
     fn default() -> Self {
         Self::new()
@@ -44,18 +165,99 @@ impl Default for StdioTransport {
 }
 
 impl StdioTransport {
-    /// Create a new stdio transport
+    /// Create a new stdio transport that auto-detects its framing
     ///
     /// # Returns
     /// New `StdioTransport` instance connected to process stdin/stdout
     #[must_use]
     pub fn new() -> Self {
+        Self::with_framing(Framing::Auto)
+    }
+
+    /// Create a new stdio transport with an explicit framing
+    ///
+    /// # Arguments
+    /// * `framing` - Framing to use, or `Framing::Auto` to detect it from input
+    ///
+    /// # Returns
+    /// New `StdioTransport` instance connected to process stdin/stdout
+    #[must_use]
+    pub fn with_framing(framing: Framing) -> Self {
+        Self::from_parts(
+            BufReader::new(tokio::io::stdin()),
+            tokio::io::stdout(),
+            framing,
+        )
+    }
+}
+
+#[cfg(unix)]
+impl UnixTransport {
+    /// Create a transport over a connected Unix domain socket
+    ///
+    /// # Arguments
+    /// * `stream` - Connected socket
+    /// * `framing` - Framing to use, or `Framing::Auto` to detect it from input
+    ///
+    /// # Returns
+    /// New `UnixTransport` owning both halves of the socket
+    #[must_use]
+    pub fn unix(stream: tokio::net::UnixStream, framing: Framing) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self::from_parts(BufReader::new(reader), writer, framing)
+    }
+}
+
+impl DuplexTransport {
+    /// Create a transport over one end of an in-memory duplex pipe
+    ///
+    /// # Arguments
+    /// * `stream` - One end of a [`tokio::io::duplex`] pair
+    /// * `framing` - Framing to use, or `Framing::Auto` to detect it from input
+    ///
+    /// # Returns
+    /// New `DuplexTransport` owning both halves of the pipe end
+    #[must_use]
+    #[allow(dead_code, reason = "used to embed the server over in-memory pipes")]
+    pub fn duplex(stream: DuplexStream, framing: Framing) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Self::from_parts(BufReader::new(reader), writer, framing)
+    }
+}
+
+impl<R, W> StreamTransport<R, W> {
+    /// Create a transport from a buffered reader and a writer
+    ///
+    /// # Arguments
+    /// * `reader` - Buffered source of incoming frames
+    /// * `writer` - Sink for outgoing frames
+    /// * `framing` - Framing to use, or `Framing::Auto` to detect it from input
+    ///
+    /// # Returns
+    /// New `StreamTransport` over the given pair
+    #[must_use]
+    pub const fn from_parts(reader: R, writer: W, framing: Framing) -> Self {
         Self {
-            stdin: BufReader::new(tokio::io::stdin()),
-            stdout: tokio::io::stdout(),
+            reader,
+            writer,
+            framing,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
         }
     }
 
+    /// Set the largest accepted message size
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum message size in bytes
+    ///
+    /// # Returns
+    /// The transport with the new limit
+    #[must_use]
+    pub const fn with_max_message_size(mut self, limit: usize) -> Self {
+        self.max_message_size = limit;
+        self
+    }
+
     /// Truncates a string to `DEBUG_MESSAGE_MAX_LEN` bytes, ensuring that the truncation
     /// occurs on a UTF-8 character boundary to prevent invalid UTF-8 sequences.
     ///
@@ -81,68 +283,335 @@ impl StdioTransport {
         }
         &message[..last_valid]
     }
+}
 
-    /// Read a message from stdin
-    ///
-    /// Reads one line from stdin. Empty lines are skipped.
-    ///
-    /// # Returns
-    /// - `Ok(Some(message))` - Successfully read a non-empty message
-    /// - `Ok(None)` - Empty line or EOF
-    ///
-    /// # Errors
-    /// Returns an error if reading from stdin fails
-    pub async fn read_message(&mut self) -> Result<Option<String>> {
-        let mut line = String::new();
-        let bytes_read = self
-            .stdin
-            .read_line(&mut line)
+impl<R, W> Transport for StreamTransport<R, W>
+where
+    R: AsyncBufRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    /// Reads one frame in the configured framing, detecting it first if needed.
+    /// Empty lines between newline-delimited messages are skipped.
+    async fn read_message(&mut self) -> Result<Option<String>> {
+        let framing = match self.framing {
+            Framing::Auto => {
+                let Some(detected) = detect_framing(&mut self.reader).await? else {
+                    debug!("EOF on input");
+                    return Ok(None);
+                };
+                debug!("Detected {:?} framing", detected);
+                self.framing = detected;
+                detected
+            }
+            framing => framing,
+        };
+
+        let message = if framing == Framing::ContentLength {
+            read_content_length_frame(&mut self.reader, self.max_message_size).await?
+        } else {
+            read_line_frame(&mut self.reader, self.max_message_size).await?
+        };
+
+        if let Some(line) = &message {
+            debug!("Read message: {}...", Self::truncate_for_debug(line));
+        } else {
+            debug!("EOF on input");
+        }
+        Ok(message)
+    }
+
+    /// Writes the message in the transport's framing, then flushes. Until the
+    /// framing has been detected, newline-delimited JSON is used.
+    async fn write_message(&mut self, message: &str) -> Result<()> {
+        debug!("Writing message: {}...", Self::truncate_for_debug(message));
+        write_frame(&mut self.writer, self.framing, message).await
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.writer
+            .shutdown()
+            .await
+            .context("Failed to shut down output")
+    }
+}
+
+/// Detects the framing from the first non-whitespace byte of input.
+///
+/// Leading whitespace is consumed; nothing else is.
+///
+/// # Returns
+/// `Ok(None)` on EOF, otherwise `Framing::ContentLength` for input that starts like a
+/// `Content-*` header and `Framing::Ndjson` for anything else (so garbage input
+/// still produces a JSON-RPC parse error).
+///
+/// # Errors
+/// Returns an error if reading fails
+async fn detect_framing<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Framing>> {
+    loop {
+        let buffer = reader.fill_buf().await.context("Failed to read input")?;
+        let Some(&first) = buffer.first() else {
+            return Ok(None);
+        };
+        if first.is_ascii_whitespace() {
+            reader.consume(1_usize);
+            continue;
+        }
+        return Ok(Some(if first.eq_ignore_ascii_case(&b'c') {
+            Framing::ContentLength
+        } else {
+            Framing::Ndjson
+        }));
+    }
+}
+
+/// Reads one newline-delimited message, skipping empty lines.
+///
+/// Bytes are buffered only up to `max_size`; the rest of an oversized line is
+/// discarded up to and including its newline.
+///
+/// # Returns
+/// `Ok(None)` on EOF, otherwise the trimmed line
+///
+/// # Errors
+/// Returns [`FrameError::TooLarge`] or [`FrameError::InvalidUtf8`] for a rejected
+/// line, or another error if reading fails
+async fn read_line_frame<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+) -> Result<Option<String>> {
+    // Room for a trailing "\r\n" that trimming removes
+    let buffer_limit = max_size.saturating_add(2_usize);
+
+    loop {
+        let mut line = Vec::new();
+        let mut oversized = false;
+        let mut saw_bytes = false;
+
+        loop {
+            let buffer = reader.fill_buf().await.context("Failed to read input")?;
+            if buffer.is_empty() {
+                break;
+            }
+            saw_bytes = true;
+            let newline = buffer.iter().position(|&byte| byte == b'\n');
+            let used = newline.map_or(buffer.len(), |index| index + 1_usize);
+            if !oversized {
+                line.extend_from_slice(buffer.get(..used).unwrap_or_default());
+                if line.len() > buffer_limit {
+                    oversized = true;
+                    line = Vec::new();
+                }
+            }
+            reader.consume(used);
+            if newline.is_some() {
+                break;
+            }
+        }
+
+        if !saw_bytes {
+            return Ok(None);
+        }
+        if oversized {
+            return Err(FrameError::TooLarge { limit: max_size }.into());
+        }
+
+        let line = String::from_utf8(line).map_err(|_| FrameError::InvalidUtf8)?;
+        let line = line.trim();
+        if line.len() > max_size {
+            return Err(FrameError::TooLarge { limit: max_size }.into());
+        }
+        if !line.is_empty() {
+            return Ok(Some(line.to_owned()));
+        }
+    }
+}
+
+/// Reads one `Content-Length` framed message.
+///
+/// Header names are case-insensitive and unknown headers are ignored. A frame whose
+/// length exceeds `max_size` is read and discarded so the next frame can still be
+/// parsed. A malformed header block is read up to its empty line (the rest of an
+/// overlong line is discarded through its newline) and, if it carries a valid
+/// `Content-Length`, its body is discarded too.
+///
+/// # Returns
+/// `Ok(None)` on EOF before a header block starts, otherwise the message body
+///
+/// # Errors
+/// Returns a [`FrameError`] if the header block is malformed or lacks
+/// `Content-Length`, the frame is too large, or the body is not valid UTF-8; returns
+/// another error if reading fails or EOF occurs mid-frame
+async fn read_content_length_frame<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+) -> Result<Option<String>> {
+    let mut content_length: Option<usize> = None;
+    let mut malformed: Option<FrameError> = None;
+    let mut saw_header = false;
+
+    loop {
+        let mut line = Vec::new();
+        let bytes_read = (&mut *reader)
+            .take(MAX_HEADER_LINE_LEN as u64)
+            .read_until(b'\n', &mut line)
             .await
-            .context("Failed to read from stdin")?;
+            .context("Failed to read frame header")?;
 
         if bytes_read == 0_usize {
-            debug!("EOF on stdin");
+            if saw_header {
+                anyhow::bail!("Unexpected EOF in frame header");
+            }
             return Ok(None);
         }
+        if line.last() != Some(&b'\n') && bytes_read == MAX_HEADER_LINE_LEN {
+            saw_header = true;
+            malformed.get_or_insert_with(|| {
+                FrameError::MalformedHeader(format!("line exceeds {MAX_HEADER_LINE_LEN} bytes"))
+            });
+            if !skip_line(reader).await? {
+                anyhow::bail!("Unexpected EOF in frame header");
+            }
+            continue;
+        }
 
+        let Ok(line) = core::str::from_utf8(&line) else {
+            saw_header = true;
+            malformed
+                .get_or_insert_with(|| FrameError::MalformedHeader("not valid UTF-8".to_owned()));
+            continue;
+        };
         let line = line.trim();
         if line.is_empty() {
-            return Ok(None);
+            if saw_header {
+                break;
+            }
+            // Tolerate blank lines between frames
+            continue;
         }
+        saw_header = true;
 
-        debug!("Read message: {}...", Self::truncate_for_debug(line));
-        Ok(Some(line.to_owned()))
+        let Some((name, value)) = line.split_once(':') else {
+            malformed.get_or_insert_with(|| FrameError::MalformedHeader(line.to_owned()));
+            continue;
+        };
+        if name.trim().eq_ignore_ascii_case(CONTENT_LENGTH_HEADER) {
+            match value.trim().parse() {
+                Ok(length) => content_length = Some(length),
+                Err(_) => {
+                    malformed.get_or_insert_with(|| {
+                        FrameError::MalformedHeader(format!(
+                            "invalid Content-Length {}",
+                            value.trim()
+                        ))
+                    });
+                }
+            }
+        }
     }
 
-    /// Write a message to stdout
-    ///
-    /// Writes the message followed by a newline, then flushes stdout.
-    ///
-    /// # Arguments
-    /// * `message` - Message to write (newline will be added)
-    ///
-    /// # Errors
-    /// Returns an error if writing to or flushing stdout fails
-    pub async fn write_message(&mut self, message: &str) -> Result<()> {
-        debug!("Writing message: {}...", Self::truncate_for_debug(message));
+    if let Some(error) = malformed {
+        if let Some(length) = content_length {
+            skip_body(reader, length).await?;
+        }
+        return Err(error.into());
+    }
+    let Some(length) = content_length else {
+        return Err(FrameError::MalformedHeader("missing Content-Length".to_owned()).into());
+    };
 
-        self.stdout
-            .write_all(message.as_bytes())
-            .await
-            .context("Failed to write to stdout")?;
+    if length > max_size {
+        warn!("Skipping {} byte frame (limit {} bytes)", length, max_size);
+        skip_body(reader, length).await?;
+        return Err(FrameError::TooLarge { limit: max_size }.into());
+    }
 
-        self.stdout
-            .write_all(b"\n")
-            .await
-            .context("Failed to write newline to stdout")?;
+    let mut body = vec![0_u8; length];
+    reader
+        .read_exact(&mut body)
+        .await
+        .context("Unexpected EOF in frame body")?;
 
-        self.stdout
-            .flush()
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| FrameError::InvalidUtf8.into())
+}
+
+/// Discards input up to and including the next newline.
+///
+/// # Returns
+/// `false` if EOF was reached before a newline
+///
+/// # Errors
+/// Returns an error if reading fails
+async fn skip_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<bool> {
+    loop {
+        let buffer = reader
+            .fill_buf()
             .await
-            .context("Failed to flush stdout")?;
+            .context("Failed to read frame header")?;
+        if buffer.is_empty() {
+            return Ok(false);
+        }
+        let newline = buffer.iter().position(|&byte| byte == b'\n');
+        let used = newline.map_or(buffer.len(), |index| index + 1_usize);
+        reader.consume(used);
+        if newline.is_some() {
+            return Ok(true);
+        }
+    }
+}
 
-        Ok(())
+/// Discards a frame body of `length` bytes.
+///
+/// # Errors
+/// Returns an error if reading fails or EOF occurs before the end of the body
+async fn skip_body<R: AsyncBufRead + Unpin>(reader: &mut R, length: usize) -> Result<()> {
+    let skipped = tokio::io::copy(
+        &mut (&mut *reader).take(length as u64),
+        &mut tokio::io::sink(),
+    )
+    .await
+    .context("Failed to skip frame body")?;
+    if skipped < length as u64 {
+        anyhow::bail!("Unexpected EOF in frame body");
     }
+    Ok(())
+}
+
+/// Writes one message in the given framing and flushes the writer.
+///
+/// `Framing::Auto` (framing not yet detected) writes newline-delimited JSON.
+///
+/// # Errors
+/// Returns an error if writing or flushing fails
+async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    framing: Framing,
+    message: &str,
+) -> Result<()> {
+    if framing == Framing::ContentLength {
+        let header = format!("Content-Length: {}\r\n\r\n", message.len());
+        writer
+            .write_all(header.as_bytes())
+            .await
+            .context("Failed to write frame header")?;
+        writer
+            .write_all(message.as_bytes())
+            .await
+            .context("Failed to write frame body")?;
+    } else {
+        writer
+            .write_all(message.as_bytes())
+            .await
+            .context("Failed to write message")?;
+        writer
+            .write_all(b"\n")
+            .await
+            .context("Failed to write newline")?;
+    }
+
+    writer.flush().await.context("Failed to flush output")?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -254,4 +723,374 @@ mod tests {
         assert_eq!(short_min, 4_usize);
         assert_eq!(long_min, 80_usize);
     }
+
+    #[tokio::test]
+    async fn detects_ndjson_framing() {
+        let mut input: &[u8] = b"  \n{\"id\":1}\n";
+        let framing = detect_framing(&mut input).await.unwrap();
+        assert_eq!(framing, Some(Framing::Ndjson));
+        assert_eq!(input, b"{\"id\":1}\n");
+    }
+
+    #[tokio::test]
+    async fn detects_content_length_framing() {
+        let mut input: &[u8] = b"Content-Length: 2\r\n\r\n{}";
+        let framing = detect_framing(&mut input).await.unwrap();
+        assert_eq!(framing, Some(Framing::ContentLength));
+    }
+
+    #[tokio::test]
+    async fn detects_garbage_as_ndjson() {
+        let mut input: &[u8] = b"not valid json\n";
+        let framing = detect_framing(&mut input).await.unwrap();
+        assert_eq!(framing, Some(Framing::Ndjson));
+    }
+
+    #[tokio::test]
+    async fn detect_framing_eof() {
+        let mut input: &[u8] = b"   \n";
+        assert_eq!(detect_framing(&mut input).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn line_frame_skips_empty_lines() {
+        let mut input: &[u8] = b"\n  \n{\"id\":1}\r\n{\"id\":2}";
+        assert_eq!(
+            read_line_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"id":1}"#)
+        );
+        assert_eq!(
+            read_line_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"id":2}"#)
+        );
+        assert_eq!(
+            read_line_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn line_frame_oversized_is_skipped() {
+        let mut input: &[u8] = b"{\"padding\":\"0123456789\"}\n{\"id\":1}\n";
+        let error = read_line_frame(&mut input, 10_usize).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FrameError>(),
+            Some(&FrameError::TooLarge { limit: 10_usize })
+        );
+        assert_eq!(
+            read_line_frame(&mut input, 10_usize)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"id":1}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn line_frame_oversized_across_buffer_refills() {
+        let mut data = vec![b'x'; 100_usize];
+        data.extend_from_slice(b"\n{}\n");
+        // A tiny buffer makes the oversized line arrive in many pieces
+        let mut reader = BufReader::with_capacity(4_usize, data.as_slice());
+        read_line_frame(&mut reader, 16_usize).await.unwrap_err();
+        assert_eq!(
+            read_line_frame(&mut reader, 16_usize)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("{}")
+        );
+    }
+
+    #[tokio::test]
+    async fn line_frame_unterminated_oversized_then_eof() {
+        let mut input: &[u8] = &[b'x'; 64_usize];
+        read_line_frame(&mut input, 16_usize).await.unwrap_err();
+        assert_eq!(read_line_frame(&mut input, 16_usize).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn line_frame_exact_limit_with_crlf_is_accepted() {
+        let mut input: &[u8] = b"{\"id\":1}\r\n";
+        assert_eq!(
+            read_line_frame(&mut input, 8_usize)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"id":1}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn line_frame_invalid_utf8_is_skipped() {
+        let mut input: &[u8] = b"\xff\xfe\n{}\n";
+        let error = read_line_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FrameError>(),
+            Some(&FrameError::InvalidUtf8)
+        );
+        assert_eq!(
+            read_line_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("{}")
+        );
+    }
+
+    #[tokio::test]
+    async fn content_length_invalid_utf8_body_is_skipped() {
+        let mut input: &[u8] = b"Content-Length: 2\r\n\r\n\xff\xfeContent-Length: 2\r\n\r\n{}";
+        let error = read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FrameError>(),
+            Some(&FrameError::InvalidUtf8)
+        );
+        assert_eq!(
+            read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("{}")
+        );
+    }
+
+    #[tokio::test]
+    async fn content_length_frames_back_to_back() {
+        let mut input: &[u8] =
+            b"Content-Length: 8\r\n\r\n{\"id\":1}Content-Length: 8\r\n\r\n{\"id\":2}";
+        assert_eq!(
+            read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"id":1}"#)
+        );
+        assert_eq!(
+            read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"id":2}"#)
+        );
+        assert_eq!(
+            read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn content_length_multiple_headers_any_case() {
+        let mut input: &[u8] = b"content-type: application/vscode-jsonrpc; charset=utf-8\r\ncontent-length: 2\r\n\r\n{}";
+        assert_eq!(
+            read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("{}")
+        );
+    }
+
+    #[tokio::test]
+    async fn content_length_partial_reads() {
+        let data: &[u8] = b"Content-Length: 11\r\n\r\n{\"id\":\"\xc3\xa9\"}";
+        // A one-byte buffer forces every header line and the body to arrive in pieces
+        let mut reader = BufReader::with_capacity(1_usize, data);
+        assert_eq!(
+            read_content_length_frame(&mut reader, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("{\"id\":\"\u{e9}\"}")
+        );
+    }
+
+    #[tokio::test]
+    async fn content_length_oversized_frame_is_skipped() {
+        let oversized = DEFAULT_MAX_MESSAGE_SIZE + 1_usize;
+        let mut data = format!("Content-Length: {oversized}\r\n\r\n").into_bytes();
+        data.resize(data.len() + oversized, b' ');
+        data.extend_from_slice(b"Content-Length: 2\r\n\r\n{}");
+        let mut input: &[u8] = &data;
+
+        let error = read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FrameError>(),
+            Some(&FrameError::TooLarge {
+                limit: DEFAULT_MAX_MESSAGE_SIZE
+            })
+        );
+        assert_eq!(
+            read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("{}")
+        );
+    }
+
+    #[tokio::test]
+    async fn content_length_missing_header_errors() {
+        let mut input: &[u8] = b"Content-Type: text/plain\r\n\r\n{}";
+        read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn content_length_invalid_value_errors() {
+        let mut input: &[u8] = b"Content-Length: lots\r\n\r\n{}";
+        read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+            .await
+            .unwrap_err();
+    }
+
+    /// Reads a malformed frame followed by a valid one, expecting a
+    /// [`FrameError::MalformedHeader`] and then the valid frame.
+    async fn assert_resyncs_after(malformed: &[u8]) {
+        let mut data = malformed.to_vec();
+        data.extend_from_slice(b"Content-Length: 8\r\n\r\n{\"id\":2}");
+        // A small buffer makes skipped lines and bodies span several reads
+        let mut reader = BufReader::with_capacity(7_usize, data.as_slice());
+
+        let error = read_content_length_frame(&mut reader, DEFAULT_MAX_MESSAGE_SIZE)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                error.downcast_ref::<FrameError>(),
+                Some(FrameError::MalformedHeader(_))
+            ),
+            "{error:#}"
+        );
+        assert_eq!(
+            read_content_length_frame(&mut reader, DEFAULT_MAX_MESSAGE_SIZE)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"id":2}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn content_length_header_without_colon_is_skipped_with_body() {
+        assert_resyncs_after(b"Bogus header\r\nContent-Length: 8\r\n\r\n{\"id\":1}").await;
+    }
+
+    #[tokio::test]
+    async fn content_length_invalid_value_is_skipped() {
+        assert_resyncs_after(b"Content-Length: lots\r\n\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn content_length_missing_header_is_skipped() {
+        assert_resyncs_after(b"Content-Type: text/plain\r\n\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn content_length_overlong_header_line_is_skipped_with_body() {
+        let mut data =
+            format!("X-Padding: {}\r\n", "x".repeat(MAX_HEADER_LINE_LEN * 3)).into_bytes();
+        data.extend_from_slice(b"Content-Length: 8\r\n\r\n{\"id\":1}");
+        assert_resyncs_after(&data).await;
+    }
+
+    #[tokio::test]
+    async fn content_length_invalid_utf8_header_is_skipped_with_body() {
+        assert_resyncs_after(b"X-Name: \xff\r\nContent-Length: 8\r\n\r\n{\"id\":1}").await;
+    }
+
+    #[tokio::test]
+    async fn content_length_truncated_body_errors() {
+        let mut input: &[u8] = b"Content-Length: 10\r\n\r\n{}";
+        read_content_length_frame(&mut input, DEFAULT_MAX_MESSAGE_SIZE)
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn write_content_length_frame() {
+        let mut output = Vec::new();
+        write_frame(&mut output, Framing::ContentLength, "{\"id\":\"\u{e9}\"}")
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            b"Content-Length: 11\r\n\r\n{\"id\":\"\xc3\xa9\"}".to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn write_ndjson_frame_before_detection() {
+        let mut output = Vec::new();
+        write_frame(&mut output, Framing::Auto, "{}").await.unwrap();
+        assert_eq!(output, b"{}\n".to_vec());
+    }
+
+    #[tokio::test]
+    async fn duplex_transport_roundtrip() {
+        let (near, far) = tokio::io::duplex(64_usize);
+        let mut server = DuplexTransport::duplex(near, Framing::Auto);
+        let mut client = DuplexTransport::duplex(far, Framing::Ndjson);
+
+        client.write_message(r#"{"id":1}"#).await.unwrap();
+        assert_eq!(
+            server.read_message().await.unwrap().as_deref(),
+            Some(r#"{"id":1}"#)
+        );
+
+        server
+            .write_message(r#"{"id":1,"result":{}}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            client.read_message().await.unwrap().as_deref(),
+            Some(r#"{"id":1,"result":{}}"#)
+        );
+
+        client.close().await.unwrap();
+        assert_eq!(server.read_message().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn stream_transport_replies_in_detected_framing() {
+        let input: &[u8] = b"Content-Length: 2\r\n\r\n{}";
+        let mut transport = StreamTransport::from_parts(input, Vec::new(), Framing::Auto);
+
+        assert_eq!(
+            transport.read_message().await.unwrap().as_deref(),
+            Some("{}")
+        );
+        transport.write_message("[]").await.unwrap();
+        assert_eq!(transport.writer, b"Content-Length: 2\r\n\r\n[]".to_vec());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_transport_roundtrip() {
+        let (near, far) = tokio::net::UnixStream::pair().unwrap();
+        let mut server = UnixTransport::unix(near, Framing::Ndjson);
+        let mut client = UnixTransport::unix(far, Framing::Ndjson);
+
+        client.write_message("{}").await.unwrap();
+        assert_eq!(server.read_message().await.unwrap().as_deref(), Some("{}"));
+        client.close().await.unwrap();
+        assert_eq!(server.read_message().await.unwrap(), None);
+    }
 }
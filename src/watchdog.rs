@@ -0,0 +1,259 @@
+//! Watchdogs that shut down a proxy nobody is using anymore.
+//!
+//! - **Parent watchdog** (`--watch-parent`, Unix only) - editors sometimes crash without
+//!   closing our stdin. The watchdog remembers the parent PID at startup and polls
+//!   `getppid`; once the parent exits the process is re-parented, the PID changes and
+//!   shutdown starts with [`ShutdownReason::ParentExited`].
+//! - **Idle watchdog** (`--idle-timeout SECS`) - every server loop marks requests as
+//!   busy through [`Activity`]; when no request has been in flight for the timeout,
+//!   shutdown starts with [`ShutdownReason::IdleTimeout`].
+//!
+//! Both watchdogs stop once shutdown has started for any other reason.
+
+use crate::shutdown::{Shutdown, ShutdownReason, ShutdownTrigger};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+#[cfg(unix)]
+use tracing::warn;
+
+/// Interval between parent PID checks (seconds)
+#[cfg(unix)]
+const PARENT_POLL_INTERVAL_SECS: u64 = 2_u64;
+
+/// Tracks requests in flight and when the last one finished
+#[derive(Clone)]
+pub struct Activity {
+    /// State shared by every clone.
+    inner: Arc<ActivityState>,
+}
+
+/// Shared state behind [`Activity`].
+struct ActivityState {
+    /// Number of requests currently being handled.
+    in_flight: AtomicUsize,
+    /// When a request last started or finished.
+    last: Mutex<Instant>,
+}
+
+/// Marks one request as in flight until dropped
+pub struct Busy {
+    /// Tracker to update when the request finishes.
+    activity: Activity,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(ActivityState {
+                in_flight: AtomicUsize::new(0_usize),
+                last: Mutex::new(Instant::now()),
+            }),
+        }
+    }
+}
+
+impl Activity {
+    /// Marks a request as in flight until the returned guard is dropped.
+    pub fn begin(&self) -> Busy {
+        self.inner.in_flight.fetch_add(1_usize, Ordering::SeqCst);
+        self.touch();
+        Busy {
+            activity: self.clone(),
+        }
+    }
+
+    /// Returns how long the proxy has been idle, or `None` while a request is in flight.
+    pub fn idle_for(&self) -> Option<Duration> {
+        if self.inner.in_flight.load(Ordering::SeqCst) > 0_usize {
+            return None;
+        }
+        Some(
+            self.inner
+                .last
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .elapsed(),
+        )
+    }
+
+    /// Records activity now.
+    fn touch(&self) {
+        *self
+            .inner
+            .last
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Instant::now();
+    }
+}
+
+impl Drop for Busy {
+    fn drop(&mut self) {
+        self.activity.touch();
+        self.activity
+            .inner
+            .in_flight
+            .fetch_sub(1_usize, Ordering::SeqCst);
+    }
+}
+
+/// Starts shutdown once no request has been in flight for `timeout`.
+///
+/// # Arguments
+/// * `timeout` - Idle time after which the proxy shuts down
+/// * `trigger` - Trigger fired on timeout
+/// * `shutdown` - Handle whose [`Activity`] is watched; stops the watchdog once triggered
+pub fn spawn_idle_watchdog(timeout: Duration, trigger: ShutdownTrigger, mut shutdown: Shutdown) {
+    let activity = shutdown.activity().clone();
+    tokio::spawn(async move {
+        loop {
+            let wait = match activity.idle_for() {
+                Some(idle) if idle >= timeout => {
+                    if trigger.trigger(ShutdownReason::IdleTimeout) {
+                        info!("No traffic for {:?}, shutting down", timeout);
+                    }
+                    return;
+                }
+                Some(idle) => timeout.saturating_sub(idle),
+                None => timeout,
+            };
+            tokio::select! {
+                _ = shutdown.triggered() => return,
+                () = tokio::time::sleep(wait) => {}
+            }
+        }
+    });
+}
+
+/// Starts shutdown once the parent process that launched the proxy has exited.
+///
+/// Does nothing if the parent is already `init` (PID 1), since there is no
+/// launching process left to watch.
+///
+/// # Arguments
+/// * `trigger` - Trigger fired when the parent exits
+/// * `shutdown` - Handle that stops the watchdog once triggered
+#[cfg(unix)]
+pub fn spawn_parent_watchdog(trigger: ShutdownTrigger, shutdown: Shutdown) {
+    let parent = std::os::unix::process::parent_id();
+    if parent == 1_u32 {
+        warn!("Parent process is init; --watch-parent has nothing to watch");
+        return;
+    }
+    info!("Watching parent process {}", parent);
+    tokio::spawn(watch_parent(
+        parent,
+        std::os::unix::process::parent_id,
+        Duration::from_secs(PARENT_POLL_INTERVAL_SECS),
+        trigger,
+        shutdown,
+    ));
+}
+
+/// Starts shutdown once the parent process has exited (unsupported on this platform).
+#[cfg(not(unix))]
+pub fn spawn_parent_watchdog(_trigger: ShutdownTrigger, _shutdown: Shutdown) {
+    tracing::warn!("--watch-parent is not supported on this platform");
+}
+
+/// Polls `current_parent` until it differs from `parent`, then fires `trigger`.
+#[cfg(unix)]
+async fn watch_parent(
+    parent: u32,
+    current_parent: impl Fn() -> u32,
+    interval: Duration,
+    trigger: ShutdownTrigger,
+    mut shutdown: Shutdown,
+) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown.triggered() => return,
+            _ = ticks.tick() => {}
+        }
+        if current_parent() != parent {
+            if trigger.trigger(ShutdownReason::ParentExited) {
+                info!("Parent process {} exited, shutting down", parent);
+            }
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, reason = "tests can use expect()")]
+#[allow(clippy::unwrap_used, reason = "tests can use unwrap()")]
+mod tests {
+    use super::*;
+    use crate::shutdown;
+    use std::sync::atomic::AtomicU32;
+
+    #[test]
+    fn busy_guard_suspends_idle_time() {
+        let activity = Activity::default();
+        assert!(activity.idle_for().is_some());
+
+        let busy = activity.begin();
+        let nested = activity.begin();
+        assert_eq!(activity.idle_for(), None);
+        drop(busy);
+        assert_eq!(activity.idle_for(), None);
+        drop(nested);
+        assert!(activity.idle_for().unwrap() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn idle_watchdog_fires_without_traffic() {
+        let (trigger, mut shutdown) = shutdown::channel(Duration::ZERO);
+        spawn_idle_watchdog(Duration::from_millis(50), trigger, shutdown.clone());
+
+        let reason = tokio::time::timeout(Duration::from_secs(5), shutdown.triggered())
+            .await
+            .expect("idle timeout fires");
+        assert_eq!(reason, ShutdownReason::IdleTimeout);
+    }
+
+    #[tokio::test]
+    async fn idle_watchdog_waits_for_in_flight_requests() {
+        let (trigger, mut shutdown) = shutdown::channel(Duration::ZERO);
+        let busy = shutdown.activity().begin();
+        spawn_idle_watchdog(Duration::from_millis(20), trigger, shutdown.clone());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(shutdown.reason(), None);
+
+        drop(busy);
+        let reason = tokio::time::timeout(Duration::from_secs(5), shutdown.triggered())
+            .await
+            .expect("idle timeout fires once the request finished");
+        assert_eq!(reason, ShutdownReason::IdleTimeout);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn parent_watchdog_fires_when_parent_changes() {
+        let (trigger, mut shutdown) = shutdown::channel(Duration::ZERO);
+        let current = Arc::new(AtomicU32::new(4242_u32));
+        let observed = Arc::clone(&current);
+        tokio::spawn(watch_parent(
+            4242_u32,
+            move || observed.load(Ordering::SeqCst),
+            Duration::from_millis(10),
+            trigger,
+            shutdown.clone(),
+        ));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(shutdown.reason(), None);
+
+        // Re-parented to init after the editor died
+        current.store(1_u32, Ordering::SeqCst);
+        let reason = tokio::time::timeout(Duration::from_secs(5), shutdown.triggered())
+            .await
+            .expect("parent exit detected");
+        assert_eq!(reason, ShutdownReason::ParentExited);
+    }
+}
@@ -68,6 +68,47 @@ mod tests {
             .stdout(predicate::str::is_match(r"\d+\.\d+\.\d+").expect("valid regex pattern"));
     }
 
+    #[test]
+    fn listen_conflicts_with_search() {
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.args(["--listen", "127.0.0.1:0", "--search", "Bun.serve"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    #[test]
+    fn socket_conflicts_with_listen() {
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.args(["--socket", "/tmp/proxy.sock", "--listen", "8080"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn connect_to_missing_socket_fails() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("missing.sock");
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.arg("--connect")
+            .arg(&path)
+            .write_stdin("")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Failed to connect"));
+    }
+
+    #[test]
+    fn listen_rejects_invalid_address() {
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.args(["--listen", "not-an-address"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid"));
+    }
+
     #[test]
     fn handles_stdin_eof_cleanly() {
         let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
@@ -102,4 +143,257 @@ mod tests {
         .stdout(predicate::str::contains("protocolVersion"))
         .stdout(predicate::str::contains("2024-11-05"));
     }
+
+    #[test]
+    fn content_length_framing_roundtrip() {
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#;
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.write_stdin(format!(
+            "Content-Length: {}\r\n\r\n{request}",
+            request.len()
+        ))
+        .timeout(Duration::from_secs(2_u64))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Content-Length: "))
+        .stdout(predicate::str::contains("protocolVersion"));
+    }
+
+    #[test]
+    fn oversized_message_gets_invalid_request_error() {
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.args(["--max-message-size", "64"])
+            .write_stdin(format!(
+                "{{\"padding\":\"{}\"}}\n{{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\"}}\n",
+                "x".repeat(100)
+            ))
+            .timeout(Duration::from_secs(2_u64))
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("-32600"))
+            .stdout(predicate::str::contains("protocolVersion"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sigterm_shuts_down_with_status_143() {
+        use std::io::{BufRead as _, BufReader, Write as _};
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new(assert_cmd::cargo::cargo_bin!("bun-docs-mcp-proxy"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("spawn proxy");
+
+        // A response proves the proxy is up and its signal handlers are installed
+        let mut stdin = child.stdin.take().expect("stdin");
+        writeln!(stdin, r#"{{"jsonrpc":"2.0","id":1,"method":"initialize"}}"#).unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().expect("stdout"))
+            .read_line(&mut line)
+            .unwrap();
+        assert!(line.contains("protocolVersion"));
+
+        let status = Command::new("kill")
+            .args(["-TERM", &child.id().to_string()])
+            .status()
+            .expect("run kill");
+        assert!(status.success());
+
+        // stdin stays open: only the signal can end the process
+        let exit = child.wait().expect("proxy exits");
+        drop(stdin);
+        assert_eq!(exit.code(), Some(143));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn idle_timeout_exits_cleanly_with_stdin_open() {
+        use std::process::{Command, Stdio};
+        use std::time::Instant;
+
+        let mut child = Command::new(assert_cmd::cargo::cargo_bin!("bun-docs-mcp-proxy"))
+            .args(["--idle-timeout", "1"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("spawn proxy");
+        let stdin = child.stdin.take();
+
+        let started = Instant::now();
+        let exit = child.wait().expect("proxy exits");
+        drop(stdin);
+        assert_eq!(exit.code(), Some(0));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn idle_timeout_rejects_zero() {
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.args(["--idle-timeout", "0"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid idle timeout"));
+    }
+
+    #[test]
+    fn upstream_url_from_environment() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/mcp")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {"content": [{"type": "text", "text": "from the mirror"}]}}"#)
+            .expect(1)
+            .create();
+
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.env("BUN_DOCS_MCP_URL", format!("{}/mcp", server.url()))
+            .args([
+                "--search",
+                "Bun.serve",
+                "--format",
+                "text",
+                "--no-disk-cache",
+            ])
+            .timeout(Duration::from_secs(10_u64))
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("from the mirror"));
+
+        mock.assert();
+        drop(server);
+    }
+
+    #[test]
+    fn second_search_is_served_from_disk_cache() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {"content": [{"type": "text", "text": "persisted"}]}}"#)
+            .expect(1)
+            .create();
+        let cache_home = tempfile::tempdir().expect("temp dir");
+
+        for _ in 0..2 {
+            let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+            cmd.env("XDG_CACHE_HOME", cache_home.path())
+                .env_remove("BUN_DOCS_MCP_CACHE_DIR")
+                .args(["--upstream-url", &server.url()])
+                .args(["--search", "Bun.serve", "--format", "text"])
+                .timeout(Duration::from_secs(10_u64))
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("persisted"));
+        }
+
+        mock.assert();
+        drop(server);
+        assert!(cache_home.path().join("bun-docs-mcp-proxy").is_dir());
+    }
+
+    #[test]
+    fn offline_search_without_cache_fails_without_network() {
+        let mut server = mockito::Server::new();
+        let mock = server.mock("POST", "/").expect(0).create();
+        let cache_dir = tempfile::tempdir().expect("temp dir");
+
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.args(["--upstream-url", &server.url(), "--offline"])
+            .arg("--cache-dir")
+            .arg(cache_dir.path())
+            .args(["--search", "Bun.serve"])
+            .timeout(Duration::from_secs(10_u64))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Offline mode"));
+
+        mock.assert();
+        drop(server);
+    }
+
+    #[test]
+    fn offline_search_serves_persisted_result_as_stale() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": {"content": [{"type": "text", "text": "remembered"}]}}"#)
+            .expect(1)
+            .create();
+        let cache_dir = tempfile::tempdir().expect("temp dir");
+
+        for offline in [false, true] {
+            let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+            cmd.args(["--upstream-url", &server.url(), "--disk-cache-ttl", "0"])
+                .arg("--cache-dir")
+                .arg(cache_dir.path())
+                .args(["--search", "Bun.serve", "--format", "text"]);
+            if offline {
+                cmd.arg("--offline");
+            }
+            let assert = cmd
+                .timeout(Duration::from_secs(10_u64))
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("remembered"));
+            if offline {
+                assert.stdout(predicate::str::contains("Stale result"));
+            }
+        }
+
+        mock.assert();
+        drop(server);
+    }
+
+    #[test]
+    fn max_retries_flag_overrides_environment() {
+        let mut server = mockito::Server::new();
+        let mock = server.mock("POST", "/").with_status(503).expect(1).create();
+
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.env("BUN_DOCS_MCP_MAX_RETRIES", "4")
+            .args(["--upstream-url", &server.url(), "--max-retries", "1"])
+            .args(["--search", "Bun.serve"])
+            .timeout(Duration::from_secs(10_u64))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("503"));
+
+        mock.assert();
+        drop(server);
+    }
+
+    #[test]
+    fn invalid_upstream_url_rejected() {
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.args(["--upstream-url", "not a url", "--search", "test"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Invalid base URL"));
+    }
+
+    #[test]
+    fn timeout_rejects_zero() {
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.args(["--timeout", "0"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid value"));
+    }
+
+    #[test]
+    fn invalid_framing_value_rejected() {
+        let mut cmd = cargo_bin_cmd!("bun-docs-mcp-proxy");
+        cmd.args(["--framing", "xml"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid value"));
+    }
 }